@@ -0,0 +1,236 @@
+use crate::database::{JsonStore, Store};
+use crate::error::AppError;
+use crate::models::{BackupInfo, BackupReason};
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, instrument, warn};
+
+const BACKUPS_DIR_NAME: &str = "backups";
+const BACKUP_PREFIX: &str = "db-";
+const BACKUP_EXTENSION: &str = "json";
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%S%6fZ";
+
+/// Rotation keeps at most this many snapshots...
+const MAX_BACKUPS: usize = 20;
+/// ...and drops those older than this, except the newest one
+const MAX_BACKUP_AGE_DAYS: i64 = 30;
+
+impl BackupReason {
+    fn slug(self) -> &'static str {
+        match self {
+            BackupReason::Startup => "startup",
+            BackupReason::BeforeImport => "before-import",
+            BackupReason::BeforeRepair => "before-repair",
+        }
+    }
+
+    fn from_slug(slug: &str) -> Option<Self> {
+        match slug {
+            "startup" => Some(BackupReason::Startup),
+            "before-import" => Some(BackupReason::BeforeImport),
+            "before-repair" => Some(BackupReason::BeforeRepair),
+            _ => None,
+        }
+    }
+}
+
+/// Snapshots live next to the database, so each workspace has its own
+pub fn backups_dir(store: &dyn Store) -> PathBuf {
+    store
+        .path()
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join(BACKUPS_DIR_NAME)
+}
+
+/// Parse `db-<timestamp>-<reason>.json`; anything else in the directory is ignored
+fn parse_backup_file(path: &Path) -> Option<BackupInfo> {
+    let file_name = path.file_name()?.to_str()?;
+    let stem = file_name
+        .strip_prefix(BACKUP_PREFIX)?
+        .strip_suffix(BACKUP_EXTENSION)?
+        .strip_suffix('.')?;
+    let (timestamp, slug) = stem.split_once('-')?;
+
+    let created_at = NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT)
+        .ok()?
+        .and_utc();
+    let size_bytes = fs::metadata(path).ok()?.len();
+
+    Some(BackupInfo {
+        file_name: file_name.to_string(),
+        path: path.to_string_lossy().to_string(),
+        reason: BackupReason::from_slug(slug)?,
+        created_at,
+        size_bytes,
+    })
+}
+
+/// Snapshots of the store's database, newest first
+#[instrument(skip(store))]
+pub fn list_backups(store: &dyn Store) -> Result<Vec<BackupInfo>, Box<dyn std::error::Error>> {
+    let dir = backups_dir(store);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(&dir)? {
+        if let Some(backup) = parse_backup_file(&entry?.path()) {
+            backups.push(backup);
+        }
+    }
+    backups.sort_by_key(|backup| std::cmp::Reverse(backup.created_at));
+    Ok(backups)
+}
+
+/// Write a snapshot of the database and rotate old ones.
+///
+/// When the newest snapshot already holds the same data it is returned instead
+/// of writing another, so repeated launches don't fill the rotation. Snapshots
+/// of an encrypted database are encrypted with the same key.
+#[instrument(skip(store))]
+pub fn create_backup(
+    store: &dyn Store,
+    reason: BackupReason,
+) -> Result<BackupInfo, Box<dyn std::error::Error>> {
+    let json_data = store.export_to_json()?;
+
+    if let Some(latest) = list_backups(store)?.into_iter().next() {
+        let latest_data = fs::read_to_string(&latest.path)
+            .map_err(Into::into)
+            .and_then(|contents| store.open_from_storage(contents));
+        if latest_data.is_ok_and(|latest_data| latest_data == json_data) {
+            debug!("Database unchanged since backup {}, skipping", latest.file_name);
+            return Ok(latest);
+        }
+    }
+
+    let dir = backups_dir(store);
+    fs::create_dir_all(&dir)?;
+
+    let now = Utc::now();
+    let path = dir.join(format!(
+        "{}{}-{}.{}",
+        BACKUP_PREFIX,
+        now.format(TIMESTAMP_FORMAT),
+        reason.slug(),
+        BACKUP_EXTENSION
+    ));
+
+    // Same write-then-rename as the database so a crash can't leave half a backup
+    let temp_path = path.with_extension("json.tmp");
+    fs::write(&temp_path, store.seal_for_storage(json_data)?)?;
+    fs::rename(&temp_path, &path)?;
+
+    let backup = parse_backup_file(&path).ok_or("Failed to read back the new backup")?;
+    info!("Created {:?} backup {}", reason, backup.file_name);
+
+    rotate_backups(store, now)?;
+    Ok(backup)
+}
+
+/// Drop snapshots beyond `MAX_BACKUPS` or older than `MAX_BACKUP_AGE_DAYS`
+fn rotate_backups(store: &dyn Store, now: DateTime<Utc>) -> Result<usize, Box<dyn std::error::Error>> {
+    let cutoff = now - Duration::days(MAX_BACKUP_AGE_DAYS);
+    let mut removed = 0;
+
+    for (index, backup) in list_backups(store)?.iter().enumerate() {
+        if index == 0 || (index < MAX_BACKUPS && backup.created_at >= cutoff) {
+            continue;
+        }
+
+        match fs::remove_file(&backup.path) {
+            Ok(()) => removed += 1,
+            Err(e) => warn!("Failed to remove old backup {}: {}", backup.file_name, e),
+        }
+    }
+
+    if removed > 0 {
+        info!("Rotated out {} old backups", removed);
+    }
+    Ok(removed)
+}
+
+/// Read a snapshot by file name, checking it parses as a database before it is used
+#[instrument(skip(store))]
+pub fn read_backup(store: &dyn Store, file_name: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let backup = list_backups(store)?
+        .into_iter()
+        .find(|backup| backup.file_name == file_name)
+        .ok_or_else(|| AppError::not_found(format!("Backup '{}' not found", file_name)))?;
+
+    let json_data = store.open_from_storage(fs::read_to_string(&backup.path)?)?;
+    JsonStore::parse_database(&json_data)?;
+    Ok(json_data)
+}
+
+/// Decrypted contents of every snapshot the store can open, so they can be
+/// rewritten with `reseal_backups` after its key changes
+#[instrument(skip(store))]
+pub fn open_all_backups(store: &dyn Store) -> Result<Vec<(BackupInfo, String)>, Box<dyn std::error::Error>> {
+    let mut opened = Vec::new();
+    for backup in list_backups(store)? {
+        match fs::read_to_string(&backup.path)
+            .map_err(Into::into)
+            .and_then(|contents| store.open_from_storage(contents))
+        {
+            Ok(json_data) => opened.push((backup, json_data)),
+            Err(e) => warn!("Leaving backup {} as is, it can't be opened: {}", backup.file_name, e),
+        }
+    }
+    Ok(opened)
+}
+
+/// Write snapshots read by `open_all_backups` back under the store's current key
+#[instrument(skip(store, backups))]
+pub fn reseal_backups(
+    store: &dyn Store,
+    backups: Vec<(BackupInfo, String)>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let count = backups.len();
+    for (backup, json_data) in backups {
+        let temp_path = Path::new(&backup.path).with_extension("json.tmp");
+        fs::write(&temp_path, store.seal_for_storage(json_data)?)?;
+        fs::rename(&temp_path, &backup.path)?;
+    }
+    info!("Rewrote {} backups under the new key", count);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Store;
+    use crate::test_support::{json_store as temp_store, sample_record};
+
+    fn insert(store: &JsonStore, name: &str) {
+        store.insert(sample_record(name, &[])).unwrap();
+    }
+
+    #[test]
+    fn test_backups_skip_unchanged_and_rotate() {
+        let store = temp_store("rotate");
+        insert(&store, "first");
+
+        let backup = create_backup(&store, BackupReason::Startup).unwrap();
+        assert_eq!(backup.reason, BackupReason::Startup);
+        let unchanged = create_backup(&store, BackupReason::Startup).unwrap();
+        assert_eq!(unchanged.file_name, backup.file_name);
+
+        for i in 0..MAX_BACKUPS + 2 {
+            insert(&store, &format!("collection {}", i));
+            create_backup(&store, BackupReason::BeforeImport).unwrap();
+        }
+
+        let backups = list_backups(&store).unwrap();
+        assert_eq!(backups.len(), MAX_BACKUPS);
+        assert!(backups.iter().all(|b| b.file_name != backup.file_name));
+
+        let json_data = read_backup(&store, &backups[0].file_name).unwrap();
+        let (database, _) = JsonStore::parse_database(&json_data).unwrap();
+        assert_eq!(database.data.len(), MAX_BACKUPS + 3);
+        assert!(read_backup(&store, "../db.json").is_err());
+    }
+}
@@ -0,0 +1,468 @@
+use crate::importers::{Importer, ParsedImport};
+use crate::integrity::is_openable_url;
+use crate::models::{BrowserProfile, CollectionConfig, CollectionRecord, SiteEntry};
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
+use std::fmt::Write;
+use tracing::{debug, instrument, warn};
+
+/// Name used for bookmarks outside any folder when the file has no `<H1>`
+const DEFAULT_ROOT_NAME: &str = "Bookmarks";
+/// Prefix of the comment carrying a collection's config after its folder heading
+const CONFIG_COMMENT: &str = "restore-sites:config";
+
+/// A folder from the file; index 0 is the root holding top-level bookmarks
+struct Folder {
+    path: Vec<String>,
+    parent: Option<usize>,
+    add_date: Option<DateTime<Utc>>,
+    last_modified: Option<DateTime<Utc>>,
+    config: Option<CollectionConfig>,
+    sites: Vec<(SiteEntry, Option<DateTime<Utc>>)>,
+}
+
+/// Netscape `bookmarks.html`, see `parse_bookmarks_html`
+pub struct BookmarksHtml {
+    pub flatten: bool,
+}
+
+impl Importer for BookmarksHtml {
+    fn name(&self) -> &'static str {
+        "bookmarks.html"
+    }
+
+    fn parse(&self, contents: &str) -> Result<ParsedImport, Box<dyn std::error::Error>> {
+        Ok(parse_bookmarks_html(contents, self.flatten))
+    }
+}
+
+/// ADD_DATE is in seconds, but some exporters write milli- or microseconds
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    let value: i64 = value.trim().parse().ok()?;
+    match value {
+        v if v > 100_000_000_000_000 => DateTime::from_timestamp_micros(v),
+        v if v > 100_000_000_000 => DateTime::from_timestamp_millis(v),
+        v => DateTime::from_timestamp(v, 0),
+    }
+}
+
+/// Attributes of a start tag such as `A HREF="..." ADD_DATE="1700000000"`, keys uppercased
+fn parse_attributes(tag: &str) -> Vec<(String, String)> {
+    let mut attributes = Vec::new();
+    // Skip the tag name
+    let mut rest = tag.trim_start().trim_start_matches(|c: char| !c.is_whitespace());
+
+    loop {
+        rest = rest.trim_start();
+        let name_len = rest
+            .find(|c: char| c == '=' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        if name_len == 0 {
+            break;
+        }
+        let name = rest[..name_len].to_ascii_uppercase();
+        rest = rest[name_len..].trim_start();
+
+        let Some(after_equals) = rest.strip_prefix('=') else {
+            attributes.push((name, String::new()));
+            continue;
+        };
+        let after_equals = after_equals.trim_start();
+        let (value, remainder) = match after_equals.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let body = &after_equals[1..];
+                let end = body.find(quote).unwrap_or(body.len());
+                (&body[..end], body.get(end + 1..).unwrap_or_default())
+            }
+            _ => {
+                let end = after_equals
+                    .find(char::is_whitespace)
+                    .unwrap_or(after_equals.len());
+                (&after_equals[..end], &after_equals[end..])
+            }
+        };
+        attributes.push((name, html_escape::decode_html_entities(value).into_owned()));
+        rest = remainder;
+    }
+
+    attributes
+}
+
+fn attribute<'a>(attributes: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    attributes
+        .iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.as_str())
+}
+
+/// Walks the file tag by tag. The format is not well-formed HTML (`<DT>` and
+/// `<p>` are never closed), so only the tags that carry structure are read.
+struct Scanner<'a> {
+    html: &'a str,
+    lower: String,
+    position: usize,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(html: &'a str) -> Self {
+        Self {
+            html,
+            lower: html.to_ascii_lowercase(),
+            position: 0,
+        }
+    }
+
+    /// Next start or end tag, returning its name (uppercased) and full contents.
+    /// Comments are returned as `!--` with the text between the markers.
+    fn next_tag(&mut self) -> Option<(String, &'a str)> {
+        let start = self.position + self.html[self.position..].find('<')?;
+        if self.html[start..].starts_with("<!--") {
+            let end = self.html[start..].find("-->").map_or(self.html.len(), |i| start + i);
+            self.position = (end + 3).min(self.html.len());
+            return Some(("!--".to_string(), &self.html[start + 4..end.max(start + 4)]));
+        }
+
+        let end = self.html[start..].find('>').map_or(self.html.len(), |i| start + i);
+        self.position = (end + 1).min(self.html.len());
+        let tag = &self.html[start + 1..end];
+        let name = tag
+            .split(|c: char| c.is_whitespace())
+            .next()
+            .unwrap_or_default()
+            .to_ascii_uppercase();
+        Some((name, tag))
+    }
+
+    /// Text up to the closing tag `name`, with entities decoded
+    fn text_until_close(&mut self, name: &str) -> String {
+        let close = format!("</{}", name.to_ascii_lowercase());
+        let end = self.lower[self.position..]
+            .find(&close)
+            .map_or(self.html.len(), |i| self.position + i);
+        let text = &self.html[self.position..end];
+        self.position = end;
+        html_escape::decode_html_entities(text.trim()).into_owned()
+    }
+}
+
+/// Parse a Netscape `bookmarks.html`, as exported by every major browser.
+///
+/// Each folder holding bookmarks becomes a collection named by its path
+/// ("Bar / Work"); with `flatten` subfolders are folded into their top-level
+/// folder instead. Bookmarks outside any folder are collected under the
+/// file's `<H1>` title; `javascript:` and `place:` bookmarks are skipped.
+/// A collection's `created_at` is the folder's ADD_DATE, or that of its
+/// oldest bookmark. Folders written by `export_bookmarks_html` get their
+/// config back from the comment that follows the heading.
+#[instrument(skip(html), fields(html_length = html.len()))]
+pub fn parse_bookmarks_html(html: &str, flatten: bool) -> ParsedImport {
+    let mut folders = vec![Folder {
+        path: Vec::new(),
+        parent: None,
+        add_date: None,
+        last_modified: None,
+        config: None,
+        sites: Vec::new(),
+    }];
+    let mut root_name = DEFAULT_ROOT_NAME.to_string();
+    // Folder each open <DL> belongs to; a <DL> without a heading reuses the current one
+    let mut stack = vec![0];
+    let mut pending_folder = None;
+    let mut skipped_sites = 0;
+    let mut scanner = Scanner::new(html);
+
+    while let Some((name, tag)) = scanner.next_tag() {
+        let current = *stack.last().unwrap_or(&0);
+        match name.as_str() {
+            "H1" => {
+                let title = scanner.text_until_close("H1");
+                if !title.is_empty() {
+                    root_name = title;
+                }
+            }
+            "H3" => {
+                let attributes = parse_attributes(tag);
+                let mut path = folders[current].path.clone();
+                path.push(scanner.text_until_close("H3"));
+                folders.push(Folder {
+                    path,
+                    parent: Some(current),
+                    add_date: attribute(&attributes, "ADD_DATE").and_then(parse_timestamp),
+                    last_modified: attribute(&attributes, "LAST_MODIFIED").and_then(parse_timestamp),
+                    config: None,
+                    sites: Vec::new(),
+                });
+                pending_folder = Some(folders.len() - 1);
+            }
+            "!--" => {
+                let Some(json) = tag.trim().strip_prefix(CONFIG_COMMENT) else {
+                    continue;
+                };
+                // The comment sits between a folder's heading and its <DL>
+                let Some(folder) = pending_folder else {
+                    continue;
+                };
+                match serde_json::from_str(json.trim()) {
+                    Ok(config) => folders[folder].config = Some(config),
+                    Err(e) => warn!("Ignoring unreadable config for '{}': {}", folders[folder].path.join(" / "), e),
+                }
+            }
+            "DL" => stack.push(pending_folder.take().unwrap_or(current)),
+            "/DL" if stack.len() > 1 => {
+                stack.pop();
+            }
+            "A" => {
+                let attributes = parse_attributes(tag);
+                let title = scanner.text_until_close("A");
+                let url = attribute(&attributes, "HREF").unwrap_or_default().trim().to_string();
+                if !is_openable_url(&url) {
+                    debug!("Skipping bookmark '{}' with URL '{}'", title, url);
+                    skipped_sites += 1;
+                    continue;
+                }
+
+                let site = SiteEntry {
+                    title: if title.is_empty() { url.clone() } else { title },
+                    url,
+                    canonical_url: None,
+                };
+                let add_date = attribute(&attributes, "ADD_DATE").and_then(parse_timestamp);
+                folders[current].sites.push((site, add_date));
+            }
+            _ => {}
+        }
+    }
+
+    let collections = collect(folders, &root_name, flatten);
+    debug!(
+        "Parsed {} collections from bookmarks, skipped {} bookmarks",
+        collections.len(),
+        skipped_sites
+    );
+    ParsedImport {
+        collections,
+        skipped_sites,
+        unnamed: HashSet::new(),
+    }
+}
+
+/// Turn folders into collections, folding subfolders into their top-level
+/// folder when flattening
+fn collect(mut folders: Vec<Folder>, root_name: &str, flatten: bool) -> Vec<CollectionRecord> {
+    if flatten {
+        // Children always come after their parent, so walk backwards
+        for index in (1..folders.len()).rev() {
+            if let Some(parent) = folders[index].parent.filter(|parent| *parent != 0) {
+                let sites = std::mem::take(&mut folders[index].sites);
+                // Keep document order: a subfolder's bookmarks follow its parent's
+                folders[parent].sites.extend(sites);
+            }
+        }
+    }
+
+    let now = Utc::now();
+    folders
+        .into_iter()
+        .filter(|folder| !folder.sites.is_empty())
+        .map(|folder| {
+            let oldest = folder.sites.iter().filter_map(|(_, date)| *date).min();
+            let newest = folder.sites.iter().filter_map(|(_, date)| *date).max();
+            let created_at = folder.add_date.or(oldest).unwrap_or(now);
+            let name = if folder.path.is_empty() {
+                root_name.to_string()
+            } else {
+                folder.path.join(" / ")
+            };
+
+            CollectionRecord {
+                id: 0, // Assigned on import
+                name,
+                sites: folder.sites.into_iter().map(|(site, _)| site).collect(),
+                config: folder.config.unwrap_or_default(),
+                created_at,
+                updated_at: folder.last_modified.or(newest).unwrap_or(created_at),
+            }
+        })
+        .collect()
+}
+
+/// A collection's config as embedded in an export. A referenced profile's
+/// browser, mode and path are copied into the fallback fields, so the config
+/// still resolves where that profile doesn't exist.
+fn portable_config(config: &CollectionConfig, profiles: &[BrowserProfile]) -> CollectionConfig {
+    let mut config = config.clone();
+    let profile = config
+        .browser_profile_id
+        .as_ref()
+        .and_then(|id| profiles.iter().find(|profile| &profile.id == id));
+    if let Some(profile) = profile {
+        config.browser.get_or_insert_with(|| profile.browser.clone());
+        config.mode.get_or_insert_with(|| profile.mode.clone());
+        if config.custom_path.is_none() {
+            config.custom_path = profile.custom_path.clone();
+        }
+    }
+    config
+}
+
+/// Write collections as a Netscape `bookmarks.html`, one folder per collection.
+///
+/// `created_at` and `updated_at` become the folder's ADD_DATE and
+/// LAST_MODIFIED. Each folder's config is written in a comment browsers ignore
+/// and `parse_bookmarks_html` reads back.
+#[instrument(skip(records, profiles), fields(collection_count = records.len()))]
+pub fn export_bookmarks_html(records: &[CollectionRecord], profiles: &[BrowserProfile]) -> String {
+    let mut html = String::from(
+        "<!DOCTYPE NETSCAPE-Bookmark-file-1>\n\
+         <!-- This is an automatically generated file.\n     \
+         It will be read and overwritten.\n     \
+         DO NOT EDIT! -->\n\
+         <META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=UTF-8\">\n\
+         <TITLE>Bookmarks</TITLE>\n\
+         <H1>Bookmarks</H1>\n\
+         <DL><p>\n",
+    );
+
+    for record in records {
+        let add_date = record.created_at.timestamp();
+        let config = serde_json::to_string(&portable_config(&record.config, profiles))
+            .unwrap_or_default()
+            // "--" may only appear in JSON strings, where an escape keeps the comment well-formed
+            .replace("--", "-\\u002d");
+
+        let _ = writeln!(
+            html,
+            "    <DT><H3 ADD_DATE=\"{}\" LAST_MODIFIED=\"{}\">{}</H3>",
+            add_date,
+            record.updated_at.timestamp(),
+            html_escape::encode_text(&record.name)
+        );
+        let _ = writeln!(html, "    <!-- {} {} -->", CONFIG_COMMENT, config);
+        html.push_str("    <DL><p>\n");
+        for site in &record.sites {
+            let _ = writeln!(
+                html,
+                "        <DT><A HREF=\"{}\" ADD_DATE=\"{}\">{}</A>",
+                html_escape::encode_double_quoted_attribute(&site.url),
+                add_date,
+                html_escape::encode_text(&site.title)
+            );
+        }
+        html.push_str("    </DL><p>\n");
+    }
+
+    html.push_str("</DL><p>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Browser, BrowserMode};
+
+    const EXPORT: &str = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<!-- This is an automatically generated file. -->
+<META HTTP-EQUIV="Content-Type" CONTENT="text/html; charset=UTF-8">
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks Menu</H1>
+<DL><p>
+    <DT><A HREF="https://example.com/" ADD_DATE="1700000000">Loose</A>
+    <DT><H3 ADD_DATE="1600000000" LAST_MODIFIED="1650000000" PERSONAL_TOOLBAR_FOLDER="true">Toolbar</H3>
+    <DL><p>
+        <DT><A HREF="https://docs.rs/?a=1&amp;b=2" ADD_DATE="1610000000">Docs &amp; crates</A>
+        <DT><A HREF="javascript:alert(1)">Bookmarklet</A>
+        <DT><h3>Work</h3>
+        <dl><p>
+            <dt><a href='https://github.com' add_date=1620000000000>GitHub</a>
+        </dl><p>
+    </DL><p>
+    <DT><H3>Empty</H3>
+    <DL><p>
+    </DL><p>
+</DL>
+"#;
+
+    fn names_and_urls(collections: &[CollectionRecord]) -> Vec<(String, Vec<String>)> {
+        collections
+            .iter()
+            .map(|c| (c.name.clone(), c.sites.iter().map(|s| s.url.clone()).collect()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_bookmarks_html() {
+        let parsed = parse_bookmarks_html(EXPORT, false);
+        assert_eq!(parsed.skipped_sites, 1);
+        assert_eq!(
+            names_and_urls(&parsed.collections),
+            vec![
+                ("Bookmarks Menu".to_string(), vec!["https://example.com/".to_string()]),
+                ("Toolbar".to_string(), vec!["https://docs.rs/?a=1&b=2".to_string()]),
+                ("Toolbar / Work".to_string(), vec!["https://github.com".to_string()]),
+            ]
+        );
+
+        let toolbar = &parsed.collections[1];
+        assert_eq!(toolbar.sites[0].title, "Docs & crates");
+        assert_eq!(toolbar.created_at.timestamp(), 1_600_000_000);
+        assert_eq!(toolbar.updated_at.timestamp(), 1_650_000_000);
+        // Millisecond ADD_DATE on the only bookmark of a folder without one
+        assert_eq!(parsed.collections[2].created_at.timestamp(), 1_620_000_000);
+
+        let flattened = parse_bookmarks_html(EXPORT, true);
+        assert_eq!(
+            names_and_urls(&flattened.collections)[1],
+            (
+                "Toolbar".to_string(),
+                vec!["https://docs.rs/?a=1&b=2".to_string(), "https://github.com".to_string()]
+            )
+        );
+        assert_eq!(flattened.collections.len(), 2);
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        let now = Utc::now();
+        let profile = BrowserProfile {
+            id: "work-firefox".to_string(),
+            name: "Work".to_string(),
+            browser: Browser::Firefox,
+            mode: BrowserMode::Private,
+            custom_path: Some("/opt/firefox--esr/firefox".to_string()),
+            is_default: false,
+            is_detected: true,
+            created_at: now,
+            updated_at: now,
+        };
+        let record = CollectionRecord {
+            id: 7,
+            name: "Research <& notes>".to_string(),
+            sites: vec![SiteEntry {
+                title: "Search \"quoted\"".to_string(),
+                url: "https://example.com/?q=a&b=\"c\"".to_string(),
+                canonical_url: Some("https://example.com/?b=%22c%22&q=a".to_string()),
+            }],
+            config: CollectionConfig {
+                browser_profile_id: Some(profile.id.clone()),
+                ..CollectionConfig::default()
+            },
+            created_at: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+            updated_at: DateTime::from_timestamp(1_710_000_000, 0).unwrap(),
+        };
+
+        let html = export_bookmarks_html(std::slice::from_ref(&record), &[profile]);
+        let parsed = parse_bookmarks_html(&html, false);
+        assert_eq!(parsed.collections.len(), 1);
+
+        let imported = &parsed.collections[0];
+        assert_eq!(imported.name, record.name);
+        assert_eq!(imported.sites[0].title, record.sites[0].title);
+        assert_eq!(imported.sites[0].url, record.sites[0].url);
+        assert_eq!(imported.created_at, record.created_at);
+        assert_eq!(imported.updated_at, record.updated_at);
+        assert_eq!(imported.config.browser_profile_id.as_deref(), Some("work-firefox"));
+        assert_eq!(imported.config.browser, Some(Browser::Firefox));
+        assert_eq!(imported.config.mode, Some(BrowserMode::Private));
+        assert_eq!(imported.config.custom_path.as_deref(), Some("/opt/firefox--esr/firefox"));
+    }
+}
@@ -0,0 +1,583 @@
+use crate::error::AppError;
+use crate::importers::ParsedImport;
+use crate::integrity::is_openable_url;
+use crate::models::{Browser, BrowserDataFilter, BrowserDataSource, CollectionConfig, CollectionRecord, SiteEntry};
+use chrono::{DateTime, Local, Utc};
+use rusqlite::{params, Connection};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use tracing::{debug, instrument};
+
+/// History is imported most recent first, up to this many sites
+pub const MAX_HISTORY_SITES: usize = 500;
+
+/// Chromium stores times as microseconds since 1601-01-01
+const WEBKIT_EPOCH_OFFSET_MICROS: i64 = 11_644_473_600_000_000;
+
+/// Firefox root folders, by guid; their stored titles are internal names
+const FIREFOX_ROOTS: &[(&str, &str)] = &[
+    ("menu________", "Bookmarks Menu"),
+    ("toolbar_____", "Bookmarks Toolbar"),
+    ("unfiled_____", "Other Bookmarks"),
+    ("mobile______", "Mobile Bookmarks"),
+];
+const FIREFOX_TAGS_ROOT: &str = "tags________";
+
+/// File holding each source in a profile directory
+fn source_file(browser: &Browser, source: BrowserDataSource) -> &'static str {
+    match (browser, source) {
+        (Browser::Firefox, _) => "places.sqlite",
+        (_, BrowserDataSource::Bookmarks) => "Bookmarks",
+        (_, BrowserDataSource::History) => "History",
+    }
+}
+
+/// Sources present in a profile directory
+pub fn available_sources(browser: &Browser, profile_dir: &Path) -> Vec<BrowserDataSource> {
+    [BrowserDataSource::Bookmarks, BrowserDataSource::History]
+        .into_iter()
+        .filter(|source| profile_dir.join(source_file(browser, *source)).is_file())
+        .collect()
+}
+
+fn from_webkit_time(micros: i64) -> Option<DateTime<Utc>> {
+    (micros > 0)
+        .then(|| DateTime::from_timestamp_micros(micros - WEBKIT_EPOCH_OFFSET_MICROS))
+        .flatten()
+}
+
+fn to_webkit_time(time: DateTime<Utc>) -> i64 {
+    time.timestamp_micros() + WEBKIT_EPOCH_OFFSET_MICROS
+}
+
+fn browser_name(browser: &Browser) -> String {
+    match browser {
+        Browser::Custom(name) => name.clone(),
+        browser => format!("{:?}", browser),
+    }
+}
+
+impl BrowserDataFilter {
+    fn matches_folder(&self, path: &str) -> bool {
+        let Some(folder) = self.folder.as_deref().map(str::trim).filter(|f| !f.is_empty()) else {
+            return true;
+        };
+        let path = path.to_lowercase();
+        let folder = folder.to_lowercase();
+        path == folder || path.starts_with(&format!("{} / ", folder))
+    }
+
+    /// Dates and visit counts are only checked when the source has them
+    fn matches(&self, url: &str, time: Option<DateTime<Utc>>, visit_count: Option<i64>) -> bool {
+        if let Some(time) = time {
+            if self.since.is_some_and(|since| time < since) || self.until.is_some_and(|until| time > until) {
+                return false;
+            }
+        }
+        if let (Some(min), Some(count)) = (self.min_visit_count, visit_count) {
+            if count < i64::from(min) {
+                return false;
+            }
+        }
+        match self.domain.as_deref().map(|d| d.trim().trim_start_matches("www.").to_lowercase()) {
+            Some(domain) if !domain.is_empty() => url::Url::parse(url)
+                .ok()
+                .and_then(|parsed| parsed.host_str().map(str::to_lowercase))
+                .is_some_and(|host| host == domain || host.ends_with(&format!(".{}", domain))),
+            _ => true,
+        }
+    }
+}
+
+/// Run `read` against a copy of a browser database, so a running browser's
+/// lock doesn't get in the way. The copy is opened query-only and removed after.
+fn with_copy<T>(
+    path: &Path,
+    read: impl FnOnce(&Connection) -> Result<T, Box<dyn std::error::Error>>,
+) -> Result<T, Box<dyn std::error::Error>> {
+    let dir = std::env::temp_dir().join(format!(
+        "restore-sites-browser-data-{}-{}",
+        std::process::id(),
+        Utc::now().timestamp_nanos_opt().unwrap_or_default()
+    ));
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| AppError::validation("Invalid database path"))?
+        .to_string_lossy()
+        .to_string();
+
+    let result = (|| {
+        fs::create_dir_all(&dir)?;
+        fs::copy(path, dir.join(&file_name))?;
+        // Recent changes may still be in the write-ahead log
+        let wal = path.with_file_name(format!("{}-wal", file_name));
+        if wal.is_file() {
+            fs::copy(&wal, dir.join(format!("{}-wal", file_name)))?;
+        }
+
+        let conn = Connection::open(dir.join(&file_name))?;
+        conn.pragma_update(None, "query_only", true)?;
+        read(&conn)
+    })();
+
+    let _ = fs::remove_dir_all(&dir);
+    result
+}
+
+/// Visited URLs and their titles, most recent first, with at most
+/// `MAX_HISTORY_SITES` openable ones
+type HistoryRows = Vec<(String, Option<String>)>;
+
+/// Bookmarks grouped by folder path, in the order folders are first seen
+#[derive(Default)]
+struct Folders {
+    order: Vec<String>,
+    folders: HashMap<String, (Vec<SiteEntry>, Option<DateTime<Utc>>)>,
+    skipped_sites: usize,
+}
+
+impl Folders {
+    fn add(&mut self, path: &str, url: String, title: Option<String>, added: Option<DateTime<Utc>>) {
+        if !is_openable_url(&url) {
+            self.skipped_sites += 1;
+            return;
+        }
+        if !self.folders.contains_key(path) {
+            self.order.push(path.to_string());
+        }
+        let (sites, oldest) = self.folders.entry(path.to_string()).or_default();
+        sites.push(SiteEntry {
+            title: title.filter(|title| !title.trim().is_empty()).unwrap_or_else(|| url.clone()),
+            url,
+            canonical_url: None,
+        });
+        *oldest = match (*oldest, added) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+    }
+
+    fn finish(mut self, browser: &Browser) -> ParsedImport {
+        let now = Utc::now();
+        let collections = self
+            .order
+            .iter()
+            .filter_map(|path| self.folders.remove(path).map(|folder| (path, folder)))
+            .map(|(path, (sites, oldest))| CollectionRecord {
+                id: 0, // Assigned on import
+                name: path.clone(),
+                sites,
+                config: CollectionConfig {
+                    browser: Some(browser.clone()),
+                    ..CollectionConfig::default()
+                },
+                created_at: oldest.unwrap_or(now),
+                updated_at: now,
+            })
+            .collect();
+        ParsedImport {
+            collections,
+            skipped_sites: self.skipped_sites,
+            unnamed: HashSet::new(),
+        }
+    }
+}
+
+/// One collection of the most recently visited sites
+fn history_collection(browser: &Browser, rows: HistoryRows) -> ParsedImport {
+    let mut folders = Folders::default();
+    let name = format!(
+        "{} history ({})",
+        browser_name(browser),
+        Local::now().format("%Y-%m-%d %H:%M")
+    );
+    for (url, title) in rows {
+        folders.add(&name, url, title, None);
+    }
+    let mut parsed = folders.finish(browser);
+    parsed.unnamed.insert(name);
+    parsed
+}
+
+fn firefox_bookmarks(conn: &Connection, filter: &BrowserDataFilter) -> Result<Folders, Box<dyn std::error::Error>> {
+    let mut stmt = conn.prepare("SELECT id, parent, title, guid FROM moz_bookmarks WHERE type = 2")?;
+    let folder_rows: HashMap<i64, (i64, String, String)> = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                (row.get(1)?, row.get::<_, Option<String>>(2)?.unwrap_or_default(), row.get(3)?),
+            ))
+        })?
+        .collect::<Result<_, _>>()?;
+
+    // Path of each folder below the roots; None for the tags tree and the roots' parent
+    let path_of = |mut id: i64| -> Option<String> {
+        let mut names = Vec::new();
+        let mut visited = HashSet::new();
+        while let Some((parent, title, guid)) = folder_rows.get(&id) {
+            // A damaged file can have folders that are their own ancestors
+            if guid == FIREFOX_TAGS_ROOT || !visited.insert(id) {
+                return None;
+            }
+            if let Some((_, name)) = FIREFOX_ROOTS.iter().find(|(root, _)| root == guid) {
+                names.push(name.to_string());
+                break;
+            }
+            names.push(title.clone());
+            id = *parent;
+        }
+        (!names.is_empty()).then(|| names.into_iter().rev().collect::<Vec<_>>().join(" / "))
+    };
+
+    let mut stmt = conn.prepare(
+        "SELECT b.parent, COALESCE(b.title, p.title), p.url, b.dateAdded, p.visit_count
+         FROM moz_bookmarks b JOIN moz_places p ON p.id = b.fk
+         WHERE b.type = 1 ORDER BY b.parent, b.position",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, Option<String>>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, Option<i64>>(3)?,
+            row.get::<_, Option<i64>>(4)?,
+        ))
+    })?;
+
+    let mut folders = Folders::default();
+    let mut paths: HashMap<i64, Option<String>> = HashMap::new();
+    for row in rows {
+        let (parent, title, url, added, visits) = row?;
+        let Some(path) = paths.entry(parent).or_insert_with(|| path_of(parent)).clone() else {
+            continue;
+        };
+        let added = added.and_then(DateTime::from_timestamp_micros);
+        if filter.matches_folder(&path) && filter.matches(&url, added, visits) {
+            folders.add(&path, url, title, added);
+        }
+    }
+    Ok(folders)
+}
+
+/// Keep the rows `filter` matches until `MAX_HISTORY_SITES` of them can be
+/// opened; unopenable ones are kept too so they are counted as skipped
+fn collect_history(
+    rows: impl Iterator<Item = rusqlite::Result<(String, Option<String>, i64, Option<DateTime<Utc>>)>>,
+    filter: &BrowserDataFilter,
+) -> Result<HistoryRows, Box<dyn std::error::Error>> {
+    let mut matching = Vec::new();
+    let mut openable = 0;
+    for row in rows {
+        let (url, title, visits, last_visit) = row?;
+        // Dates and visit counts were narrowed down in SQL, the domain can't be
+        if !filter.matches(&url, last_visit, Some(visits)) {
+            continue;
+        }
+        if is_openable_url(&url) {
+            openable += 1;
+        }
+        matching.push((url, title));
+        if openable == MAX_HISTORY_SITES {
+            break;
+        }
+    }
+    Ok(matching)
+}
+
+fn firefox_history(conn: &Connection, filter: &BrowserDataFilter) -> Result<HistoryRows, Box<dyn std::error::Error>> {
+    let mut stmt = conn.prepare(
+        "SELECT url, title, visit_count, last_visit_date FROM moz_places
+         WHERE hidden = 0 AND last_visit_date IS NOT NULL
+           AND (?1 IS NULL OR last_visit_date >= ?1)
+           AND (?2 IS NULL OR last_visit_date <= ?2)
+           AND visit_count >= ?3
+         ORDER BY last_visit_date DESC",
+    )?;
+    let rows = stmt.query_map(
+        params![
+            filter.since.map(|since| since.timestamp_micros()),
+            filter.until.map(|until| until.timestamp_micros()),
+            filter.min_visit_count.unwrap_or(0)
+        ],
+        |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, i64>(2)?,
+                DateTime::from_timestamp_micros(row.get(3)?),
+            ))
+        },
+    )?;
+    collect_history(rows, filter)
+}
+
+fn chromium_history(conn: &Connection, filter: &BrowserDataFilter) -> Result<HistoryRows, Box<dyn std::error::Error>> {
+    let mut stmt = conn.prepare(
+        "SELECT url, title, visit_count, last_visit_time FROM urls
+         WHERE hidden = 0
+           AND (?1 IS NULL OR last_visit_time >= ?1)
+           AND (?2 IS NULL OR last_visit_time <= ?2)
+           AND visit_count >= ?3
+         ORDER BY last_visit_time DESC",
+    )?;
+    let rows = stmt.query_map(
+        params![
+            filter.since.map(to_webkit_time),
+            filter.until.map(to_webkit_time),
+            filter.min_visit_count.unwrap_or(0)
+        ],
+        |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, i64>(2)?,
+                from_webkit_time(row.get(3)?),
+            ))
+        },
+    )?;
+    collect_history(rows, filter)
+}
+
+#[derive(Deserialize)]
+struct ChromiumBookmarks {
+    roots: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct ChromiumNode {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    name: String,
+    url: Option<String>,
+    date_added: Option<String>,
+    #[serde(default)]
+    children: Vec<ChromiumNode>,
+}
+
+fn chromium_bookmark_folder(node: &ChromiumNode, path: &str, filter: &BrowserDataFilter, folders: &mut Folders) {
+    for child in &node.children {
+        let added = child
+            .date_added
+            .as_deref()
+            .and_then(|micros| micros.parse().ok())
+            .and_then(from_webkit_time);
+        match (child.kind.as_str(), &child.url) {
+            ("url", Some(url)) if filter.matches_folder(path) && filter.matches(url, added, None) => {
+                folders.add(path, url.clone(), Some(child.name.clone()), added);
+            }
+            ("folder", _) => {
+                let child_path = format!("{} / {}", path, child.name);
+                chromium_bookmark_folder(child, &child_path, filter, folders);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn chromium_bookmarks(path: &Path, filter: &BrowserDataFilter) -> Result<Folders, Box<dyn std::error::Error>> {
+    // Written by replacing the file, so it can be read while the browser runs
+    let bookmarks: ChromiumBookmarks = serde_json::from_str(&fs::read_to_string(path)?).map_err(|e| {
+        AppError::validation("Not a Chromium Bookmarks file").with_details(e.to_string())
+    })?;
+
+    let mut folders = Folders::default();
+    // "bookmark_bar", "other" and "synced". serde_json's map is sorted by key
+    // without its preserve_order feature, so roots are read alphabetically
+    for root in bookmarks.roots.values() {
+        let Ok(root) = ChromiumNode::deserialize(root) else {
+            continue; // Older files have a "sync_transaction_version" entry here
+        };
+        chromium_bookmark_folder(&root, &root.name, filter, &mut folders);
+    }
+    Ok(folders)
+}
+
+/// Read bookmarks (one collection per folder) or history (one collection of
+/// the most recent matching sites) from a browser profile directory.
+///
+/// `min_visit_count` applies where the source has visit counts: history and
+/// Firefox bookmarks.
+#[instrument(skip(filter))]
+pub fn read_browser_data(
+    browser: &Browser,
+    profile_dir: &Path,
+    source: BrowserDataSource,
+    filter: &BrowserDataFilter,
+) -> Result<ParsedImport, Box<dyn std::error::Error>> {
+    let path = profile_dir.join(source_file(browser, source));
+    if !path.is_file() {
+        return Err(AppError::not_found(format!("No {} found in {}", path.display(), profile_dir.display())).into());
+    }
+
+    let parsed = match (browser, source) {
+        (Browser::Firefox, BrowserDataSource::Bookmarks) => {
+            with_copy(&path, |conn| firefox_bookmarks(conn, filter))?.finish(browser)
+        }
+        (Browser::Firefox, BrowserDataSource::History) => {
+            history_collection(browser, with_copy(&path, |conn| firefox_history(conn, filter))?)
+        }
+        (_, BrowserDataSource::Bookmarks) => chromium_bookmarks(&path, filter)?.finish(browser),
+        (_, BrowserDataSource::History) => {
+            history_collection(browser, with_copy(&path, |conn| chromium_history(conn, filter))?)
+        }
+    };
+
+    debug!(
+        "Read {} collections from {:?} {:?}, skipped {} entries",
+        parsed.collections.len(),
+        browser,
+        source,
+        parsed.skipped_sites
+    );
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    fn sites(parsed: &ParsedImport) -> Vec<(String, Vec<String>)> {
+        parsed
+            .collections
+            .iter()
+            .map(|c| (c.name.clone(), c.sites.iter().map(|s| s.url.clone()).collect()))
+            .collect()
+    }
+
+    #[test]
+    fn test_firefox_places() {
+        let dir = temp_dir("places");
+        let conn = Connection::open(dir.join("places.sqlite")).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE moz_places (id INTEGER PRIMARY KEY, url TEXT, title TEXT, visit_count INTEGER,
+                last_visit_date INTEGER, hidden INTEGER DEFAULT 0);
+             CREATE TABLE moz_bookmarks (id INTEGER PRIMARY KEY, type INTEGER, fk INTEGER, parent INTEGER,
+                position INTEGER, title TEXT, dateAdded INTEGER, guid TEXT);
+             INSERT INTO moz_places VALUES
+                (1, 'https://docs.rs/', 'Docs', 12, 1700000000000000, 0),
+                (2, 'https://blog.example.com/post', 'Post', 1, 1600000000000000, 0),
+                (3, 'place:tag=rust', NULL, 0, NULL, 0),
+                (4, 'https://example.com/', 'Example', 3, 1710000000000000, 0);
+             INSERT INTO moz_bookmarks VALUES
+                (1, 2, NULL, 0, 0, '', 0, 'root________'),
+                (2, 2, NULL, 1, 0, 'toolbar', 0, 'toolbar_____'),
+                (3, 2, NULL, 1, 1, 'tags', 0, 'tags________'),
+                (4, 2, NULL, 2, 0, 'Work', 0, 'folder000001'),
+                (5, 1, 1, 4, 0, 'Rust docs', 1650000000000000, 'bookmark0001'),
+                (6, 1, 2, 2, 0, NULL, 1660000000000000, 'bookmark0002'),
+                (7, 1, 3, 4, 1, 'Tag query', 0, 'bookmark0003'),
+                (8, 2, NULL, 3, 0, 'rust', 0, 'tag000000001'),
+                (9, 1, 1, 8, 0, NULL, 0, 'bookmark0004'),
+                (10, 2, NULL, 11, 0, 'Loop', 0, 'folder000002'),
+                (11, 2, NULL, 10, 0, 'Back', 0, 'folder000003'),
+                (12, 1, 4, 10, 0, NULL, 0, 'bookmark0005');",
+        )
+        .unwrap();
+        drop(conn);
+
+        let all = BrowserDataFilter::default();
+        let bookmarks = read_browser_data(&Browser::Firefox, &dir, BrowserDataSource::Bookmarks, &all).unwrap();
+        assert_eq!(
+            sites(&bookmarks),
+            vec![
+                ("Bookmarks Toolbar".to_string(), vec!["https://blog.example.com/post".to_string()]),
+                ("Bookmarks Toolbar / Work".to_string(), vec!["https://docs.rs/".to_string()]),
+            ]
+        );
+        assert_eq!(bookmarks.skipped_sites, 1);
+        assert_eq!(bookmarks.collections[1].sites[0].title, "Rust docs");
+        assert_eq!(bookmarks.collections[1].config.browser, Some(Browser::Firefox));
+
+        let work = BrowserDataFilter {
+            folder: Some("bookmarks toolbar / work".to_string()),
+            ..BrowserDataFilter::default()
+        };
+        let bookmarks = read_browser_data(&Browser::Firefox, &dir, BrowserDataSource::Bookmarks, &work).unwrap();
+        assert_eq!(bookmarks.collections.len(), 1);
+
+        let frequent = BrowserDataFilter {
+            domain: Some("www.example.com".to_string()),
+            min_visit_count: Some(2),
+            since: DateTime::from_timestamp(1_650_000_000, 0),
+            ..BrowserDataFilter::default()
+        };
+        let history = read_browser_data(&Browser::Firefox, &dir, BrowserDataSource::History, &frequent).unwrap();
+        assert_eq!(sites(&history)[0].1, vec!["https://example.com/".to_string()]);
+        assert!(history.collections[0].name.starts_with("Firefox history ("));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_chromium_bookmarks_and_history() {
+        let dir = temp_dir("chromium-data");
+        fs::write(
+            dir.join("Bookmarks"),
+            r#"{
+                "checksum": "0",
+                "roots": {
+                    "bookmark_bar": { "type": "folder", "name": "Bookmarks bar", "children": [
+                        { "type": "url", "name": "Docs", "url": "https://docs.rs/", "date_added": "13345000000000000" },
+                        { "type": "folder", "name": "Work", "children": [
+                            { "type": "url", "name": "Mail", "url": "https://mail.example.com/" }
+                        ] }
+                    ] },
+                    "other": { "type": "folder", "name": "Other bookmarks", "children": [] },
+                    "sync_transaction_version": "1"
+                },
+                "version": 1
+            }"#,
+        )
+        .unwrap();
+        let conn = Connection::open(dir.join("History")).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE urls (id INTEGER PRIMARY KEY, url TEXT, title TEXT, visit_count INTEGER,
+                last_visit_time INTEGER, hidden INTEGER DEFAULT 0);
+             INSERT INTO urls VALUES
+                (1, 'https://docs.rs/', 'Docs', 5, 13345000000000000, 0),
+                (2, 'https://crates.io/', 'Crates', 1, 13346000000000000, 0),
+                (3, 'chrome://settings/', 'Settings', 9, 13347000000000000, 0);",
+        )
+        .unwrap();
+        drop(conn);
+
+        assert_eq!(
+            available_sources(&Browser::Chrome, &dir),
+            vec![BrowserDataSource::Bookmarks, BrowserDataSource::History]
+        );
+
+        let all = BrowserDataFilter::default();
+        let bookmarks = read_browser_data(&Browser::Chrome, &dir, BrowserDataSource::Bookmarks, &all).unwrap();
+        assert_eq!(
+            sites(&bookmarks),
+            vec![
+                ("Bookmarks bar".to_string(), vec!["https://docs.rs/".to_string()]),
+                ("Bookmarks bar / Work".to_string(), vec!["https://mail.example.com/".to_string()]),
+            ]
+        );
+        // 13345000000000000 microseconds after 1601 is 2023-11-20
+        assert_eq!(bookmarks.collections[0].created_at.timestamp(), 1_700_526_400);
+
+        let history = read_browser_data(&Browser::Chrome, &dir, BrowserDataSource::History, &all).unwrap();
+        assert_eq!(
+            sites(&history)[0].1,
+            vec!["https://crates.io/".to_string(), "https://docs.rs/".to_string()]
+        );
+        assert_eq!(history.skipped_sites, 1);
+
+        let frequent = BrowserDataFilter {
+            min_visit_count: Some(2),
+            until: DateTime::from_timestamp(1_700_600_000, 0),
+            ..BrowserDataFilter::default()
+        };
+        let history = read_browser_data(&Browser::Chrome, &dir, BrowserDataSource::History, &frequent).unwrap();
+        assert_eq!(sites(&history)[0].1, vec!["https://docs.rs/".to_string()]);
+        assert_eq!(history.skipped_sites, 0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,436 @@
+use crate::error::AppError;
+use crate::integrity::is_openable_url;
+use crate::models::{Browser, BrowserSession, SessionFile, SessionWindow, SiteEntry};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, instrument, warn};
+
+const SNSS_MAGIC: &[u8] = b"SNSS";
+
+// Command ids from Chromium's session_service_commands.cc; anything else is ignored
+const SET_TAB_WINDOW: u8 = 0;
+const SET_TAB_INDEX_IN_WINDOW: u8 = 2;
+const TAB_NAVIGATION_PATH_PRUNED_FROM_BACK: u8 = 5;
+const UPDATE_TAB_NAVIGATION: u8 = 6;
+const SET_SELECTED_NAVIGATION_INDEX: u8 = 7;
+const SET_SELECTED_TAB_IN_INDEX: u8 = 8;
+const SET_WINDOW_TYPE: u8 = 9;
+const TAB_NAVIGATION_PATH_PRUNED_FROM_FRONT: u8 = 11;
+const SET_PINNED_STATE: u8 = 12;
+const TAB_CLOSED: u8 = 16;
+const WINDOW_CLOSED: u8 = 17;
+const TAB_NAVIGATION_PATH_PRUNED: u8 = 24;
+
+/// `SessionWindow::TYPE_NORMAL`; popups and app windows are not imported
+const NORMAL_WINDOW: i32 = 0;
+
+#[derive(Default)]
+struct TabState {
+    window_id: Option<i32>,
+    index_in_window: i32,
+    navigations: BTreeMap<i32, (String, String)>,  // Navigation index -> (url, title)
+    selected_navigation: Option<i32>,
+    pinned: bool,
+}
+
+#[derive(Default)]
+struct WindowState {
+    selected_tab_index: Option<i32>,
+    window_type: i32,
+}
+
+/// Reads a command's payload: fixed structs of 32-bit fields, or a base::Pickle
+/// whose fields are 4-byte aligned after a 4-byte size header
+struct Payload<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Payload<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    fn pickle(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 4 }
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let slice = self.bytes.get(self.position..self.position.checked_add(len)?)?;
+        // Pickle fields are padded to 4 bytes; fixed structs only hold 4-byte fields
+        self.position += len.div_ceil(4) * 4;
+        Some(slice)
+    }
+
+    fn int(&mut self) -> Option<i32> {
+        self.take(4).map(|bytes| i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let len = usize::try_from(self.int()?).ok()?;
+        self.take(len).map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+    }
+
+    fn string16(&mut self) -> Option<String> {
+        let len = usize::try_from(self.int()?).ok()?;
+        let units: Vec<u16> = self
+            .take(len.checked_mul(2)?)?
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .collect();
+        Some(String::from_utf16_lossy(&units))
+    }
+}
+
+/// Session files in the given Chromium profile directories, newest first.
+///
+/// Current builds write `Sessions/Session_<time>`; older ones `Current Session`
+/// and `Last Session` in the profile itself.
+#[instrument(skip(profile_dirs))]
+pub fn locate_session_files(browser: &Browser, profile_dirs: &[PathBuf]) -> Vec<SessionFile> {
+    let mut found = Vec::new();
+
+    for dir in profile_dirs {
+        let mut candidates = vec![dir.join("Current Session"), dir.join("Last Session")];
+        if let Ok(entries) = fs::read_dir(dir.join("Sessions")) {
+            candidates.extend(
+                entries
+                    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                    .filter(|path| {
+                        path.file_name()
+                            .is_some_and(|name| name.to_string_lossy().starts_with("Session_"))
+                    }),
+            );
+        }
+
+        let profile = dir
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        for path in candidates {
+            let Ok(modified) = fs::metadata(&path).and_then(|meta| meta.modified()) else {
+                continue;
+            };
+            found.push(SessionFile {
+                path: path.to_string_lossy().to_string(),
+                browser: browser.clone(),
+                profile: profile.clone(),
+                modified_at: DateTime::<Utc>::from(modified),
+            });
+        }
+    }
+
+    found.sort_by_key(|file| std::cmp::Reverse(file.modified_at));
+    debug!("Found {} {:?} session files", found.len(), browser);
+    found
+}
+
+/// Open windows and tabs by their session ids
+type Replayed = (HashMap<i32, WindowState>, HashMap<i32, TabState>);
+
+/// Replay the commands of an SNSS file into its open windows and tabs
+fn replay(bytes: &[u8]) -> Result<Replayed, Box<dyn std::error::Error>> {
+    let body = bytes
+        .strip_prefix(SNSS_MAGIC)
+        .and_then(|rest| rest.get(4..)) // File version
+        .ok_or_else(|| AppError::validation("Not a Chromium session file (missing SNSS header)"))?;
+
+    let mut windows: HashMap<i32, WindowState> = HashMap::new();
+    let mut tabs: HashMap<i32, TabState> = HashMap::new();
+    let mut rest = body;
+
+    while let Some((size, after)) = rest.split_first_chunk::<2>() {
+        let size = u16::from_le_bytes(*size) as usize;
+        let Some(command) = after.get(..size) else {
+            // A browser killed mid-write leaves a partial last command
+            warn!("Session file ends in a truncated command");
+            break;
+        };
+        rest = &after[size..];
+        let Some((&id, payload)) = command.split_first() else {
+            continue;
+        };
+
+        let mut fields = Payload::new(payload);
+        match id {
+            SET_TAB_WINDOW => {
+                if let (Some(window_id), Some(tab_id)) = (fields.int(), fields.int()) {
+                    windows.entry(window_id).or_default();
+                    tabs.entry(tab_id).or_default().window_id = Some(window_id);
+                }
+            }
+            SET_TAB_INDEX_IN_WINDOW => {
+                if let (Some(tab_id), Some(index)) = (fields.int(), fields.int()) {
+                    tabs.entry(tab_id).or_default().index_in_window = index;
+                }
+            }
+            UPDATE_TAB_NAVIGATION => {
+                let mut pickle = Payload::pickle(payload);
+                if let (Some(tab_id), Some(index), Some(url), Some(title)) =
+                    (pickle.int(), pickle.int(), pickle.string(), pickle.string16())
+                {
+                    tabs.entry(tab_id).or_default().navigations.insert(index, (url, title));
+                }
+            }
+            SET_SELECTED_NAVIGATION_INDEX => {
+                if let (Some(tab_id), Some(index)) = (fields.int(), fields.int()) {
+                    tabs.entry(tab_id).or_default().selected_navigation = Some(index);
+                }
+            }
+            SET_SELECTED_TAB_IN_INDEX => {
+                if let (Some(window_id), Some(index)) = (fields.int(), fields.int()) {
+                    windows.entry(window_id).or_default().selected_tab_index = Some(index);
+                }
+            }
+            SET_WINDOW_TYPE => {
+                if let (Some(window_id), Some(window_type)) = (fields.int(), fields.int()) {
+                    windows.entry(window_id).or_default().window_type = window_type;
+                }
+            }
+            SET_PINNED_STATE => {
+                if let (Some(tab_id), Some(pinned)) = (fields.int(), payload.get(4)) {
+                    tabs.entry(tab_id).or_default().pinned = *pinned != 0;
+                }
+            }
+            TAB_NAVIGATION_PATH_PRUNED_FROM_BACK => {
+                if let (Some(tab_id), Some(index)) = (fields.int(), fields.int()) {
+                    tabs.entry(tab_id).or_default().navigations.retain(|nav, _| *nav < index);
+                }
+            }
+            TAB_NAVIGATION_PATH_PRUNED_FROM_FRONT => {
+                if let (Some(tab_id), Some(count)) = (fields.int(), fields.int()) {
+                    prune(tabs.entry(tab_id).or_default(), 0, count);
+                }
+            }
+            TAB_NAVIGATION_PATH_PRUNED => {
+                if let (Some(tab_id), Some(index), Some(count)) = (fields.int(), fields.int(), fields.int()) {
+                    prune(tabs.entry(tab_id).or_default(), index, count);
+                }
+            }
+            TAB_CLOSED => {
+                if let Some(tab_id) = fields.int() {
+                    tabs.remove(&tab_id);
+                }
+            }
+            WINDOW_CLOSED => {
+                if let Some(window_id) = fields.int() {
+                    windows.remove(&window_id);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok((windows, tabs))
+}
+
+/// Drop `count` navigations starting at `index`, shifting later ones down
+fn prune(tab: &mut TabState, index: i32, count: i32) {
+    let navigations = std::mem::take(&mut tab.navigations);
+    tab.navigations = navigations
+        .into_iter()
+        .filter(|(nav, _)| *nav < index || *nav >= index + count)
+        .map(|(nav, entry)| (if nav >= index + count { nav - count } else { nav }, entry))
+        .collect();
+    tab.selected_navigation = tab.selected_navigation.map(|selected| {
+        if selected >= index + count {
+            selected - count
+        } else if selected >= index {
+            // The selected entry was pruned, the next one takes its place
+            index
+        } else {
+            selected
+        }
+    });
+}
+
+/// Read the open windows and tabs of a Chromium session file.
+///
+/// Each tab contributes its selected navigation. Tabs without an openable URL
+/// (`chrome://` pages and the like), and pinned tabs with `skip_pinned`, are
+/// counted in `skipped_tabs`.
+#[instrument]
+pub fn read_session(path: &Path, browser: Browser, skip_pinned: bool) -> Result<BrowserSession, Box<dyn std::error::Error>> {
+    let bytes = fs::read(path)?;
+    let (windows, tabs) = replay(&bytes)?;
+    let saved_at = fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .map(DateTime::<Utc>::from)
+        .unwrap_or_else(|_| Utc::now());
+
+    let mut window_ids: Vec<i32> = windows
+        .iter()
+        .filter(|(_, window)| window.window_type == NORMAL_WINDOW)
+        .map(|(id, _)| *id)
+        .collect();
+    window_ids.sort();
+
+    let mut tabs_by_window: HashMap<i32, Vec<TabState>> = HashMap::new();
+    for tab in tabs.into_values() {
+        if let Some(window_id) = tab.window_id {
+            tabs_by_window.entry(window_id).or_default().push(tab);
+        }
+    }
+
+    let mut skipped_tabs = 0;
+    let mut session_windows = Vec::new();
+    for window_id in window_ids {
+        let mut window_tabs = tabs_by_window.remove(&window_id).unwrap_or_default();
+        window_tabs.sort_by_key(|tab| tab.index_in_window);
+
+        let selected_index = windows[&window_id].selected_tab_index;
+        let mut session_tabs = Vec::new();
+        let mut selected_tab = None;
+        for tab in window_tabs {
+            if skip_pinned && tab.pinned {
+                skipped_tabs += 1;
+                continue;
+            }
+            let current = tab
+                .selected_navigation
+                .and_then(|index| tab.navigations.get(&index))
+                .or_else(|| tab.navigations.values().next_back());
+            let Some((url, title)) = current.filter(|(url, _)| is_openable_url(url)) else {
+                skipped_tabs += 1;
+                continue;
+            };
+
+            if selected_index == Some(tab.index_in_window) {
+                selected_tab = Some(session_tabs.len());
+            }
+            session_tabs.push(SiteEntry {
+                title: if title.trim().is_empty() { url.clone() } else { title.clone() },
+                url: url.clone(),
+                canonical_url: None,
+            });
+        }
+
+        if session_tabs.is_empty() {
+            continue;
+        }
+        session_windows.push(SessionWindow {
+            tabs: session_tabs,
+            selected_tab,
+        });
+    }
+
+    Ok(BrowserSession {
+        browser,
+        saved_at,
+        windows: session_windows,
+        skipped_tabs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(id: u8, fields: &[i32]) -> Vec<u8> {
+        let mut payload = vec![id];
+        for field in fields {
+            payload.extend(field.to_le_bytes());
+        }
+        let mut bytes = (payload.len() as u16).to_le_bytes().to_vec();
+        bytes.extend(payload);
+        bytes
+    }
+
+    fn pad(bytes: &mut Vec<u8>) {
+        while !bytes.len().is_multiple_of(4) {
+            bytes.push(0);
+        }
+    }
+
+    fn navigation(tab_id: i32, index: i32, url: &str, title: &str) -> Vec<u8> {
+        let mut pickle = Vec::new();
+        pickle.extend(tab_id.to_le_bytes());
+        pickle.extend(index.to_le_bytes());
+        pickle.extend((url.len() as i32).to_le_bytes());
+        pickle.extend(url.as_bytes());
+        pad(&mut pickle);
+        let title: Vec<u16> = title.encode_utf16().collect();
+        pickle.extend((title.len() as i32).to_le_bytes());
+        pickle.extend(title.iter().flat_map(|unit| unit.to_le_bytes()));
+        pad(&mut pickle);
+
+        let mut payload = vec![UPDATE_TAB_NAVIGATION];
+        payload.extend((pickle.len() as u32).to_le_bytes());
+        payload.extend(pickle);
+        let mut bytes = (payload.len() as u16).to_le_bytes().to_vec();
+        bytes.extend(payload);
+        bytes
+    }
+
+    #[test]
+    fn test_read_session() {
+        let mut bytes = SNSS_MAGIC.to_vec();
+        bytes.extend(3i32.to_le_bytes());
+        let commands = [
+            command(SET_TAB_WINDOW, &[1, 10]),
+            command(SET_TAB_INDEX_IN_WINDOW, &[10, 1]),
+            navigation(10, 0, "https://docs.rs/", "Docs"),
+            navigation(10, 1, "https://docs.rs/serde", "serde - Rust"),
+            command(SET_SELECTED_NAVIGATION_INDEX, &[10, 0]),
+            command(SET_TAB_WINDOW, &[1, 11]),
+            command(SET_TAB_INDEX_IN_WINDOW, &[11, 0]),
+            navigation(11, 0, "https://mail.example.com/", ""),
+            command(SET_PINNED_STATE, &[11, 1]),
+            command(SET_TAB_WINDOW, &[1, 12]),
+            command(SET_TAB_INDEX_IN_WINDOW, &[12, 2]),
+            navigation(12, 0, "chrome://newtab/", "New Tab"),
+            command(SET_TAB_WINDOW, &[1, 13]),
+            navigation(13, 0, "https://closed.example.com/", "Closed"),
+            command(TAB_CLOSED, &[13, 0, 0]),
+            command(SET_SELECTED_TAB_IN_INDEX, &[1, 1]),
+            command(SET_TAB_WINDOW, &[2, 20]),
+            navigation(20, 0, "https://gone.example.com/", "Gone"),
+            command(WINDOW_CLOSED, &[2, 0, 0]),
+            command(SET_TAB_WINDOW, &[3, 30]),
+            navigation(30, 0, "https://popup.example.com/", "Popup"),
+            command(SET_WINDOW_TYPE, &[3, 1]),
+        ];
+        for command in commands {
+            bytes.extend(command);
+        }
+        // Partial command left by a crash
+        bytes.extend([40, 0, SET_TAB_WINDOW]);
+
+        let dir = crate::test_support::temp_dir("chromium-session");
+        let path = dir.join("Session_13370000000000000");
+        fs::write(&path, &bytes).unwrap();
+
+        let session = read_session(&path, Browser::Chrome, false).unwrap();
+        assert_eq!(session.windows.len(), 1);
+        assert_eq!(session.skipped_tabs, 1);
+        let window = &session.windows[0];
+        let tabs: Vec<(&str, &str)> = window
+            .tabs
+            .iter()
+            .map(|tab| (tab.title.as_str(), tab.url.as_str()))
+            .collect();
+        assert_eq!(
+            tabs,
+            vec![("https://mail.example.com/", "https://mail.example.com/"), ("Docs", "https://docs.rs/")]
+        );
+        assert_eq!(window.selected_tab, Some(1));
+
+        let without_pinned = read_session(&path, Browser::Chrome, true).unwrap();
+        assert_eq!(without_pinned.windows[0].tabs.len(), 1);
+        assert_eq!(without_pinned.skipped_tabs, 2);
+
+        let mut tab = TabState::default();
+        for index in 0..5 {
+            tab.navigations.insert(index, (format!("https://example.com/{}", index), String::new()));
+        }
+        tab.selected_navigation = Some(4);
+        prune(&mut tab, 1, 2);
+        assert_eq!(tab.navigations.keys().copied().collect::<Vec<_>>(), vec![0, 1, 2]);
+        assert_eq!(tab.navigations[&2].0, "https://example.com/4");
+        assert_eq!(tab.selected_navigation, Some(2));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
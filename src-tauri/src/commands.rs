@@ -1,7 +1,15 @@
-use crate::models::{BrowserProfile, BrowserMode, CollectionConfig, CollectionData, CollectionRecord, SaveCollectionRequest, SiteEntry};
-use crate::services::{BrowserService, CollectionService, ProfileService};
+use crate::database;
+use crate::error::{AppError, ErrorKind};
+use crate::exporters;
+use crate::models::{BackupInfo, Browser, BrowserDataFilter, BrowserDataProfile, BrowserDataSource, BrowserProfile, BrowserMode, CollectionConfig, CollectionData, CollectionRecord, CollectionRevision, DedupeResult, DedupeStrategy, DuplicateReport, EncryptionStatus, ExportFormat, ImportFormat, ImportPreview, ImportReport, ImportSelection, MergeReport, MergeStrategy, BrowserSession, SessionFile, IntegrityIssue, JournalEntryInfo, JournalStatus, ParsedSites, RepairReport, RevisionDiff, SaveCollectionRequest, SiteEntry, StorageBackend, TrashedCollection, WorkspaceInfo};
+use crate::parser;
+use crate::services::BrowserService;
+use crate::state::AppState;
+use crate::workspaces;
 use chrono::Utc;
 use std::fs;
+use std::sync::Arc;
+use tauri::State;
 use tracing::{info, instrument};
 
 #[tauri::command]
@@ -11,9 +19,23 @@ pub fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
+/// Parse pasted "Title https://url" text into sites, with a diagnostic for
+/// every line that was skipped or changed
 #[tauri::command]
-#[instrument(skip(request), fields(site_count = request.sites.len()))]
-pub fn save_collection(request: SaveCollectionRequest) -> Result<CollectionData, String> {
+#[instrument(skip(text), fields(text_length = text.len()))]
+pub fn parse_sites_text(text: String) -> ParsedSites {
+    let parsed = parser::parse_sites_text(&text);
+    info!(
+        "Parsed {} sites, {} diagnostics",
+        parsed.sites.len(),
+        parsed.diagnostics.len()
+    );
+    parsed
+}
+
+#[tauri::command]
+#[instrument(skip(state, request), fields(site_count = request.sites.len()))]
+pub fn save_collection(state: State<'_, AppState>, request: SaveCollectionRequest) -> Result<CollectionData, AppError> {
     info!("Starting collection save operation");
 
     let collection_data = CollectionData {
@@ -23,273 +45,410 @@ pub fn save_collection(request: SaveCollectionRequest) -> Result<CollectionData,
         config: request.config.unwrap_or_default(), // Use provided config or default
     };
 
-    match CollectionService::new() {
-        Ok(service) => match service.save_collection(collection_data.clone()) {
-            Ok(_) => {
-                info!("Collection saved successfully");
-                Ok(collection_data)
-            }
-            Err(e) => {
-                tracing::error!("Failed to save collection: {}", e);
-                Err(format!("Failed to save collection: {}", e))
-            }
-        },
+    match state.collections().save_collection(collection_data.clone()) {
+        Ok(_) => {
+            info!("Collection saved successfully");
+            Ok(collection_data)
+        }
         Err(e) => {
-            tracing::error!("Failed to initialize collection service: {}", e);
-            Err(format!("Failed to initialize service: {}", e))
+            tracing::error!("Failed to save collection: {}", e);
+            Err(AppError::from(e).context("Failed to save collection"))
         }
     }
 }
 
 #[tauri::command]
-#[instrument]
-pub fn load_collections() -> Result<Vec<CollectionRecord>, String> {
+#[instrument(skip(state))]
+pub fn load_collections(state: State<'_, AppState>) -> Result<Vec<CollectionRecord>, AppError> {
     info!("Loading all collections");
 
-    match CollectionService::new() {
-        Ok(service) => match service.load_all_collections() {
-            Ok(records) => {
-                let collections: Vec<CollectionRecord> = records
-                    .into_iter()
-                    .map(|record| CollectionRecord {
-                        id: record.id,
-                        sites: record.sites,
-                        created_at: record.created_at,
-                        updated_at: record.updated_at,
-                        name: record.name,
-                        config: record.config,
-                    })
-                    .collect();
-                Ok(collections)
-            }
-            Err(e) => {
-                tracing::error!("Failed to load collections: {}", e);
-                Err(format!("Failed to load collections: {}", e))
-            }
-        },
+    match state.collections().load_all_collections() {
+        Ok(records) => {
+            let collections: Vec<CollectionRecord> = records
+                .into_iter()
+                .map(|record| CollectionRecord {
+                    id: record.id,
+                    sites: record.sites,
+                    created_at: record.created_at,
+                    updated_at: record.updated_at,
+                    name: record.name,
+                    config: record.config,
+                })
+                .collect();
+            Ok(collections)
+        }
         Err(e) => {
-            tracing::error!("Failed to initialize collection service: {}", e);
-            Err(format!("Failed to initialize service: {}", e))
+            tracing::error!("Failed to load collections: {}", e);
+            Err(AppError::from(e).context("Failed to load collections"))
         }
     }
 }
 
 #[tauri::command]
-#[instrument(skip(sites), fields(url_count = sites.len()))]
-pub fn restore_collection(sites: Vec<SiteEntry>, config: Option<CollectionConfig>) -> Result<(), String> {
+#[instrument(skip(state, sites), fields(url_count = sites.len()))]
+pub fn restore_collection(state: State<'_, AppState>, sites: Vec<SiteEntry>, config: Option<CollectionConfig>) -> Result<(), AppError> {
     info!("Restoring collection with {} URLs", sites.len());
 
     let collection_config = config.unwrap_or_default();
-    match BrowserService::restore_sites_with_config(sites, &collection_config) {
+    match BrowserService::restore_sites_with_config(&state.profiles(), sites, &collection_config) {
         Ok(_) => {
             info!("Successfully restored all sites");
             Ok(())
         }
         Err(e) => {
             tracing::error!("Failed to restore sites: {}", e);
-            Err(format!("Failed to restore sites: {}", e))
+            Err(AppError::from(e).context("Failed to restore sites"))
         }
     }
 }
 
 #[tauri::command]
-#[instrument]
-pub fn get_collection(id: u64) -> Result<Option<CollectionRecord>, String> {
+#[instrument(skip(state))]
+pub fn get_collection(state: State<'_, AppState>, id: u64) -> Result<Option<CollectionRecord>, AppError> {
     info!("Getting collection with ID: {}", id);
 
-    match CollectionService::new() {
-        Ok(service) => match service.get_collection(id) {
-            Ok(collection) => {
-                if collection.is_some() {
-                    info!("Collection found with ID: {}", id);
-                } else {
-                    info!("Collection not found with ID: {}", id);
-                }
-                Ok(collection)
+    match state.collections().get_collection(id) {
+        Ok(collection) => {
+            if collection.is_some() {
+                info!("Collection found with ID: {}", id);
+            } else {
+                info!("Collection not found with ID: {}", id);
             }
-            Err(e) => {
-                tracing::error!("Failed to get collection: {}", e);
-                Err(format!("Failed to get collection: {}", e))
-            }
-        },
+            Ok(collection)
+        }
         Err(e) => {
-            tracing::error!("Failed to initialize collection service: {}", e);
-            Err(format!("Failed to initialize service: {}", e))
+            tracing::error!("Failed to get collection: {}", e);
+            Err(AppError::from(e).context("Failed to get collection"))
         }
     }
 }
 
 #[tauri::command]
-#[instrument(skip(collection_data))]
-pub fn update_collection(id: u64, collection_data: CollectionData) -> Result<CollectionRecord, String> {
+#[instrument(skip(state, collection_data))]
+pub fn update_collection(state: State<'_, AppState>, id: u64, collection_data: CollectionData) -> Result<CollectionRecord, AppError> {
     info!("Updating collection with ID: {}", id);
 
-    match CollectionService::new() {
-        Ok(service) => match service.update_collection(id, collection_data) {
-            Ok(updated_record) => {
-                info!("Collection updated successfully with ID: {}", id);
-                Ok(updated_record)
-            }
-            Err(e) => {
-                tracing::error!("Failed to update collection: {}", e);
-                Err(format!("Failed to update collection: {}", e))
-            }
-        },
+    match state.collections().update_collection(id, collection_data) {
+        Ok(updated_record) => {
+            info!("Collection updated successfully with ID: {}", id);
+            Ok(updated_record)
+        }
         Err(e) => {
-            tracing::error!("Failed to initialize collection service: {}", e);
-            Err(format!("Failed to initialize service: {}", e))
+            tracing::error!("Failed to update collection: {}", e);
+            Err(AppError::from(e).context("Failed to update collection"))
         }
     }
 }
 
 #[tauri::command]
-#[instrument]
-pub fn delete_collection(id: u64) -> Result<bool, String> {
+#[instrument(skip(state))]
+pub fn delete_collection(state: State<'_, AppState>, id: u64) -> Result<bool, AppError> {
     info!("Deleting collection with ID: {}", id);
 
-    match CollectionService::new() {
-        Ok(service) => match service.delete_collection(id) {
-            Ok(deleted) => {
-                if deleted {
-                    info!("Collection deleted successfully with ID: {}", id);
-                } else {
-                    info!("Collection not found for deletion with ID: {}", id);
-                }
-                Ok(deleted)
+    match state.collections().delete_collection(id) {
+        Ok(deleted) => {
+            if deleted {
+                info!("Collection deleted successfully with ID: {}", id);
+            } else {
+                info!("Collection not found for deletion with ID: {}", id);
             }
-            Err(e) => {
-                tracing::error!("Failed to delete collection: {}", e);
-                Err(format!("Failed to delete collection: {}", e))
-            }
-        },
+            Ok(deleted)
+        }
+        Err(e) => {
+            tracing::error!("Failed to delete collection: {}", e);
+            Err(AppError::from(e).context("Failed to delete collection"))
+        }
+    }
+}
+
+// Trash Commands
+
+#[tauri::command]
+#[instrument(skip(state))]
+pub fn list_trash(state: State<'_, AppState>) -> Result<Vec<TrashedCollection>, AppError> {
+    match state.collections().list_trash() {
+        Ok(trash) => {
+            info!("Loaded {} collections from trash", trash.len());
+            Ok(trash)
+        }
+        Err(e) => {
+            tracing::error!("Failed to load trash: {}", e);
+            Err(AppError::from(e).context("Failed to load trash"))
+        }
+    }
+}
+
+#[tauri::command]
+#[instrument(skip(state))]
+pub fn restore_from_trash(state: State<'_, AppState>, id: u64) -> Result<CollectionRecord, AppError> {
+    info!("Restoring collection from trash with ID: {}", id);
+
+    match state.collections().restore_from_trash(id) {
+        Ok(record) => {
+            info!("Collection restored successfully with ID: {}", record.id);
+            Ok(record)
+        }
+        Err(e) => {
+            tracing::error!("Failed to restore collection from trash: {}", e);
+            Err(AppError::from(e).context("Failed to restore collection from trash"))
+        }
+    }
+}
+
+#[tauri::command]
+#[instrument(skip(state))]
+pub fn empty_trash(state: State<'_, AppState>) -> Result<usize, AppError> {
+    match state.collections().empty_trash() {
+        Ok(count) => {
+            info!("Trash emptied, {} collections permanently deleted", count);
+            Ok(count)
+        }
+        Err(e) => {
+            tracing::error!("Failed to empty trash: {}", e);
+            Err(AppError::from(e).context("Failed to empty trash"))
+        }
+    }
+}
+
+#[tauri::command]
+#[instrument(skip(state))]
+pub fn get_trash_retention_days(state: State<'_, AppState>) -> Result<u32, AppError> {
+    match state.collections().get_trash_retention_days() {
+        Ok(days) => Ok(days),
+        Err(e) => {
+            tracing::error!("Failed to get trash retention: {}", e);
+            Err(AppError::from(e).context("Failed to get trash retention"))
+        }
+    }
+}
+
+#[tauri::command]
+#[instrument(skip(state))]
+pub fn set_trash_retention_days(state: State<'_, AppState>, days: u32) -> Result<(), AppError> {
+    info!("Setting trash retention to {} days", days);
+
+    match state.collections().set_trash_retention_days(days) {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            tracing::error!("Failed to set trash retention: {}", e);
+            Err(AppError::from(e).context("Failed to set trash retention"))
+        }
+    }
+}
+
+// Revision History Commands
+
+#[tauri::command]
+#[instrument(skip(state))]
+pub fn list_revisions(state: State<'_, AppState>, collection_id: u64) -> Result<Vec<CollectionRevision>, AppError> {
+    match state.collections().list_revisions(collection_id) {
+        Ok(revisions) => {
+            info!("Loaded {} revisions for collection {}", revisions.len(), collection_id);
+            Ok(revisions)
+        }
+        Err(e) => {
+            tracing::error!("Failed to load revisions: {}", e);
+            Err(AppError::from(e).context("Failed to load revisions"))
+        }
+    }
+}
+
+#[tauri::command]
+#[instrument(skip(state))]
+pub fn diff_revisions(
+    state: State<'_, AppState>,
+    collection_id: u64,
+    from_revision: u32,
+    to_revision: Option<u32>,
+) -> Result<RevisionDiff, AppError> {
+    match state.collections().diff_revisions(collection_id, from_revision, to_revision) {
+        Ok(diff) => Ok(diff),
+        Err(e) => {
+            tracing::error!("Failed to diff revisions: {}", e);
+            Err(AppError::from(e).context("Failed to diff revisions"))
+        }
+    }
+}
+
+#[tauri::command]
+#[instrument(skip(state))]
+pub fn rollback_collection(state: State<'_, AppState>, collection_id: u64, revision: u32) -> Result<CollectionRecord, AppError> {
+    info!("Rolling back collection {} to revision {}", collection_id, revision);
+
+    match state.collections().rollback_to_revision(collection_id, revision) {
+        Ok(record) => Ok(record),
+        Err(e) => {
+            tracing::error!("Failed to roll back collection: {}", e);
+            Err(AppError::from(e).context("Failed to roll back collection"))
+        }
+    }
+}
+
+// Duplicate Commands
+
+#[tauri::command]
+#[instrument(skip(state))]
+pub fn find_duplicates(state: State<'_, AppState>) -> Result<DuplicateReport, AppError> {
+    match state.collections().find_duplicates() {
+        Ok(report) => Ok(report),
+        Err(e) => {
+            tracing::error!("Failed to find duplicates: {}", e);
+            Err(AppError::from(e).context("Failed to find duplicates"))
+        }
+    }
+}
+
+#[tauri::command]
+#[instrument(skip(state))]
+pub fn dedupe_collection(state: State<'_, AppState>, id: u64, strategy: DedupeStrategy) -> Result<DedupeResult, AppError> {
+    info!("Deduplicating collection {} with {:?}", id, strategy);
+
+    match state.collections().dedupe_collection(id, strategy) {
+        Ok(result) => Ok(result),
+        Err(e) => {
+            tracing::error!("Failed to deduplicate collection: {}", e);
+            Err(AppError::from(e).context("Failed to deduplicate collection"))
+        }
+    }
+}
+
+// Undo/Redo Commands
+
+#[tauri::command]
+#[instrument(skip(state))]
+pub fn undo_last_operation(state: State<'_, AppState>) -> Result<Option<JournalEntryInfo>, AppError> {
+    match state.journal().undo(state.store().as_ref()) {
+        Ok(Some(entry)) => {
+            info!("Undid operation: {}", entry.description);
+            Ok(Some(entry))
+        }
+        Ok(None) => {
+            info!("Nothing to undo");
+            Ok(None)
+        }
+        Err(e) => {
+            tracing::error!("Failed to undo operation: {}", e);
+            Err(AppError::from(e).context("Failed to undo operation"))
+        }
+    }
+}
+
+#[tauri::command]
+#[instrument(skip(state))]
+pub fn redo_operation(state: State<'_, AppState>) -> Result<Option<JournalEntryInfo>, AppError> {
+    match state.journal().redo(state.store().as_ref()) {
+        Ok(Some(entry)) => {
+            info!("Redid operation: {}", entry.description);
+            Ok(Some(entry))
+        }
+        Ok(None) => {
+            info!("Nothing to redo");
+            Ok(None)
+        }
         Err(e) => {
-            tracing::error!("Failed to initialize collection service: {}", e);
-            Err(format!("Failed to initialize service: {}", e))
+            tracing::error!("Failed to redo operation: {}", e);
+            Err(AppError::from(e).context("Failed to redo operation"))
         }
     }
 }
 
+#[tauri::command]
+#[instrument(skip(state))]
+pub fn get_journal_status(state: State<'_, AppState>) -> JournalStatus {
+    state.journal().status()
+}
+
 // Browser Profile Management Commands
 
 #[tauri::command]
-#[instrument(skip(profile))]
-pub fn create_browser_profile(profile: BrowserProfile) -> Result<BrowserProfile, String> {
+#[instrument(skip(state, profile))]
+pub fn create_browser_profile(state: State<'_, AppState>, profile: BrowserProfile) -> Result<BrowserProfile, AppError> {
     info!("Creating browser profile: {}", profile.id);
 
-    match ProfileService::new() {
-        Ok(service) => match service.create_profile(profile) {
-            Ok(created_profile) => {
-                info!("Browser profile created successfully: {}", created_profile.id);
-                Ok(created_profile)
-            }
-            Err(e) => {
-                tracing::error!("Failed to create browser profile: {}", e);
-                Err(format!("Failed to create browser profile: {}", e))
-            }
-        },
+    match state.profiles().create_profile(profile) {
+        Ok(created_profile) => {
+            info!("Browser profile created successfully: {}", created_profile.id);
+            Ok(created_profile)
+        }
         Err(e) => {
-            tracing::error!("Failed to initialize profile service: {}", e);
-            Err(format!("Failed to initialize service: {}", e))
+            tracing::error!("Failed to create browser profile: {}", e);
+            Err(AppError::from(e).context("Failed to create browser profile"))
         }
     }
 }
 
 #[tauri::command]
-#[instrument]
-pub fn get_browser_profiles() -> Result<Vec<BrowserProfile>, String> {
+#[instrument(skip(state))]
+pub fn get_browser_profiles(state: State<'_, AppState>) -> Result<Vec<BrowserProfile>, AppError> {
     info!("Loading all browser profiles");
 
-    match ProfileService::new() {
-        Ok(service) => match service.get_all_profiles() {
-            Ok(profiles) => {
-                info!("Loaded {} browser profiles", profiles.len());
-                Ok(profiles)
-            }
-            Err(e) => {
-                tracing::error!("Failed to load browser profiles: {}", e);
-                Err(format!("Failed to load browser profiles: {}", e))
-            }
-        },
+    match state.profiles().get_all_profiles() {
+        Ok(profiles) => {
+            info!("Loaded {} browser profiles", profiles.len());
+            Ok(profiles)
+        }
         Err(e) => {
-            tracing::error!("Failed to initialize profile service: {}", e);
-            Err(format!("Failed to initialize service: {}", e))
+            tracing::error!("Failed to load browser profiles: {}", e);
+            Err(AppError::from(e).context("Failed to load browser profiles"))
         }
     }
 }
 
 #[tauri::command]
-#[instrument]
-pub fn get_browser_profile(id: String) -> Result<Option<BrowserProfile>, String> {
+#[instrument(skip(state))]
+pub fn get_browser_profile(state: State<'_, AppState>, id: String) -> Result<Option<BrowserProfile>, AppError> {
     info!("Getting browser profile with ID: {}", id);
 
-    match ProfileService::new() {
-        Ok(service) => match service.get_profile(&id) {
-            Ok(profile) => {
-                if profile.is_some() {
-                    info!("Browser profile found with ID: {}", id);
-                } else {
-                    info!("Browser profile not found with ID: {}", id);
-                }
-                Ok(profile)
+    match state.profiles().get_profile(&id) {
+        Ok(profile) => {
+            if profile.is_some() {
+                info!("Browser profile found with ID: {}", id);
+            } else {
+                info!("Browser profile not found with ID: {}", id);
             }
-            Err(e) => {
-                tracing::error!("Failed to get browser profile: {}", e);
-                Err(format!("Failed to get browser profile: {}", e))
-            }
-        },
+            Ok(profile)
+        }
         Err(e) => {
-            tracing::error!("Failed to initialize profile service: {}", e);
-            Err(format!("Failed to initialize service: {}", e))
+            tracing::error!("Failed to get browser profile: {}", e);
+            Err(AppError::from(e).context("Failed to get browser profile"))
         }
     }
 }
 
 #[tauri::command]
-#[instrument(skip(profile))]
-pub fn update_browser_profile(id: String, profile: BrowserProfile) -> Result<BrowserProfile, String> {
+#[instrument(skip(state, profile))]
+pub fn update_browser_profile(state: State<'_, AppState>, id: String, profile: BrowserProfile) -> Result<BrowserProfile, AppError> {
     info!("Updating browser profile with ID: {}", id);
 
-    match ProfileService::new() {
-        Ok(service) => match service.update_profile(&id, profile) {
-            Ok(updated_profile) => {
-                info!("Browser profile updated successfully: {}", id);
-                Ok(updated_profile)
-            }
-            Err(e) => {
-                tracing::error!("Failed to update browser profile: {}", e);
-                Err(format!("Failed to update browser profile: {}", e))
-            }
-        },
+    match state.profiles().update_profile(&id, profile) {
+        Ok(updated_profile) => {
+            info!("Browser profile updated successfully: {}", id);
+            Ok(updated_profile)
+        }
         Err(e) => {
-            tracing::error!("Failed to initialize profile service: {}", e);
-            Err(format!("Failed to initialize service: {}", e))
+            tracing::error!("Failed to update browser profile: {}", e);
+            Err(AppError::from(e).context("Failed to update browser profile"))
         }
     }
 }
 
 #[tauri::command]
-#[instrument]
-pub fn delete_browser_profile(id: String) -> Result<bool, String> {
+#[instrument(skip(state))]
+pub fn delete_browser_profile(state: State<'_, AppState>, id: String) -> Result<bool, AppError> {
     info!("Deleting browser profile with ID: {}", id);
 
-    match ProfileService::new() {
-        Ok(service) => match service.delete_profile(&id) {
-            Ok(deleted) => {
-                if deleted {
-                    info!("Browser profile deleted successfully: {}", id);
-                } else {
-                    info!("Browser profile not found for deletion: {}", id);
-                }
-                Ok(deleted)
+    match state.profiles().delete_profile(&id) {
+        Ok(deleted) => {
+            if deleted {
+                info!("Browser profile deleted successfully: {}", id);
+            } else {
+                info!("Browser profile not found for deletion: {}", id);
             }
-            Err(e) => {
-                tracing::error!("Failed to delete browser profile: {}", e);
-                Err(format!("Failed to delete browser profile: {}", e))
-            }
-        },
+            Ok(deleted)
+        }
         Err(e) => {
-            tracing::error!("Failed to initialize profile service: {}", e);
-            Err(format!("Failed to initialize service: {}", e))
+            tracing::error!("Failed to delete browser profile: {}", e);
+            Err(AppError::from(e).context("Failed to delete browser profile"))
         }
     }
 }
@@ -297,24 +456,18 @@ pub fn delete_browser_profile(id: String) -> Result<bool, String> {
 // Browser Detection Commands
 
 #[tauri::command]
-#[instrument]
-pub fn check_browser_detection() -> Result<Vec<BrowserProfile>, String> {
+#[instrument(skip(state))]
+pub fn check_browser_detection(state: State<'_, AppState>) -> Result<Vec<BrowserProfile>, AppError> {
     info!("Checking browser detection status for all profiles");
 
-    match ProfileService::new() {
-        Ok(service) => match service.update_all_detection_status() {
-            Ok(profiles) => {
-                info!("Browser detection check completed for {} profiles", profiles.len());
-                Ok(profiles)
-            }
-            Err(e) => {
-                tracing::error!("Failed to check browser detection: {}", e);
-                Err(format!("Failed to check browser detection: {}", e))
-            }
-        },
+    match state.profiles().update_all_detection_status() {
+        Ok(profiles) => {
+            info!("Browser detection check completed for {} profiles", profiles.len());
+            Ok(profiles)
+        }
         Err(e) => {
-            tracing::error!("Failed to initialize profile service: {}", e);
-            Err(format!("Failed to initialize service: {}", e))
+            tracing::error!("Failed to check browser detection: {}", e);
+            Err(AppError::from(e).context("Failed to check browser detection"))
         }
     }
 }
@@ -322,47 +475,35 @@ pub fn check_browser_detection() -> Result<Vec<BrowserProfile>, String> {
 // Default Browser Mode Management
 
 #[tauri::command]
-#[instrument]
-pub fn get_default_browser_mode() -> Result<BrowserMode, String> {
+#[instrument(skip(state))]
+pub fn get_default_browser_mode(state: State<'_, AppState>) -> Result<BrowserMode, AppError> {
     info!("Getting default browser mode");
 
-    match ProfileService::new() {
-        Ok(service) => match service.get_default_browser_mode() {
-            Ok(mode) => {
-                info!("Default browser mode: {:?}", mode);
-                Ok(mode)
-            }
-            Err(e) => {
-                tracing::error!("Failed to get default browser mode: {}", e);
-                Err(format!("Failed to get default browser mode: {}", e))
-            }
-        },
+    match state.profiles().get_default_browser_mode() {
+        Ok(mode) => {
+            info!("Default browser mode: {:?}", mode);
+            Ok(mode)
+        }
         Err(e) => {
-            tracing::error!("Failed to initialize profile service: {}", e);
-            Err(format!("Failed to initialize service: {}", e))
+            tracing::error!("Failed to get default browser mode: {}", e);
+            Err(AppError::from(e).context("Failed to get default browser mode"))
         }
     }
 }
 
 #[tauri::command]
-#[instrument]
-pub fn set_default_browser_mode(mode: BrowserMode) -> Result<(), String> {
+#[instrument(skip(state))]
+pub fn set_default_browser_mode(state: State<'_, AppState>, mode: BrowserMode) -> Result<(), AppError> {
     info!("Setting default browser mode to: {:?}", mode);
 
-    match ProfileService::new() {
-        Ok(service) => match service.set_default_browser_mode(mode) {
-            Ok(_) => {
-                info!("Default browser mode updated successfully");
-                Ok(())
-            }
-            Err(e) => {
-                tracing::error!("Failed to set default browser mode: {}", e);
-                Err(format!("Failed to set default browser mode: {}", e))
-            }
-        },
+    match state.profiles().set_default_browser_mode(mode) {
+        Ok(_) => {
+            info!("Default browser mode updated successfully");
+            Ok(())
+        }
         Err(e) => {
-            tracing::error!("Failed to initialize profile service: {}", e);
-            Err(format!("Failed to initialize service: {}", e))
+            tracing::error!("Failed to set default browser mode: {}", e);
+            Err(AppError::from(e).context("Failed to set default browser mode"))
         }
     }
 }
@@ -370,53 +511,41 @@ pub fn set_default_browser_mode(mode: BrowserMode) -> Result<(), String> {
 // Backup and Restore Commands
 
 #[tauri::command]
-#[instrument]
-pub fn export_database() -> Result<String, String> {
+#[instrument(skip(state, passphrase))]
+pub fn export_database(
+    state: State<'_, AppState>,
+    passphrase: Option<String>,
+    ids: Option<Vec<u64>>,
+) -> Result<String, AppError> {
     info!("Exporting database to JSON");
 
-    match CollectionService::new() {
-        Ok(service) => match service.export_database() {
-            Ok(json_data) => {
-                info!("Database exported successfully, {} characters", json_data.len());
-                Ok(json_data)
-            }
-            Err(e) => {
-                tracing::error!("Failed to export database: {}", e);
-                Err(format!("Failed to export database: {}", e))
-            }
-        },
+    match state.collections().export_database(passphrase.as_deref(), ids.as_deref()) {
+        Ok(json_data) => {
+            info!("Database exported successfully, {} characters", json_data.len());
+            Ok(json_data)
+        }
         Err(e) => {
-            tracing::error!("Failed to initialize collection service: {}", e);
-            Err(format!("Failed to initialize service: {}", e))
+            tracing::error!("Failed to export database: {}", e);
+            Err(AppError::from(e).context("Failed to export database"))
         }
     }
 }
 
 #[tauri::command]
-#[instrument]
-pub async fn export_database_to_file(window: tauri::Window) -> Result<String, String> {
+#[instrument(skip(state, passphrase))]
+pub async fn export_database_to_file(
+    state: State<'_, AppState>,
+    window: tauri::Window,
+    passphrase: Option<String>,
+    ids: Option<Vec<u64>>,
+) -> Result<String, AppError> {
     info!("Exporting database to file with save dialog");
 
-    // First get the data
-    let json_data = match CollectionService::new() {
-        Ok(service) => match service.export_database() {
-            Ok(data) => data,
-            Err(e) => {
-                tracing::error!("Failed to export database: {}", e);
-                return Err(format!("Failed to export database: {}", e));
-            }
-        },
-        Err(e) => {
-            tracing::error!("Failed to initialize collection service: {}", e);
-            return Err(format!("Failed to initialize service: {}", e));
-        }
-    };
-
     // Create filename with timestamp
     let timestamp = Utc::now().format("%Y-%m-%dT%H-%M-%S").to_string();
     let default_filename = format!("restore-sites-backup-{}.json", timestamp);
 
-    // Use Tauri dialog to save file
+    // Use Tauri dialog to save file; the chosen filter's extension picks the format
     use tauri_plugin_dialog::DialogExt;
     
     let file_path = window
@@ -425,53 +554,611 @@ pub async fn export_database_to_file(window: tauri::Window) -> Result<String, St
         .set_title("Save Database Backup")
         .set_file_name(&default_filename)
         .add_filter("JSON files", &["json"])
+        .add_filter("Markdown", &["md"])
+        .add_filter("CSV", &["csv"])
+        .add_filter("OPML", &["opml"])
+        .add_filter("URL list", &["txt"])
         .add_filter("All files", &["*"])
         .blocking_save_file();
 
-    match file_path {
-        Some(path) => {
-            // Convert FilePath to PathBuf
-            let path_buf = path.as_path().unwrap();
-            
-            // Write the file
-            match fs::write(&path_buf, json_data) {
-                Ok(_) => {
-                    let path_str = path_buf.to_string_lossy().to_string();
-                    info!("Database exported successfully to: {}", path_str);
-                    Ok(path_str)
-                }
-                Err(e) => {
-                    tracing::error!("Failed to write file: {}", e);
-                    Err(format!("Failed to write file: {}", e))
-                }
-            }
+    let Some(path) = file_path else {
+        info!("Export cancelled by user");
+        return Err(AppError::new(ErrorKind::Cancelled, "Export cancelled"));
+    };
+    // Convert FilePath to PathBuf
+    let path_buf = path.as_path().unwrap();
+
+    // Then get the data, as JSON or text, of the selected collections or all of them
+    let data = match exporters::format_for_path(path_buf) {
+        None => state.collections().export_database(passphrase.as_deref(), ids.as_deref()),
+        Some(_) if passphrase.is_some() => {
+            Err(AppError::validation("Only JSON exports can be encrypted").into())
+        }
+        Some(format) => state.collections().export_collections(ids.as_deref(), format),
+    };
+    let data = match data {
+        Ok(data) => data,
+        Err(e) => {
+            tracing::error!("Failed to export database: {}", e);
+            return Err(AppError::from(e).context("Failed to export database"));
+        }
+    };
+
+    // Write the file
+    match fs::write(path_buf, data) {
+        Ok(_) => {
+            let path_str = path_buf.to_string_lossy().to_string();
+            info!("Database exported successfully to: {}", path_str);
+            Ok(path_str)
         }
-        None => {
-            info!("Export cancelled by user");
-            Err("Export cancelled".to_string())
+        Err(e) => {
+            tracing::error!("Failed to write file: {}", e);
+            Err(AppError::from(e).context("Failed to write file"))
         }
     }
 }
 
 #[tauri::command]
-#[instrument(skip(json_data), fields(data_length = json_data.len()))]
-pub fn import_database(json_data: String, replace_existing: bool) -> Result<usize, String> {
+#[instrument(skip(state, json_data, passphrase), fields(data_length = json_data.len()))]
+pub fn import_database(
+    state: State<'_, AppState>,
+    json_data: String,
+    replace_existing: bool,
+    passphrase: Option<String>,
+) -> Result<usize, AppError> {
     info!("Importing database from JSON, replace_existing: {}", replace_existing);
 
-    match CollectionService::new() {
-        Ok(service) => match service.import_database(json_data, replace_existing) {
-            Ok(imported_count) => {
-                info!("Database imported successfully, {} collections imported", imported_count);
-                Ok(imported_count)
+    match state
+        .collections()
+        .import_database(json_data, replace_existing, passphrase.as_deref())
+    {
+        Ok(imported_count) => {
+            info!("Database imported successfully, {} collections imported", imported_count);
+            Ok(imported_count)
+        }
+        Err(e) => {
+            tracing::error!("Failed to import database: {}", e);
+            Err(AppError::from(e).context("Failed to import database"))
+        }
+    }
+}
+
+#[tauri::command]
+#[instrument(skip(state, json_data, passphrase), fields(data_length = json_data.len()))]
+pub fn merge_database(
+    state: State<'_, AppState>,
+    json_data: String,
+    passphrase: Option<String>,
+    strategy: MergeStrategy,
+    dry_run: bool,
+) -> Result<MergeReport, AppError> {
+    info!("Merging database from JSON, strategy: {:?}, dry_run: {}", strategy, dry_run);
+
+    match state
+        .collections()
+        .merge_database(json_data, passphrase.as_deref(), strategy, dry_run)
+    {
+        Ok(report) => Ok(report),
+        Err(e) => {
+            tracing::error!("Failed to merge database: {}", e);
+            Err(AppError::from(e).context("Failed to merge database"))
+        }
+    }
+}
+
+#[tauri::command]
+#[instrument(skip(state, json_data, passphrase), fields(data_length = json_data.len()))]
+pub fn preview_import(
+    state: State<'_, AppState>,
+    json_data: String,
+    passphrase: Option<String>,
+) -> Result<ImportPreview, AppError> {
+    match state.collections().preview_import(json_data, passphrase.as_deref()) {
+        Ok(preview) => Ok(preview),
+        Err(e) => {
+            tracing::error!("Failed to read import file: {}", e);
+            Err(AppError::from(e).context("Failed to read import file"))
+        }
+    }
+}
+
+#[tauri::command]
+#[instrument(skip(state, json_data, passphrase, selection), fields(data_length = json_data.len()))]
+pub fn import_selected(
+    state: State<'_, AppState>,
+    json_data: String,
+    passphrase: Option<String>,
+    selection: ImportSelection,
+    strategy: MergeStrategy,
+) -> Result<ImportReport, AppError> {
+    info!(
+        "Importing {} collections and {} profiles, strategy: {:?}",
+        selection.collection_ids.len(),
+        selection.profile_ids.len(),
+        strategy
+    );
+
+    match state
+        .collections()
+        .import_selected(json_data, passphrase.as_deref(), &selection, strategy)
+    {
+        Ok(report) => Ok(report),
+        Err(e) => {
+            tracing::error!("Failed to import database: {}", e);
+            Err(AppError::from(e).context("Failed to import database"))
+        }
+    }
+}
+
+#[tauri::command]
+#[instrument(skip(state, html), fields(html_length = html.len()))]
+pub fn import_bookmarks_html(
+    state: State<'_, AppState>,
+    html: String,
+    flatten: bool,
+    strategy: MergeStrategy,
+) -> Result<ImportReport, AppError> {
+    info!("Importing bookmarks HTML, flatten: {}, strategy: {:?}", flatten, strategy);
+
+    match state.collections().import_bookmarks_html(&html, flatten, strategy) {
+        Ok(report) => Ok(report),
+        Err(e) => {
+            tracing::error!("Failed to import bookmarks: {}", e);
+            Err(AppError::from(e).context("Failed to import bookmarks"))
+        }
+    }
+}
+
+#[tauri::command]
+#[instrument(skip(state))]
+pub fn export_bookmarks_html(state: State<'_, AppState>, ids: Vec<u64>) -> Result<String, AppError> {
+    info!("Exporting {} collections as bookmarks HTML", ids.len());
+
+    match state.collections().export_bookmarks_html(&ids) {
+        Ok(html) => Ok(html),
+        Err(e) => {
+            tracing::error!("Failed to export bookmarks: {}", e);
+            Err(AppError::from(e).context("Failed to export bookmarks"))
+        }
+    }
+}
+
+#[tauri::command]
+#[instrument(skip(state))]
+pub fn export_collections(
+    state: State<'_, AppState>,
+    ids: Option<Vec<u64>>,
+    format: ExportFormat,
+) -> Result<String, AppError> {
+    info!("Exporting collections as {:?}", format);
+
+    match state.collections().export_collections(ids.as_deref(), format) {
+        Ok(contents) => Ok(contents),
+        Err(e) => {
+            tracing::error!("Failed to export collections: {}", e);
+            Err(AppError::from(e).context("Failed to export collections"))
+        }
+    }
+}
+
+#[tauri::command]
+#[instrument(skip(state, contents), fields(contents_length = contents.len()))]
+pub fn import_from_format(
+    state: State<'_, AppState>,
+    format: ImportFormat,
+    contents: String,
+    strategy: MergeStrategy,
+) -> Result<ImportReport, AppError> {
+    info!("Importing {:?} export, strategy: {:?}", format, strategy);
+
+    match state.collections().import_from_format(format, &contents, strategy) {
+        Ok(report) => Ok(report),
+        Err(e) => {
+            tracing::error!("Failed to import {:?} export: {}", format, e);
+            Err(AppError::from(e).context("Failed to import export"))
+        }
+    }
+}
+
+// Browser Session Commands
+
+#[tauri::command]
+#[instrument(skip(state))]
+pub fn list_firefox_sessions(state: State<'_, AppState>) -> Vec<SessionFile> {
+    state.collections().list_firefox_sessions()
+}
+
+#[tauri::command]
+#[instrument(skip(state))]
+pub fn read_firefox_session(state: State<'_, AppState>, path: String, skip_pinned: bool) -> Result<BrowserSession, AppError> {
+    match state.collections().read_firefox_session(&path, skip_pinned) {
+        Ok(session) => Ok(session),
+        Err(e) => {
+            tracing::error!("Failed to read Firefox session: {}", e);
+            Err(AppError::from(e).context("Failed to read Firefox session"))
+        }
+    }
+}
+
+#[tauri::command]
+#[instrument(skip(state))]
+pub fn import_firefox_session(
+    state: State<'_, AppState>,
+    path: String,
+    skip_pinned: bool,
+    windows: Option<Vec<usize>>,
+    strategy: MergeStrategy,
+) -> Result<ImportReport, AppError> {
+    info!("Importing Firefox session from {}", path);
+
+    match state.collections().import_firefox_session(&path, skip_pinned, windows, strategy) {
+        Ok(report) => Ok(report),
+        Err(e) => {
+            tracing::error!("Failed to import Firefox session: {}", e);
+            Err(AppError::from(e).context("Failed to import Firefox session"))
+        }
+    }
+}
+
+#[tauri::command]
+#[instrument(skip(state))]
+pub fn list_chromium_sessions(state: State<'_, AppState>) -> Vec<SessionFile> {
+    state.collections().list_chromium_sessions()
+}
+
+#[tauri::command]
+#[instrument(skip(state))]
+pub fn read_chromium_session(
+    state: State<'_, AppState>,
+    path: String,
+    browser: Browser,
+    skip_pinned: bool,
+) -> Result<BrowserSession, AppError> {
+    match state.collections().read_chromium_session(&path, browser, skip_pinned) {
+        Ok(session) => Ok(session),
+        Err(e) => {
+            tracing::error!("Failed to read browser session: {}", e);
+            Err(AppError::from(e).context("Failed to read browser session"))
+        }
+    }
+}
+
+#[tauri::command]
+#[instrument(skip(state))]
+pub fn import_chromium_session(
+    state: State<'_, AppState>,
+    path: String,
+    browser: Browser,
+    skip_pinned: bool,
+    windows: Option<Vec<usize>>,
+    strategy: MergeStrategy,
+) -> Result<ImportReport, AppError> {
+    info!("Importing {:?} session from {}", browser, path);
+
+    match state
+        .collections()
+        .import_chromium_session(&path, browser, skip_pinned, windows, strategy)
+    {
+        Ok(report) => Ok(report),
+        Err(e) => {
+            tracing::error!("Failed to import browser session: {}", e);
+            Err(AppError::from(e).context("Failed to import browser session"))
+        }
+    }
+}
+
+// Browser Bookmarks and History Commands
+
+#[tauri::command]
+#[instrument(skip(state))]
+pub fn list_browser_data_profiles(state: State<'_, AppState>) -> Vec<BrowserDataProfile> {
+    state.collections().list_browser_data_profiles()
+}
+
+#[tauri::command]
+#[instrument(skip(state, filter))]
+pub fn import_browser_data(
+    state: State<'_, AppState>,
+    browser: Browser,
+    profile_path: String,
+    source: BrowserDataSource,
+    filter: Option<BrowserDataFilter>,
+    strategy: MergeStrategy,
+) -> Result<ImportReport, AppError> {
+    info!("Importing {:?} {:?} from {}", browser, source, profile_path);
+
+    match state
+        .collections()
+        .import_browser_data(browser, &profile_path, source, &filter.unwrap_or_default(), strategy)
+    {
+        Ok(report) => Ok(report),
+        Err(e) => {
+            tracing::error!("Failed to import browser data: {}", e);
+            Err(AppError::from(e).context("Failed to import browser data"))
+        }
+    }
+}
+
+// Storage Backend Commands
+
+#[tauri::command]
+#[instrument(skip(state))]
+pub fn get_storage_backend(state: State<'_, AppState>) -> Result<StorageBackend, AppError> {
+    info!("Getting storage backend");
+
+    Ok(state.collections().storage_backend())
+}
+
+#[tauri::command]
+#[instrument(skip(state))]
+pub fn migrate_to_sqlite(state: State<'_, AppState>) -> Result<usize, AppError> {
+    info!("Migrating database from JSON to SQLite");
+
+    let migrated = database::migrate_json_to_sqlite().and_then(|migrated_count| {
+        // Swap the JSON store held in state for the new SQLite one
+        state.reload()?;
+        Ok(migrated_count)
+    });
+
+    match migrated {
+        Ok(migrated_count) => {
+            info!("Database migrated to SQLite, {} collections", migrated_count);
+            Ok(migrated_count)
+        }
+        Err(e) => {
+            tracing::error!("Failed to migrate database to SQLite: {}", e);
+            Err(AppError::from(e).context("Failed to migrate database to SQLite"))
+        }
+    }
+}
+
+#[tauri::command]
+#[instrument(skip(state))]
+pub fn get_startup_error(state: State<'_, AppState>) -> Option<AppError> {
+    state.startup_error()
+}
+
+/// Try opening the database again, e.g. after another process released it
+#[tauri::command]
+#[instrument(skip(state))]
+pub fn reopen_database(state: State<'_, AppState>) -> Result<(), AppError> {
+    info!("Reopening database");
+
+    match state.reload() {
+        Ok(()) => {
+            let collections = state.collections();
+            if collections.encryption_status().unlocked {
+                collections.run_startup_maintenance();
             }
-            Err(e) => {
-                tracing::error!("Failed to import database: {}", e);
-                Err(format!("Failed to import database: {}", e))
+            Ok(())
+        }
+        Err(e) => {
+            tracing::error!("Failed to reopen database: {}", e);
+            Err(AppError::from(e).context("Failed to open the database"))
+        }
+    }
+}
+
+// Encryption Commands
+
+#[tauri::command]
+#[instrument(skip(state))]
+pub fn get_encryption_status(state: State<'_, AppState>) -> Result<EncryptionStatus, AppError> {
+    info!("Getting encryption status");
+
+    Ok(state.collections().encryption_status())
+}
+
+#[tauri::command]
+#[instrument(skip(state, passphrase))]
+pub fn unlock_database(state: State<'_, AppState>, passphrase: String) -> Result<(), AppError> {
+    info!("Unlocking encrypted database");
+
+    let collections = state.collections();
+    match collections.unlock(&passphrase) {
+        Ok(()) => {
+            // Skipped at launch while the database couldn't be read
+            collections.run_startup_maintenance();
+            info!("Database unlocked successfully");
+            Ok(())
+        }
+        Err(e) => {
+            tracing::error!("Failed to unlock database: {}", e);
+            Err(AppError::from(e).context("Failed to unlock database"))
+        }
+    }
+}
+
+#[tauri::command]
+#[instrument(skip(state, passphrase))]
+pub fn enable_encryption(state: State<'_, AppState>, passphrase: String) -> Result<(), AppError> {
+    info!("Enabling database encryption");
+
+    match state.collections().enable_encryption(&passphrase) {
+        Ok(()) => {
+            info!("Database encryption enabled successfully");
+            Ok(())
+        }
+        Err(e) => {
+            tracing::error!("Failed to enable encryption: {}", e);
+            Err(AppError::from(e).context("Failed to enable encryption"))
+        }
+    }
+}
+
+#[tauri::command]
+#[instrument(skip(state, current_passphrase, new_passphrase))]
+pub fn change_passphrase(
+    state: State<'_, AppState>,
+    current_passphrase: String,
+    new_passphrase: String,
+) -> Result<(), AppError> {
+    info!("Changing database passphrase");
+
+    match state
+        .collections()
+        .change_passphrase(&current_passphrase, &new_passphrase)
+    {
+        Ok(()) => {
+            info!("Database passphrase changed successfully");
+            Ok(())
+        }
+        Err(e) => {
+            tracing::error!("Failed to change passphrase: {}", e);
+            Err(AppError::from(e).context("Failed to change passphrase"))
+        }
+    }
+}
+
+#[tauri::command]
+#[instrument(skip(state, passphrase))]
+pub fn disable_encryption(state: State<'_, AppState>, passphrase: String) -> Result<(), AppError> {
+    info!("Disabling database encryption");
+
+    match state.collections().disable_encryption(&passphrase) {
+        Ok(()) => {
+            info!("Database encryption disabled successfully");
+            Ok(())
+        }
+        Err(e) => {
+            tracing::error!("Failed to disable encryption: {}", e);
+            Err(AppError::from(e).context("Failed to disable encryption"))
+        }
+    }
+}
+
+// Workspace Commands
+
+#[tauri::command]
+#[instrument]
+pub fn get_workspaces() -> Result<Vec<WorkspaceInfo>, AppError> {
+    info!("Listing workspaces");
+
+    match workspaces::list_workspaces() {
+        Ok(workspaces) => {
+            info!("Found {} workspaces", workspaces.len());
+            Ok(workspaces)
+        }
+        Err(e) => {
+            tracing::error!("Failed to list workspaces: {}", e);
+            Err(AppError::from(e).context("Failed to list workspaces"))
+        }
+    }
+}
+
+#[tauri::command]
+#[instrument]
+pub fn create_workspace(name: String) -> Result<WorkspaceInfo, AppError> {
+    info!("Creating workspace: {}", name);
+
+    match workspaces::create_workspace(&name) {
+        Ok(workspace) => {
+            info!("Workspace created successfully: {}", workspace.name);
+            Ok(workspace)
+        }
+        Err(e) => {
+            tracing::error!("Failed to create workspace: {}", e);
+            Err(AppError::from(e).context("Failed to create workspace"))
+        }
+    }
+}
+
+#[tauri::command]
+#[instrument(skip(state))]
+pub fn switch_workspace(state: State<'_, AppState>, name: String) -> Result<WorkspaceInfo, AppError> {
+    info!("Switching to workspace: {}", name);
+
+    // The new store is opened before the switch is remembered, so a workspace
+    // that can't be opened leaves both the running app and the next launch as they were
+    let switched = workspaces::existing_workspace_dir(&name)
+        .and_then(|dir| database::open_store_in(&dir))
+        .and_then(|store| {
+            let workspace = workspaces::set_active_workspace(&name)?;
+            state.replace_store(Arc::from(store));
+            Ok(workspace)
+        });
+
+    match switched {
+        Ok(workspace) => {
+            // As at launch, the new workspace's trash and backups are looked after too
+            let collections = state.collections();
+            if collections.encryption_status().unlocked {
+                collections.run_startup_maintenance();
             }
-        },
+            info!("Switched to workspace: {}", workspace.name);
+            Ok(workspace)
+        }
+        Err(e) => {
+            tracing::error!("Failed to switch workspace: {}", e);
+            Err(AppError::from(e).context("Failed to switch workspace"))
+        }
+    }
+}
+
+#[tauri::command]
+#[instrument(skip(state))]
+pub fn list_backups(state: State<'_, AppState>) -> Result<Vec<BackupInfo>, AppError> {
+    match state.collections().list_backups() {
+        Ok(backups) => {
+            info!("Found {} backups", backups.len());
+            Ok(backups)
+        }
+        Err(e) => {
+            tracing::error!("Failed to list backups: {}", e);
+            Err(AppError::from(e).context("Failed to list backups"))
+        }
+    }
+}
+
+#[tauri::command]
+#[instrument(skip(state))]
+pub fn restore_backup(state: State<'_, AppState>, file_name: String) -> Result<usize, AppError> {
+    info!("Restoring backup: {}", file_name);
+
+    match state.collections().restore_backup(&file_name) {
+        Ok(count) => {
+            info!("Backup restored with {} collections", count);
+            Ok(count)
+        }
+        Err(e) => {
+            tracing::error!("Failed to restore backup: {}", e);
+            Err(AppError::from(e).context("Failed to restore backup"))
+        }
+    }
+}
+
+#[tauri::command]
+#[instrument(skip(state))]
+pub fn verify_database(state: State<'_, AppState>) -> Result<Vec<IntegrityIssue>, AppError> {
+    match state.collections().verify_database() {
+        Ok(issues) => {
+            info!("Database check found {} issues", issues.len());
+            Ok(issues)
+        }
+        Err(e) => {
+            tracing::error!("Failed to verify database: {}", e);
+            Err(AppError::from(e).context("Failed to verify database"))
+        }
+    }
+}
+
+#[tauri::command]
+#[instrument(skip(state))]
+pub fn repair_database(state: State<'_, AppState>) -> Result<RepairReport, AppError> {
+    info!("Repairing database");
+
+    match state.collections().repair_database() {
+        Ok(report) => {
+            info!(
+                "Database repaired, {} issues fixed, {} remaining",
+                report.fixed.len(),
+                report.remaining.len()
+            );
+            Ok(report)
+        }
         Err(e) => {
-            tracing::error!("Failed to initialize collection service: {}", e);
-            Err(format!("Failed to initialize service: {}", e))
+            tracing::error!("Failed to repair database: {}", e);
+            Err(AppError::from(e).context("Failed to repair database"))
         }
     }
 }
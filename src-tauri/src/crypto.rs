@@ -0,0 +1,305 @@
+use crate::error::{AppError, ErrorKind};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+use zeroize::Zeroizing;
+
+/// Marks a file as an encrypted envelope rather than a plaintext database
+const ENVELOPE_FORMAT: &str = "restore-sites-encrypted";
+const ENVELOPE_VERSION: u32 = 1;
+const KDF_NAME: &str = "argon2id";
+const CIPHER_NAME: &str = "xchacha20poly1305";
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const KEY_LEN: usize = 32;
+const MIN_PASSPHRASE_CHARS: usize = 8;
+
+/// Argon2id cost recommended by OWASP: 19 MiB, 2 passes, 1 lane
+const DEFAULT_M_COST: u32 = 19 * 1024;
+const DEFAULT_T_COST: u32 = 2;
+const DEFAULT_P_COST: u32 = 1;
+
+/// Files may ask for a stronger KDF than we write, but not one that would take
+/// the machine's memory or minutes of CPU just to reject a passphrase
+const MAX_M_COST: u32 = 8 * DEFAULT_M_COST;
+const MAX_T_COST: u32 = 8 * DEFAULT_T_COST;
+const MAX_P_COST: u32 = 8;
+
+/// Returned when the database is encrypted and no passphrase has been entered yet
+#[derive(Debug)]
+pub struct DatabaseLocked;
+
+impl std::fmt::Display for DatabaseLocked {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Database is encrypted, unlock it with your passphrase first")
+    }
+}
+
+impl std::error::Error for DatabaseLocked {}
+
+/// Authentication failed: the passphrase is wrong or the file was tampered with
+#[derive(Debug)]
+pub struct WrongPassphrase;
+
+impl std::fmt::Display for WrongPassphrase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Wrong passphrase, or the encrypted data is damaged")
+    }
+}
+
+impl std::error::Error for WrongPassphrase {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct KdfParams {
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+}
+
+impl KdfParams {
+    fn check_limits(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.m_cost > MAX_M_COST || self.t_cost > MAX_T_COST || self.p_cost > MAX_P_COST {
+            return Err(AppError::corrupt(format!(
+                "Encrypted data asks for an unreasonable key derivation cost (m={}, t={}, p={})",
+                self.m_cost, self.t_cost, self.p_cost
+            ))
+            .into());
+        }
+        Ok(())
+    }
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            m_cost: DEFAULT_M_COST,
+            t_cost: DEFAULT_T_COST,
+            p_cost: DEFAULT_P_COST,
+        }
+    }
+}
+
+/// On-disk form of encrypted data; everything needed to derive the key except the passphrase
+#[derive(Debug, Serialize, Deserialize)]
+struct Envelope {
+    format: String,
+    version: u32,
+    kdf: String,
+    kdf_params: KdfParams,
+    salt: String,
+    cipher: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// Just enough of a document to tell an envelope from a plaintext database
+#[derive(Deserialize)]
+struct Probe {
+    format: Option<String>,
+}
+
+/// Whether `contents` is an encrypted envelope
+pub fn is_encrypted(contents: &str) -> bool {
+    serde_json::from_str::<Probe>(contents)
+        .is_ok_and(|probe| probe.format.as_deref() == Some(ENVELOPE_FORMAT))
+}
+
+pub fn validate_passphrase(passphrase: &str) -> Result<(), String> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_CHARS {
+        return Err(format!(
+            "Passphrase must be at least {} characters",
+            MIN_PASSPHRASE_CHARS
+        ));
+    }
+    Ok(())
+}
+
+fn random_bytes<const N: usize>() -> Result<[u8; N], Box<dyn std::error::Error>> {
+    let mut bytes = [0u8; N];
+    getrandom::getrandom(&mut bytes).map_err(|e| format!("No secure random source: {}", e))?;
+    Ok(bytes)
+}
+
+fn decode<const N: usize>(field: &str, value: &str) -> Result<[u8; N], Box<dyn std::error::Error>> {
+    BASE64
+        .decode(value)?
+        .try_into()
+        .map_err(|_| format!("Encrypted data has an invalid {}", field).into())
+}
+
+fn parse_envelope(contents: &str) -> Result<Envelope, Box<dyn std::error::Error>> {
+    let envelope: Envelope = serde_json::from_str(contents)?;
+    if envelope.format != ENVELOPE_FORMAT {
+        return Err(AppError::corrupt("Not an encrypted Restore Sites file").into());
+    }
+    if envelope.version > ENVELOPE_VERSION
+        || envelope.kdf != KDF_NAME
+        || envelope.cipher != CIPHER_NAME
+    {
+        return Err(format!(
+            "Unsupported encryption (v{}, {}, {}), please update the app",
+            envelope.version, envelope.kdf, envelope.cipher
+        )
+        .into());
+    }
+    Ok(envelope)
+}
+
+/// A passphrase-derived key, kept in memory while the database is unlocked.
+///
+/// Deriving is deliberately slow, so the key is derived once per salt and
+/// reused for every save; each save still gets a fresh random nonce.
+#[derive(Clone)]
+pub struct EncryptionKey {
+    key: Zeroizing<[u8; KEY_LEN]>,
+    salt: [u8; SALT_LEN],
+    params: KdfParams,
+}
+
+impl std::fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EncryptionKey").finish_non_exhaustive()
+    }
+}
+
+impl EncryptionKey {
+    /// Derive a key for a new passphrase with a fresh salt
+    #[instrument(skip(passphrase))]
+    pub fn generate(passphrase: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        validate_passphrase(passphrase).map_err(AppError::validation)?;
+        Self::derive(passphrase, random_bytes()?, KdfParams::default())
+    }
+
+    fn derive(
+        passphrase: &str,
+        salt: [u8; SALT_LEN],
+        params: KdfParams,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let argon2 = Argon2::new(
+            Algorithm::Argon2id,
+            Version::V0x13,
+            Params::new(params.m_cost, params.t_cost, params.p_cost, Some(KEY_LEN))
+                .map_err(|e| format!("Invalid key derivation parameters: {}", e))?,
+        );
+
+        let mut key = Zeroizing::new([0u8; KEY_LEN]);
+        argon2
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut key[..])
+            .map_err(|e| format!("Key derivation failed: {}", e))?;
+
+        debug!("Derived encryption key");
+        Ok(Self { key, salt, params })
+    }
+
+    /// Encrypt `plaintext` into an envelope document
+    pub fn seal(&self, plaintext: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let nonce: [u8; NONCE_LEN] = random_bytes()?;
+        let cipher = XChaCha20Poly1305::new(Key::from_slice(&self.key[..]));
+        let ciphertext = cipher
+            .encrypt(XNonce::from_slice(&nonce), plaintext.as_bytes())
+            .map_err(|_| "Encryption failed")?;
+
+        let envelope = Envelope {
+            format: ENVELOPE_FORMAT.to_string(),
+            version: ENVELOPE_VERSION,
+            kdf: KDF_NAME.to_string(),
+            kdf_params: self.params,
+            salt: BASE64.encode(self.salt),
+            cipher: CIPHER_NAME.to_string(),
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(ciphertext),
+        };
+        Ok(serde_json::to_string_pretty(&envelope)?)
+    }
+
+    /// Decrypt an envelope sealed with this key
+    pub fn open(&self, contents: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let envelope = parse_envelope(contents)?;
+        if decode::<SALT_LEN>("salt", &envelope.salt)? != self.salt
+            || envelope.kdf_params != self.params
+        {
+            // Sealed under another passphrase, e.g. before it was changed
+            return Err(WrongPassphrase.into());
+        }
+        self.decrypt(&envelope)
+    }
+
+    fn decrypt(&self, envelope: &Envelope) -> Result<String, Box<dyn std::error::Error>> {
+        let nonce = decode::<NONCE_LEN>("nonce", &envelope.nonce)?;
+        let ciphertext = BASE64.decode(&envelope.ciphertext)?;
+
+        let cipher = XChaCha20Poly1305::new(Key::from_slice(&self.key[..]));
+        let plaintext = cipher
+            .decrypt(XNonce::from_slice(&nonce), ciphertext.as_slice())
+            .map_err(|_| WrongPassphrase)?;
+        Ok(String::from_utf8(plaintext)?)
+    }
+}
+
+/// Decrypt an envelope with a passphrase, returning the plaintext and the key it derived
+#[instrument(skip(contents, passphrase))]
+pub fn open_with_passphrase(
+    contents: &str,
+    passphrase: &str,
+) -> Result<(String, EncryptionKey), Box<dyn std::error::Error>> {
+    let envelope = parse_envelope(contents)?;
+    envelope.kdf_params.check_limits()?;
+    let key = EncryptionKey::derive(
+        passphrase,
+        decode("salt", &envelope.salt)?,
+        envelope.kdf_params,
+    )?;
+    let plaintext = key.decrypt(&envelope)?;
+    Ok((plaintext, key))
+}
+
+/// Plaintext of an exported file, decrypting it first if it was exported with a passphrase
+pub fn open_export(contents: String, passphrase: Option<&str>) -> Result<String, Box<dyn std::error::Error>> {
+    if !is_encrypted(&contents) {
+        return Ok(contents);
+    }
+    let passphrase = passphrase.ok_or_else(|| {
+        AppError::new(
+            ErrorKind::DatabaseLocked,
+            "This file is encrypted, enter its passphrase to import it",
+        )
+    })?;
+    let (plaintext, _) = open_with_passphrase(&contents, passphrase)?;
+    Ok(plaintext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open() {
+        let key = EncryptionKey::generate("correct horse").unwrap();
+        let sealed = key.seal(r#"{"data": []}"#).unwrap();
+
+        assert!(is_encrypted(&sealed));
+        assert!(!is_encrypted(r#"{"meta": {}, "data": []}"#));
+        assert!(!sealed.contains("data"));
+        assert_eq!(key.open(&sealed).unwrap(), r#"{"data": []}"#);
+
+        let (plaintext, _) = open_with_passphrase(&sealed, "correct horse").unwrap();
+        assert_eq!(plaintext, r#"{"data": []}"#);
+
+        let err = open_with_passphrase(&sealed, "wrong horse").unwrap_err();
+        assert!(err.is::<WrongPassphrase>());
+
+        let other = EncryptionKey::generate("correct horse").unwrap();
+        assert!(other.open(&sealed).unwrap_err().is::<WrongPassphrase>());
+        assert!(EncryptionKey::generate("short").is_err());
+
+        let mut envelope = parse_envelope(&sealed).unwrap();
+        envelope.kdf_params.m_cost = u32::MAX;
+        let costly = serde_json::to_string(&envelope).unwrap();
+        assert!(open_with_passphrase(&costly, "correct horse").unwrap_err().is::<AppError>());
+    }
+}
@@ -1,58 +1,680 @@
 #![allow(dead_code)]
-use crate::models::{BrowserProfile, BrowserMode, CollectionRecord, Database};
-use crate::utils::get_data_dir;
-use chrono::Utc;
+use crate::crypto::{self, DatabaseLocked, EncryptionKey, WrongPassphrase};
+use crate::duplicates::site_key;
+use crate::error::{AppError, ErrorKind};
+use crate::migrations;
+use crate::models::{
+    BrowserProfile, BrowserMode, CollectionRecord, CollectionRevision, Database, EncryptionStatus,
+    MergeReport, MergeStrategy, MergedCollection, RenamedCollection, SiteEntry, StorageBackend,
+    TrashedCollection, MAX_REVISIONS_PER_COLLECTION,
+};
+use crate::normalize::{canonical_url, canonicalize_sites};
+use crate::sqlite_store::SqliteStore;
+use crate::workspaces::active_workspace_dir;
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
 use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, RwLock};
+use std::time::{Duration, Instant, SystemTime};
 use tracing::{debug, info, instrument, warn};
 
-const DB_FILE_NAME: &str = "db.json";
+pub(crate) const DB_FILE_NAME: &str = "db.json";
+pub(crate) const SQLITE_FILE_NAME: &str = "db.sqlite3";
+const MIGRATED_EXTENSION: &str = "json.migrated";
+const BACKUP_EXTENSION: &str = "json.bak";
+const TEMP_EXTENSION: &str = "json.tmp";
+const LOCK_EXTENSION: &str = "lock";
 
+/// How long a writer waits for another process to finish before giving up
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Exclusive advisory lock on the data directory, released on drop
+#[derive(Debug)]
+pub struct DatabaseLock {
+    _file: fs::File,
+}
+
+/// Persistence operations shared by every storage backend
+pub trait Store: std::fmt::Debug + Send + Sync {
+    fn backend(&self) -> StorageBackend;
+    /// The database file, whose directory also holds its backups
+    fn path(&self) -> &Path;
+
+    // Collections
+    fn insert(&self, record: CollectionRecord) -> Result<CollectionRecord, Box<dyn std::error::Error>>;
+    fn get_all(&self) -> Result<Vec<CollectionRecord>, Box<dyn std::error::Error>>;
+    fn get_by_id(&self, id: u64) -> Result<Option<CollectionRecord>, Box<dyn std::error::Error>>;
+    fn search_by_name(&self, query: &str) -> Result<Vec<CollectionRecord>, Box<dyn std::error::Error>>;
+    /// Replaces the record, keeping the previous version in its revision history
+    fn update(&self, record: CollectionRecord) -> Result<CollectionRecord, Box<dyn std::error::Error>>;
+    /// Soft delete: moves the collection to the trash
+    fn delete_by_id(&self, id: u64) -> Result<bool, Box<dyn std::error::Error>>;
+
+    // Trash
+    fn list_trash(&self) -> Result<Vec<TrashedCollection>, Box<dyn std::error::Error>>;
+    fn restore_from_trash(&self, id: u64) -> Result<Option<CollectionRecord>, Box<dyn std::error::Error>>;
+    fn empty_trash(&self) -> Result<usize, Box<dyn std::error::Error>>;
+    fn purge_trash_before(&self, cutoff: DateTime<Utc>) -> Result<usize, Box<dyn std::error::Error>>;
+    fn get_trash_retention_days(&self) -> Result<u32, Box<dyn std::error::Error>>;
+    fn set_trash_retention_days(&self, days: u32) -> Result<(), Box<dyn std::error::Error>>;
+
+    // Revision history
+    /// Newest revision first
+    fn list_revisions(&self, collection_id: u64) -> Result<Vec<CollectionRevision>, Box<dyn std::error::Error>>;
+    fn get_revision(&self, collection_id: u64, revision: u32) -> Result<Option<CollectionRevision>, Box<dyn std::error::Error>>;
+
+    // Browser profiles
+    fn create_profile(&self, profile: BrowserProfile) -> Result<BrowserProfile, Box<dyn std::error::Error>>;
+    fn get_profile(&self, id: &str) -> Result<Option<BrowserProfile>, Box<dyn std::error::Error>>;
+    fn get_all_profiles(&self) -> Result<Vec<BrowserProfile>, Box<dyn std::error::Error>>;
+    fn update_profile(&self, id: &str, profile: BrowserProfile) -> Result<BrowserProfile, Box<dyn std::error::Error>>;
+    fn delete_profile(&self, id: &str) -> Result<bool, Box<dyn std::error::Error>>;
+
+    // Default browser mode
+    fn get_default_browser_mode(&self) -> Result<BrowserMode, Box<dyn std::error::Error>>;
+    fn set_default_browser_mode(&self, mode: BrowserMode) -> Result<(), Box<dyn std::error::Error>>;
+
+    // Backup and restore
+    fn export_to_json(&self) -> Result<String, Box<dyn std::error::Error>>;
+    fn import_from_json(&self, json_data: String, replace_existing: bool) -> Result<usize, Box<dyn std::error::Error>>;
+    /// Merge an export, handling name clashes as `strategy` says
+    fn merge_from_json(&self, json_data: String, strategy: MergeStrategy) -> Result<MergeReport, Box<dyn std::error::Error>>;
+
+    // Encryption at rest, only offered by the JSON store
+    fn encryption_status(&self) -> EncryptionStatus {
+        EncryptionStatus {
+            encrypted: false,
+            unlocked: true,
+        }
+    }
+    fn unlock(&self, _passphrase: &str) -> Result<(), Box<dyn std::error::Error>> {
+        Err(AppError::validation(ENCRYPTION_UNSUPPORTED).into())
+    }
+    fn enable_encryption(&self, _passphrase: &str) -> Result<(), Box<dyn std::error::Error>> {
+        Err(AppError::validation(ENCRYPTION_UNSUPPORTED).into())
+    }
+    fn change_passphrase(&self, _current: &str, _new: &str) -> Result<(), Box<dyn std::error::Error>> {
+        Err(AppError::validation(ENCRYPTION_UNSUPPORTED).into())
+    }
+    fn disable_encryption(&self, _passphrase: &str) -> Result<(), Box<dyn std::error::Error>> {
+        Err(AppError::validation(ENCRYPTION_UNSUPPORTED).into())
+    }
+    /// Prepare exported JSON for writing next to the database, e.g. as a backup
+    fn seal_for_storage(&self, json_data: String) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(json_data)
+    }
+    /// Reverse of `seal_for_storage` for files this store wrote
+    fn open_from_storage(&self, contents: String) -> Result<String, Box<dyn std::error::Error>> {
+        if crypto::is_encrypted(&contents) {
+            return Err(DatabaseLocked.into());
+        }
+        Ok(contents)
+    }
+}
+
+const ENCRYPTION_UNSUPPORTED: &str = "Encryption is only available for the JSON storage backend";
+
+/// Open the active workspace's store: SQLite once `db.json` has been migrated, JSON otherwise
+pub fn open_store() -> Result<Box<dyn Store>, Box<dyn std::error::Error>> {
+    open_store_in(&active_workspace_dir()?)
+}
+
+/// Open the store in a workspace directory, whether or not it is the active one
+pub fn open_store_in(data_dir: &Path) -> Result<Box<dyn Store>, Box<dyn std::error::Error>> {
+    let sqlite_path = data_dir.join(SQLITE_FILE_NAME);
+
+    if sqlite_path.exists() {
+        Ok(Box::new(SqliteStore::open(&sqlite_path)?))
+    } else {
+        Ok(Box::new(JsonStore::with_path(data_dir.join(DB_FILE_NAME))?))
+    }
+}
+
+/// Stands in for a store that failed to open, so the app can still start and
+/// show why; every operation returns the original error
+#[derive(Debug)]
+pub struct UnavailableStore {
+    path: PathBuf,
+    error: AppError,
+}
+
+impl UnavailableStore {
+    pub fn new(error: AppError) -> Self {
+        let path = active_workspace_dir()
+            .map(|dir| dir.join(DB_FILE_NAME))
+            .unwrap_or_default();
+        Self { path, error }
+    }
+
+    fn fail<T>(&self) -> Result<T, Box<dyn std::error::Error>> {
+        Err(self.error.clone().into())
+    }
+}
+
+impl Store for UnavailableStore {
+    fn backend(&self) -> StorageBackend {
+        StorageBackend::Json
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn insert(&self, _record: CollectionRecord) -> Result<CollectionRecord, Box<dyn std::error::Error>> {
+        self.fail()
+    }
+
+    fn get_all(&self) -> Result<Vec<CollectionRecord>, Box<dyn std::error::Error>> {
+        self.fail()
+    }
+
+    fn get_by_id(&self, _id: u64) -> Result<Option<CollectionRecord>, Box<dyn std::error::Error>> {
+        self.fail()
+    }
+
+    fn search_by_name(&self, _query: &str) -> Result<Vec<CollectionRecord>, Box<dyn std::error::Error>> {
+        self.fail()
+    }
+
+    fn update(&self, _record: CollectionRecord) -> Result<CollectionRecord, Box<dyn std::error::Error>> {
+        self.fail()
+    }
+
+    fn delete_by_id(&self, _id: u64) -> Result<bool, Box<dyn std::error::Error>> {
+        self.fail()
+    }
+
+    fn list_trash(&self) -> Result<Vec<TrashedCollection>, Box<dyn std::error::Error>> {
+        self.fail()
+    }
+
+    fn restore_from_trash(&self, _id: u64) -> Result<Option<CollectionRecord>, Box<dyn std::error::Error>> {
+        self.fail()
+    }
+
+    fn empty_trash(&self) -> Result<usize, Box<dyn std::error::Error>> {
+        self.fail()
+    }
+
+    fn purge_trash_before(&self, _cutoff: DateTime<Utc>) -> Result<usize, Box<dyn std::error::Error>> {
+        self.fail()
+    }
+
+    fn get_trash_retention_days(&self) -> Result<u32, Box<dyn std::error::Error>> {
+        self.fail()
+    }
+
+    fn set_trash_retention_days(&self, _days: u32) -> Result<(), Box<dyn std::error::Error>> {
+        self.fail()
+    }
+
+    fn list_revisions(&self, _collection_id: u64) -> Result<Vec<CollectionRevision>, Box<dyn std::error::Error>> {
+        self.fail()
+    }
+
+    fn get_revision(&self, _collection_id: u64, _revision: u32) -> Result<Option<CollectionRevision>, Box<dyn std::error::Error>> {
+        self.fail()
+    }
+
+    fn create_profile(&self, _profile: BrowserProfile) -> Result<BrowserProfile, Box<dyn std::error::Error>> {
+        self.fail()
+    }
+
+    fn get_profile(&self, _id: &str) -> Result<Option<BrowserProfile>, Box<dyn std::error::Error>> {
+        self.fail()
+    }
+
+    fn get_all_profiles(&self) -> Result<Vec<BrowserProfile>, Box<dyn std::error::Error>> {
+        self.fail()
+    }
+
+    fn update_profile(&self, _id: &str, _profile: BrowserProfile) -> Result<BrowserProfile, Box<dyn std::error::Error>> {
+        self.fail()
+    }
+
+    fn delete_profile(&self, _id: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        self.fail()
+    }
+
+    fn get_default_browser_mode(&self) -> Result<BrowserMode, Box<dyn std::error::Error>> {
+        self.fail()
+    }
+
+    fn set_default_browser_mode(&self, _mode: BrowserMode) -> Result<(), Box<dyn std::error::Error>> {
+        self.fail()
+    }
+
+    fn export_to_json(&self) -> Result<String, Box<dyn std::error::Error>> {
+        self.fail()
+    }
+
+    fn import_from_json(&self, _json_data: String, _replace_existing: bool) -> Result<usize, Box<dyn std::error::Error>> {
+        self.fail()
+    }
+
+    fn merge_from_json(&self, _json_data: String, _strategy: MergeStrategy) -> Result<MergeReport, Box<dyn std::error::Error>> {
+        self.fail()
+    }
+}
+
+/// One-shot move from `db.json` to SQLite.
+///
+/// The SQLite file is built under a temp name and only renamed into place once
+/// complete; `db.json` is then kept as `db.json.migrated` for reference.
+/// Returns the number of collections migrated.
+#[instrument]
+pub fn migrate_json_to_sqlite() -> Result<usize, Box<dyn std::error::Error>> {
+    let data_dir = active_workspace_dir()?;
+    let sqlite_path = data_dir.join(SQLITE_FILE_NAME);
+    if sqlite_path.exists() {
+        return Err(AppError::conflict("Database is already stored in SQLite").into());
+    }
+
+    let json_store = JsonStore::new()?;
+    if json_store.encryption_status().encrypted {
+        return Err(AppError::conflict("Disable encryption before moving the database to SQLite").into());
+    }
+    let _lock = json_store.lock()?;
+    let database = json_store.load()?;
+
+    let temp_path = sqlite_path.with_extension("sqlite3.tmp");
+    if temp_path.exists() {
+        fs::remove_file(&temp_path)?;
+    }
+    SqliteStore::open(&temp_path)?.replace_all(&database)?;
+    fs::rename(&temp_path, &sqlite_path)?;
+
+    if json_store.file_path.exists() {
+        fs::rename(
+            &json_store.file_path,
+            json_store.file_path.with_extension(MIGRATED_EXTENSION),
+        )?;
+    }
+
+    info!(
+        "Migrated {} collections and {} profiles to SQLite",
+        database.data.len(),
+        database.profiles.len()
+    );
+    Ok(database.data.len())
+}
+
+/// Identifies one version of the database file on disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    modified: SystemTime,
+    len: u64,
+}
+
+#[derive(Debug)]
+struct CachedDatabase {
+    database: Database,
+    stamp: Option<FileStamp>,
+}
+
+/// JSON file store with a write-through in-memory copy of the database.
+///
+/// Reads are served from memory until the file's mtime or size changes, which
+/// happens when another process saves, so only the first read pays for a parse.
 #[derive(Debug)]
 pub struct JsonStore {
-    file_path: std::path::PathBuf,
+    file_path: PathBuf,
+    lock_timeout: Duration,
+    cache: Mutex<Option<CachedDatabase>>,
+    /// Set once an encrypted database is unlocked; saves are sealed with it
+    key: RwLock<Option<EncryptionKey>>,
+    /// Whether the file was an envelope when it last had this stamp
+    encrypted: Mutex<Option<(FileStamp, bool)>>,
 }
 
 impl JsonStore {
     pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        let data_dir = get_data_dir()?;
-        let file_path = data_dir.join(DB_FILE_NAME);
+        let data_dir = active_workspace_dir()?;
+        Self::with_path(data_dir.join(DB_FILE_NAME))
+    }
 
+    /// Open a store backed by an arbitrary database file
+    pub fn with_path(file_path: PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
         // Create directory if it doesn't exist
         if let Some(parent) = file_path.parent() {
             fs::create_dir_all(parent)?;
         }
 
-        Ok(Self { file_path })
+        Ok(Self {
+            file_path,
+            lock_timeout: LOCK_TIMEOUT,
+            cache: Mutex::new(None),
+            key: RwLock::new(None),
+            encrypted: Mutex::new(None),
+        })
+    }
+
+    /// Path of the last known good copy, kept next to the database file
+    fn backup_path(&self) -> PathBuf {
+        self.file_path.with_extension(BACKUP_EXTENSION)
+    }
+
+    /// Path of the scratch file new contents are written to before the rename
+    fn temp_path(&self) -> PathBuf {
+        self.file_path.with_extension(TEMP_EXTENSION)
     }
 
+    fn lock_path(&self) -> PathBuf {
+        self.file_path.with_extension(LOCK_EXTENSION)
+    }
+
+    /// Take the cross-process write lock.
+    ///
+    /// Every read-modify-write holds this guard from `load` through `save` so two
+    /// app windows, or the app and a script, can't overwrite each other's changes.
+    /// Waits up to `lock_timeout` for a concurrent writer before failing.
+    #[instrument(skip(self))]
+    pub fn lock(&self) -> Result<DatabaseLock, Box<dyn std::error::Error>> {
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.lock_path())?;
+
+        let started = Instant::now();
+        loop {
+            match file.try_lock() {
+                Ok(()) => {
+                    debug!("Acquired database lock after {:?}", started.elapsed());
+                    return Ok(DatabaseLock { _file: file });
+                }
+                Err(fs::TryLockError::WouldBlock) => {
+                    if started.elapsed() >= self.lock_timeout {
+                        warn!("Timed out waiting for database lock");
+                        return Err(AppError::new(
+                            ErrorKind::DatabaseBusy,
+                            "Database busy: another process is writing to it, try again shortly",
+                        )
+                        .with_details(self.file_path.display().to_string())
+                        .into());
+                    }
+                    std::thread::sleep(LOCK_RETRY_INTERVAL);
+                }
+                Err(fs::TryLockError::Error(e)) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Parse a database document, running any pending schema migrations.
+    ///
+    /// Returns the schema version the document was stored at.
+    pub(crate) fn parse_database(content: &str) -> Result<(Database, u32), Box<dyn std::error::Error>> {
+        let value: serde_json::Value = serde_json::from_str(content)?;
+        let (value, original_version) = migrations::migrate(value)?;
+        let database: Database = serde_json::from_value(value)?;
+        Ok((database, original_version))
+    }
+
+    fn key(&self) -> Option<EncryptionKey> {
+        self.key
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    fn set_key(&self, key: Option<EncryptionKey>) {
+        *self.key.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = key;
+    }
+
+    /// Decrypt file contents with the unlocked key; plaintext passes through
+    fn open_contents(&self, content: String) -> Result<String, Box<dyn std::error::Error>> {
+        if !crypto::is_encrypted(&content) {
+            return Ok(content);
+        }
+        match self.key() {
+            Some(key) => key.open(&content),
+            None => Err(DatabaseLocked.into()),
+        }
+    }
+
+    /// Pre-migration snapshots kept beside the database
+    fn migration_backups(&self) -> Vec<PathBuf> {
+        let stem = self
+            .file_path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or_default();
+        let prefix = format!("{}.v", stem);
+        let Some(Ok(entries)) = self.file_path.parent().map(fs::read_dir) else {
+            return Vec::new();
+        };
+        entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with(&prefix) && name.ends_with(".json.bak"))
+            })
+            .collect()
+    }
+
+    /// Switch keys and rewrite the database, and the copies kept beside it, under the new one.
+    ///
+    /// Copies the current key can't open are left alone rather than lost.
+    fn rekey(&self, key: Option<EncryptionKey>) -> Result<(), Box<dyn std::error::Error>> {
+        let _lock = self.lock()?;
+        let database = self.load()?;
+
+        let mut side_files = Vec::new();
+        for path in self.migration_backups() {
+            match fs::read_to_string(&path).map_err(Into::into).and_then(|c| self.open_contents(c)) {
+                Ok(contents) => side_files.push((path, contents)),
+                Err(e) => warn!("Leaving {} as is, it can't be opened: {}", path.display(), e),
+            }
+        }
+
+        self.set_key(key);
+        self.save(&database)?;
+        // The save moved the old file, under the old key, to the last good copy
+        fs::copy(&self.file_path, self.backup_path())?;
+
+        for (path, contents) in side_files {
+            fs::write(&path, self.seal_for_storage(contents)?)?;
+        }
+        Ok(())
+    }
+
+    /// Check `passphrase` against the encrypted file and unlock with it
+    fn verify_passphrase(&self, passphrase: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let contents = fs::read_to_string(&self.file_path)?;
+        if !crypto::is_encrypted(&contents) {
+            return Err(AppError::conflict("Database is not encrypted").into());
+        }
+        let (_, key) = crypto::open_with_passphrase(&contents, passphrase)?;
+        self.set_key(Some(key));
+        Ok(())
+    }
+
+    fn read_database(&self, path: &Path) -> Result<Database, Box<dyn std::error::Error>> {
+        let content = self.open_contents(fs::read_to_string(path)?)?;
+        let (database, original_version) = Self::parse_database(&content)?;
+
+        // The migrated copy is only persisted on the next save, so keep the
+        // original around in case the upgrade turns out to be wrong
+        if original_version < migrations::CURRENT_VERSION {
+            let backup_path = path.with_extension(format!("v{}.json.bak", original_version));
+            if !backup_path.exists() {
+                fs::copy(path, &backup_path)?;
+                info!(
+                    "Wrote pre-migration backup of schema v{} database to {}",
+                    original_version,
+                    backup_path.display()
+                );
+            }
+        }
+
+        Ok(database)
+    }
+
+    fn file_stamp(&self) -> Option<FileStamp> {
+        let metadata = fs::metadata(&self.file_path).ok()?;
+        Some(FileStamp {
+            modified: metadata.modified().ok()?,
+            len: metadata.len(),
+        })
+    }
+
+    /// Whether `db.json` is an encrypted envelope, only reading the file again
+    /// once it changed on disk; the UI asks on every navigation
+    fn is_encrypted_on_disk(&self) -> bool {
+        let Some(stamp) = self.file_stamp() else {
+            return false;
+        };
+        let mut encrypted = self.encrypted.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        match *encrypted {
+            Some((cached, is_encrypted)) if cached == stamp => is_encrypted,
+            _ => {
+                let is_encrypted = fs::read_to_string(&self.file_path)
+                    .is_ok_and(|contents| crypto::is_encrypted(&contents));
+                *encrypted = Some((stamp, is_encrypted));
+                is_encrypted
+            }
+        }
+    }
+
+    fn cache(&self) -> Result<MutexGuard<'_, Option<CachedDatabase>>, Box<dyn std::error::Error>> {
+        self.cache
+            .lock()
+            .map_err(|_| "Database cache lock poisoned".into())
+    }
+
+    /// Run `f` against the in-memory database, re-reading the file only if it
+    /// changed on disk since it was cached
+    fn with_database<T>(
+        &self,
+        f: impl FnOnce(&Database) -> T,
+    ) -> Result<T, Box<dyn std::error::Error>> {
+        let stamp = self.file_stamp();
+        let mut cache = self.cache()?;
+
+        match cache.as_ref() {
+            Some(cached) if stamp.is_some() && cached.stamp == stamp => Ok(f(&cached.database)),
+            _ => {
+                let database = self.read_from_disk()?;
+                let result = f(&database);
+                *cache = Some(CachedDatabase { database, stamp });
+                Ok(result)
+            }
+        }
+    }
+
+    /// Load a copy of the database for a read-modify-write cycle
     #[instrument]
     pub fn load(&self) -> Result<Database, Box<dyn std::error::Error>> {
+        self.with_database(Database::clone)
+    }
+
+    fn read_from_disk(&self) -> Result<Database, Box<dyn std::error::Error>> {
         if !self.file_path.exists() {
             info!("Database file not found, creating new database");
             return Ok(Database::default());
         }
 
-        let content = fs::read_to_string(&self.file_path)?;
-        let database: Database = serde_json::from_str(&content)?;
+        let database = match self.read_database(&self.file_path) {
+            Ok(database) => database,
+            // Never fall back to an older copy: the next save would clobber the newer file
+            Err(e) if e.is::<migrations::UnsupportedVersion>() => return Err(e),
+            // Nor when the file is fine but can't be decrypted yet
+            Err(e) if e.is::<DatabaseLocked>() || e.is::<WrongPassphrase>() => return Err(e),
+            Err(e) => {
+                // A crash mid-save can no longer truncate db.json, but the file may
+                // still be damaged by hand edits or disk errors - try the backup
+                let backup_path = self.backup_path();
+                if !backup_path.exists() {
+                    return Err(e);
+                }
+
+                warn!(
+                    "Failed to read database file ({}), falling back to backup at {}",
+                    e,
+                    backup_path.display()
+                );
+                self.read_database(&backup_path).map_err(|_| e)?
+            }
+        };
 
         debug!("Loaded database with {} records", database.data.len());
         Ok(database)
     }
 
+    /// Atomically replace the database file.
+    ///
+    /// The new contents are written and fsynced to a temp file which is then
+    /// renamed over `db.json`, so readers only ever see the old or the new file.
+    /// The previous file is copied to `db.json.bak` first if it is still valid JSON.
     #[instrument(skip(database))]
     pub fn save(&self, database: &Database) -> Result<(), Box<dyn std::error::Error>> {
-        let content = serde_json::to_string_pretty(database)?;
-        fs::write(&self.file_path, content)?;
+        let content = self.seal_for_storage(serde_json::to_string_pretty(database)?)?;
+
+        let temp_path = self.temp_path();
+        {
+            let mut file = fs::File::create(&temp_path)?;
+            file.write_all(content.as_bytes())?;
+            file.sync_all()?;
+        }
+
+        // Keep the previous good copy, but never overwrite it with a damaged file
+        if let Ok(previous) = fs::read(&self.file_path) {
+            if serde_json::from_slice::<serde::de::IgnoredAny>(&previous).is_ok() {
+                fs::copy(&self.file_path, self.backup_path())?;
+            } else {
+                warn!("Existing database file is not valid JSON, keeping previous backup");
+            }
+        }
+
+        fs::rename(&temp_path, &self.file_path)?;
+        Self::sync_parent_dir(&self.file_path);
+
+        // Write-through: writers hold the cross-process lock, so nobody else can
+        // have replaced the file between the rename and this stat
+        *self.cache()? = Some(CachedDatabase {
+            database: database.clone(),
+            stamp: self.file_stamp(),
+        });
 
         info!("Saved database with {} records", database.data.len());
         Ok(())
     }
 
+    /// Flush the directory entry so the rename survives a power loss
+    fn sync_parent_dir(path: &Path) {
+        #[cfg(unix)]
+        if let Some(parent) = path.parent() {
+            if let Err(e) = fs::File::open(parent).and_then(|dir| dir.sync_all()) {
+                warn!("Failed to sync data directory {}: {}", parent.display(), e);
+            }
+        }
+
+        #[cfg(not(unix))]
+        let _ = path;
+    }
+
+}
+
+impl Store for JsonStore {
+    fn backend(&self) -> StorageBackend {
+        StorageBackend::Json
+    }
+
+    fn path(&self) -> &Path {
+        &self.file_path
+    }
+
     #[instrument(skip(self, record))]
-    pub fn insert(
+    fn insert(
         &self,
         mut record: CollectionRecord,
     ) -> Result<CollectionRecord, Box<dyn std::error::Error>> {
+        let _lock = self.lock()?;
         let mut database = self.load()?;
 
         // Auto-assign ID
@@ -72,57 +694,57 @@ impl JsonStore {
     }
 
     #[instrument]
-    pub fn get_all(&self) -> Result<Vec<CollectionRecord>, Box<dyn std::error::Error>> {
-        let database = self.load()?;
-        Ok(database.data)
+    fn get_all(&self) -> Result<Vec<CollectionRecord>, Box<dyn std::error::Error>> {
+        self.with_database(|database| database.data.clone())
     }
 
     #[instrument]
-    pub fn get_by_id(
+    fn get_by_id(
         &self,
         id: u64,
     ) -> Result<Option<CollectionRecord>, Box<dyn std::error::Error>> {
-        let database = self.load()?;
-        let record = database.data.into_iter().find(|r| r.id == id);
-        Ok(record)
+        self.with_database(|database| database.data.iter().find(|r| r.id == id).cloned())
     }
 
     #[instrument]
-    pub fn search_by_name(
+    fn search_by_name(
         &self,
         query: &str,
     ) -> Result<Vec<CollectionRecord>, Box<dyn std::error::Error>> {
-        let database = self.load()?;
-        let results: Vec<CollectionRecord> = database
-            .data
-            .into_iter()
-            .filter(|record| record.name.to_lowercase().contains(&query.to_lowercase()))
-            .collect();
+        let results: Vec<CollectionRecord> = self.with_database(|database| {
+            database
+                .data
+                .iter()
+                .filter(|record| record.name.to_lowercase().contains(&query.to_lowercase()))
+                .cloned()
+                .collect()
+        })?;
 
         debug!("Search for '{}' returned {} results", query, results.len());
         Ok(results)
     }
 
     #[instrument(skip(self, updated_record))]
-    pub fn update(
+    fn update(
         &self,
         updated_record: CollectionRecord,
     ) -> Result<CollectionRecord, Box<dyn std::error::Error>> {
+        let _lock = self.lock()?;
         let mut database = self.load()?;
         
         // Find and replace the record with the given ID
-        let mut found = false;
-        for record in &mut database.data {
-            if record.id == updated_record.id {
-                *record = updated_record.clone();
-                record.updated_at = Utc::now(); // Ensure updated timestamp
-                found = true;
-                break;
-            }
-        }
+        let Some(record) = database
+            .data
+            .iter_mut()
+            .find(|record| record.id == updated_record.id)
+        else {
+            return Err(AppError::not_found(format!("Record with ID {} not found for update", updated_record.id)).into());
+        };
 
-        if !found {
-            return Err(format!("Record with ID {} not found for update", updated_record.id).into());
+        let previous = std::mem::replace(record, updated_record.clone());
+        record.updated_at = Utc::now(); // Ensure updated timestamp
+        if is_revision_worthy(&previous, record) {
+            record_revision(&mut database.revisions, &previous);
         }
 
         database.meta.last_updated = Utc::now();
@@ -134,17 +756,20 @@ impl JsonStore {
     }
 
     #[instrument]
-    pub fn delete_by_id(&self, id: u64) -> Result<bool, Box<dyn std::error::Error>> {
+    fn delete_by_id(&self, id: u64) -> Result<bool, Box<dyn std::error::Error>> {
+        let _lock = self.lock()?;
         let mut database = self.load()?;
-        let initial_len = database.data.len();
-
-        database.data.retain(|record| record.id != id);
 
-        if database.data.len() < initial_len {
+        if let Some(index) = database.data.iter().position(|record| record.id == id) {
+            let record = database.data.remove(index);
+            database.trash.push(TrashedCollection {
+                record,
+                deleted_at: Utc::now(),
+            });
             database.meta.record_count = database.data.len();
             database.meta.last_updated = Utc::now();
             self.save(&database)?;
-            info!("Deleted record with ID: {}", id);
+            info!("Moved record with ID {} to trash", id);
             Ok(true)
         } else {
             warn!("Record with ID {} not found for deletion", id);
@@ -152,18 +777,125 @@ impl JsonStore {
         }
     }
 
+    // Trash
+
+    #[instrument]
+    fn list_trash(&self) -> Result<Vec<TrashedCollection>, Box<dyn std::error::Error>> {
+        self.with_database(|database| database.trash.clone())
+    }
+
+    #[instrument]
+    fn restore_from_trash(
+        &self,
+        id: u64,
+    ) -> Result<Option<CollectionRecord>, Box<dyn std::error::Error>> {
+        let _lock = self.lock()?;
+        let mut database = self.load()?;
+
+        let Some(index) = database.trash.iter().position(|t| t.record.id == id) else {
+            warn!("Record with ID {} not found in trash", id);
+            return Ok(None);
+        };
+
+        let mut record = database.trash.remove(index).record;
+        restore_record_id(&mut database, &mut record);
+        database.data.push(record.clone());
+        database.meta.record_count = database.data.len();
+        database.meta.last_updated = Utc::now();
+        database.meta.last_updated_id = record.id;
+
+        self.save(&database)?;
+        info!("Restored record with ID {} from trash", record.id);
+        Ok(Some(record))
+    }
+
+    #[instrument]
+    fn empty_trash(&self) -> Result<usize, Box<dyn std::error::Error>> {
+        self.purge_trash_before(DateTime::<Utc>::MAX_UTC)
+    }
+
+    #[instrument]
+    fn purge_trash_before(&self, cutoff: DateTime<Utc>) -> Result<usize, Box<dyn std::error::Error>> {
+        let _lock = self.lock()?;
+        let mut database = self.load()?;
+        let initial_len = database.trash.len();
+
+        database.trash.retain(|trashed| trashed.deleted_at >= cutoff);
+        let purged = initial_len - database.trash.len();
+
+        if purged > 0 {
+            prune_orphan_revisions(&mut database);
+            database.meta.last_updated = Utc::now();
+            self.save(&database)?;
+            info!("Permanently deleted {} collections from trash", purged);
+        }
+        Ok(purged)
+    }
+
+    #[instrument]
+    fn get_trash_retention_days(&self) -> Result<u32, Box<dyn std::error::Error>> {
+        self.with_database(|database| database.meta.trash_retention_days)
+    }
+
+    #[instrument]
+    fn set_trash_retention_days(&self, days: u32) -> Result<(), Box<dyn std::error::Error>> {
+        let _lock = self.lock()?;
+        let mut database = self.load()?;
+        database.meta.trash_retention_days = days;
+        database.meta.last_updated = Utc::now();
+
+        self.save(&database)?;
+        info!("Updated trash retention to {} days", days);
+        Ok(())
+    }
+
+    // Revision History
+
+    #[instrument]
+    fn list_revisions(
+        &self,
+        collection_id: u64,
+    ) -> Result<Vec<CollectionRevision>, Box<dyn std::error::Error>> {
+        self.with_database(|database| {
+            let mut revisions: Vec<CollectionRevision> = database
+                .revisions
+                .iter()
+                .filter(|revision| revision.collection_id == collection_id)
+                .cloned()
+                .collect();
+            revisions.sort_by_key(|revision| std::cmp::Reverse(revision.revision));
+            revisions
+        })
+    }
+
+    #[instrument]
+    fn get_revision(
+        &self,
+        collection_id: u64,
+        revision: u32,
+    ) -> Result<Option<CollectionRevision>, Box<dyn std::error::Error>> {
+        self.with_database(|database| {
+            database
+                .revisions
+                .iter()
+                .find(|r| r.collection_id == collection_id && r.revision == revision)
+                .cloned()
+        })
+    }
+
     // Browser Profile Management
     
     #[instrument(skip(self, profile))]
-    pub fn create_profile(
+    fn create_profile(
         &self,
         profile: BrowserProfile,
     ) -> Result<BrowserProfile, Box<dyn std::error::Error>> {
+        let _lock = self.lock()?;
         let mut database = self.load()?;
         
         // Check for duplicate profile ID
         if database.profiles.iter().any(|p| p.id == profile.id) {
-            return Err(format!("Profile with ID '{}' already exists", profile.id).into());
+            return Err(AppError::conflict(format!("Profile with ID '{}' already exists", profile.id)).into());
         }
         
         database.profiles.push(profile.clone());
@@ -176,27 +908,25 @@ impl JsonStore {
     }
     
     #[instrument]
-    pub fn get_profile(
+    fn get_profile(
         &self,
         id: &str,
     ) -> Result<Option<BrowserProfile>, Box<dyn std::error::Error>> {
-        let database = self.load()?;
-        let profile = database.profiles.into_iter().find(|p| p.id == id);
-        Ok(profile)
+        self.with_database(|database| database.profiles.iter().find(|p| p.id == id).cloned())
     }
     
     #[instrument]
-    pub fn get_all_profiles(&self) -> Result<Vec<BrowserProfile>, Box<dyn std::error::Error>> {
-        let database = self.load()?;
-        Ok(database.profiles)
+    fn get_all_profiles(&self) -> Result<Vec<BrowserProfile>, Box<dyn std::error::Error>> {
+        self.with_database(|database| database.profiles.clone())
     }
     
     #[instrument(skip(self, updated_profile))]
-    pub fn update_profile(
+    fn update_profile(
         &self,
         id: &str,
         updated_profile: BrowserProfile,
     ) -> Result<BrowserProfile, Box<dyn std::error::Error>> {
+        let _lock = self.lock()?;
         let mut database = self.load()?;
         
         // Find and replace the profile with the given ID
@@ -211,7 +941,7 @@ impl JsonStore {
         }
         
         if !found {
-            return Err(format!("Profile with ID '{}' not found for update", id).into());
+            return Err(AppError::not_found(format!("Profile with ID '{}' not found for update", id)).into());
         }
         
         database.meta.last_updated = Utc::now();
@@ -222,7 +952,8 @@ impl JsonStore {
     }
     
     #[instrument]
-    pub fn delete_profile(&self, id: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    fn delete_profile(&self, id: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        let _lock = self.lock()?;
         let mut database = self.load()?;
         let initial_len = database.profiles.len();
         
@@ -242,16 +973,16 @@ impl JsonStore {
     // Default Browser Mode Management
     
     #[instrument]
-    pub fn get_default_browser_mode(&self) -> Result<BrowserMode, Box<dyn std::error::Error>> {
-        let database = self.load()?;
-        Ok(database.meta.default_browser_mode)
+    fn get_default_browser_mode(&self) -> Result<BrowserMode, Box<dyn std::error::Error>> {
+        self.with_database(|database| database.meta.default_browser_mode.clone())
     }
     
     #[instrument]
-    pub fn set_default_browser_mode(
+    fn set_default_browser_mode(
         &self,
         mode: BrowserMode,
     ) -> Result<(), Box<dyn std::error::Error>> {
+        let _lock = self.lock()?;
         let mut database = self.load()?;
         database.meta.default_browser_mode = mode.clone();
         database.meta.last_updated = Utc::now();
@@ -263,57 +994,450 @@ impl JsonStore {
     }
 
     #[instrument(skip(self))]
-    pub fn export_to_json(&self) -> Result<String, Box<dyn std::error::Error>> {
-        let database = self.load()?;
-        let json_data = serde_json::to_string_pretty(&database)?;
+    fn export_to_json(&self) -> Result<String, Box<dyn std::error::Error>> {
+        let json_data = self.with_database(serde_json::to_string_pretty)??;
         info!("Exported database to JSON, {} characters", json_data.len());
         Ok(json_data)
     }
 
     #[instrument(skip(self, json_data), fields(data_length = json_data.len()))]
-    pub fn import_from_json(&self, json_data: String, replace_existing: bool) -> Result<usize, Box<dyn std::error::Error>> {
-        let import_database: Database = serde_json::from_str(&json_data)?;
+    fn import_from_json(&self, json_data: String, replace_existing: bool) -> Result<usize, Box<dyn std::error::Error>> {
+        if !replace_existing {
+            return Ok(self.merge_from_json(json_data, MergeStrategy::Skip)?.created_count());
+        }
+        let (import_database, _) = Self::parse_database(&json_data)?;
         
-        if replace_existing {
-            // Replace entire database
-            self.save(&import_database)?;
-            info!("Replaced entire database with {} collections", import_database.data.len());
-            Ok(import_database.data.len())
-        } else {
-            // Merge with existing database
-            let mut existing_database = self.load()?;
-            let mut imported_count = 0;
-            
-            for import_record in import_database.data {
-                // Check if collection with same name already exists
-                let name_exists = existing_database.data.iter().any(|existing| 
-                    existing.name.to_lowercase() == import_record.name.to_lowercase()
-                );
-                
-                if !name_exists {
-                    // Assign new ID
-                    let new_id = existing_database.meta.max_id + 1;
-                    existing_database.meta.max_id = new_id;
-                    
-                    let mut new_record = import_record;
-                    new_record.id = new_id;
-                    new_record.updated_at = Utc::now();
-                    
-                    existing_database.data.push(new_record);
-                    imported_count += 1;
-                } else {
-                    warn!("Skipping collection '{}' - name already exists", import_record.name);
+        let _lock = self.lock()?;
+
+        // Replace entire database
+        self.save(&import_database)?;
+        info!("Replaced entire database with {} collections", import_database.data.len());
+        Ok(import_database.data.len())
+    }
+
+    #[instrument(skip(self, json_data), fields(data_length = json_data.len()))]
+    fn merge_from_json(&self, json_data: String, strategy: MergeStrategy) -> Result<MergeReport, Box<dyn std::error::Error>> {
+        let (import_database, _) = Self::parse_database(&json_data)?;
+
+        let _lock = self.lock()?;
+        let mut existing_database = self.load()?;
+        let report = merge_databases(&mut existing_database, import_database, strategy);
+
+        self.save(&existing_database)?;
+        info!("Merged database, imported {} new collections", report.created_count());
+        Ok(report)
+    }
+
+    fn encryption_status(&self) -> EncryptionStatus {
+        let encrypted = self.is_encrypted_on_disk();
+        EncryptionStatus {
+            encrypted,
+            unlocked: !encrypted || self.key().is_some(),
+        }
+    }
+
+    #[instrument(skip(self, passphrase))]
+    fn unlock(&self, passphrase: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.verify_passphrase(passphrase)?;
+        // Anything cached was read before the key was known
+        *self.cache()? = None;
+        info!("Unlocked encrypted database");
+        Ok(())
+    }
+
+    #[instrument(skip(self, passphrase))]
+    fn enable_encryption(&self, passphrase: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if self.encryption_status().encrypted {
+            return Err(AppError::conflict("Database is already encrypted").into());
+        }
+        self.rekey(Some(EncryptionKey::generate(passphrase)?))?;
+        info!("Enabled database encryption");
+        Ok(())
+    }
+
+    #[instrument(skip(self, current, new))]
+    fn change_passphrase(&self, current: &str, new: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let key = EncryptionKey::generate(new)?;
+        self.verify_passphrase(current)?;
+        self.rekey(Some(key))?;
+        info!("Changed database passphrase");
+        Ok(())
+    }
+
+    #[instrument(skip(self, passphrase))]
+    fn disable_encryption(&self, passphrase: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.verify_passphrase(passphrase)?;
+        self.rekey(None)?;
+        info!("Disabled database encryption");
+        Ok(())
+    }
+
+    fn seal_for_storage(&self, json_data: String) -> Result<String, Box<dyn std::error::Error>> {
+        match self.key() {
+            Some(key) => key.seal(&json_data),
+            None => Ok(json_data),
+        }
+    }
+
+    fn open_from_storage(&self, contents: String) -> Result<String, Box<dyn std::error::Error>> {
+        self.open_contents(contents)
+    }
+}
+
+/// Only edits to the name, sites or config are worth a revision
+pub fn is_revision_worthy(previous: &CollectionRecord, updated: &CollectionRecord) -> bool {
+    previous.name != updated.name || previous.sites != updated.sites || previous.config != updated.config
+}
+
+/// Snapshot `previous` into the revision history, dropping the collection's
+/// oldest revisions beyond `MAX_REVISIONS_PER_COLLECTION`
+pub fn record_revision(revisions: &mut Vec<CollectionRevision>, previous: &CollectionRecord) {
+    let next = revisions
+        .iter()
+        .filter(|r| r.collection_id == previous.id)
+        .map(|r| r.revision)
+        .max()
+        .unwrap_or(0)
+        + 1;
+
+    revisions.push(CollectionRevision {
+        collection_id: previous.id,
+        revision: next,
+        name: previous.name.clone(),
+        sites: previous.sites.clone(),
+        config: previous.config.clone(),
+        saved_at: previous.updated_at,
+    });
+
+    let oldest_kept = next.saturating_sub(MAX_REVISIONS_PER_COLLECTION as u32);
+    revisions.retain(|r| r.collection_id != previous.id || r.revision > oldest_kept);
+}
+
+/// Drop the history of collections that are neither live nor in the trash
+fn prune_orphan_revisions(database: &mut Database) {
+    let Database {
+        data,
+        trash,
+        revisions,
+        ..
+    } = database;
+    revisions.retain(|revision| {
+        data.iter().any(|record| record.id == revision.collection_id)
+            || trash.iter().any(|t| t.record.id == revision.collection_id)
+    });
+}
+
+/// Keep a restored collection's ID unless a live collection took it meanwhile
+/// (e.g. after a replace-import), in which case it gets a fresh one
+pub fn restore_record_id(database: &mut Database, record: &mut CollectionRecord) {
+    if database.data.iter().any(|existing| existing.id == record.id) {
+        database.meta.max_id += 1;
+        record.id = database.meta.max_id;
+    }
+    database.meta.max_id = database.meta.max_id.max(record.id);
+}
+
+/// `name`, or the first of "name (2)", "name (3)", ... that isn't taken
+pub fn free_name(name: &str, taken: impl Fn(&str) -> bool) -> String {
+    if !taken(name) {
+        return name.to_string();
+    }
+    (2..)
+        .map(|n| format!("{} ({})", name.trim(), n))
+        .find(|name| !taken(name))
+        .unwrap_or_default()
+}
+
+/// Merge the collections of `incoming` into `existing`, handling name clashes
+/// (compared case-insensitively) as `strategy` says. Profiles whose ID is not
+/// taken yet are added too; see `selection` for renaming the ones that clash
+/// before merging. Existing collections that get overwritten or gain sites
+/// keep their ID and get a revision.
+///
+/// Shared by every `Store` so JSON and SQLite imports behave the same, and
+/// free of I/O so a dry run can merge into a copy.
+pub fn merge_databases(existing: &mut Database, incoming: Database, strategy: MergeStrategy) -> MergeReport {
+    let mut report = MergeReport::default();
+    let known_profiles = existing.profiles.len();
+
+    for mut profile in incoming.profiles {
+        if existing.profiles.iter().any(|p| p.id == profile.id) {
+            continue;
+        }
+        // The existing default stays the default
+        profile.is_default = false;
+        existing.profiles.push(profile);
+    }
+
+    for mut import_record in incoming.data {
+        let name_taken = |database: &Database, name: &str| {
+            database
+                .data
+                .iter()
+                .position(|record| record.name.to_lowercase() == name.to_lowercase())
+        };
+
+        let Some(position) = name_taken(existing, &import_record.name) else {
+            report.added.push(import_record.name.clone());
+            add_record(existing, import_record);
+            continue;
+        };
+
+        match strategy {
+            MergeStrategy::Skip => {
+                warn!("Skipping collection '{}' - name already exists", import_record.name);
+                report.skipped.push(import_record.name);
+            }
+            MergeStrategy::Rename => {
+                let from = import_record.name.clone();
+                let to = free_name(&from, |name| name_taken(existing, name).is_some());
+                import_record.name = to.clone();
+                add_record(existing, import_record);
+                report.renamed.push(RenamedCollection { from, to });
+            }
+            MergeStrategy::Overwrite => {
+                let record = &mut existing.data[position];
+                let previous = record.clone();
+                record.name = import_record.name.clone();
+                record.sites = import_record.sites;
+                record.config = import_record.config;
+                canonicalize_sites(&mut record.sites, &record.config);
+                record.updated_at = Utc::now();
+                if is_revision_worthy(&previous, record) {
+                    record_revision(&mut existing.revisions, &previous);
                 }
+                report.overwritten.push(import_record.name);
+            }
+            MergeStrategy::Union => {
+                let record = &mut existing.data[position];
+                // Incoming sites are compared under the existing collection's settings
+                let options = record.config.url_normalization.clone().unwrap_or_default();
+                let mut keys: HashSet<String> = record.sites.iter().map(|site| site_key(site, record)).collect();
+                let mut added: Vec<SiteEntry> = import_record
+                    .sites
+                    .into_iter()
+                    .filter(|site| keys.insert(canonical_url(&site.url, &options)))
+                    .collect();
+                canonicalize_sites(&mut added, &record.config);
+
+                if !added.is_empty() {
+                    let previous = record.clone();
+                    record.sites.extend(added.iter().cloned());
+                    record.updated_at = Utc::now();
+                    record_revision(&mut existing.revisions, &previous);
+                }
+                report.merged.push(MergedCollection {
+                    name: import_record.name,
+                    added_sites: added.len(),
+                });
             }
-            
-            // Update metadata
-            existing_database.meta.record_count = existing_database.data.len();
-            existing_database.meta.last_updated = Utc::now();
-            existing_database.meta.last_updated_id = existing_database.meta.max_id;
-            
-            self.save(&existing_database)?;
-            info!("Merged database, imported {} new collections", imported_count);
-            Ok(imported_count)
         }
     }
+
+    // Update metadata, unless every collection was skipped; an import that
+    // changed nothing must not look like a write to the undo journal
+    let changed = existing.profiles.len() > known_profiles
+        || report.created_count() > 0
+        || !report.overwritten.is_empty()
+        || report.merged.iter().any(|merged| merged.added_sites > 0);
+    if changed {
+        existing.meta.record_count = existing.data.len();
+        existing.meta.last_updated = Utc::now();
+        existing.meta.last_updated_id = existing.meta.max_id;
+    }
+
+    report
+}
+
+/// Append an imported collection under the next free ID
+fn add_record(database: &mut Database, mut record: CollectionRecord) {
+    database.meta.max_id += 1;
+    record.id = database.meta.max_id;
+    record.updated_at = Utc::now();
+    database.data.push(record);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{json_store as temp_store, sample_record};
+
+    #[test]
+    fn test_concurrent_writer_fails_with_busy_error() {
+        let store = temp_store("lock");
+        let mut other = JsonStore::with_path(store.file_path.clone()).unwrap();
+        other.lock_timeout = Duration::from_millis(100);
+
+        let guard = store.lock().unwrap();
+        let err = other.insert(sample_record("blocked", &[])).unwrap_err();
+        assert!(err.to_string().starts_with("Database busy"));
+
+        drop(guard);
+        other.insert(sample_record("unblocked", &[])).unwrap();
+        assert_eq!(store.load().unwrap().data.len(), 1);
+    }
+
+    #[test]
+    fn test_cached_reads_see_writes_from_other_processes() {
+        let store = temp_store("cache");
+        store.insert(sample_record("first", &[])).unwrap();
+        assert_eq!(store.get_all().unwrap().len(), 1);
+
+        // A second handle stands in for another app window
+        let other = JsonStore::with_path(store.file_path.clone()).unwrap();
+        other.insert(sample_record("second", &[])).unwrap();
+
+        assert_eq!(store.get_all().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_delete_moves_collection_to_trash() {
+        let store = temp_store("trash");
+        let record = store.insert(sample_record("first", &[])).unwrap();
+
+        assert!(store.delete_by_id(record.id).unwrap());
+        assert!(store.get_by_id(record.id).unwrap().is_none());
+        assert_eq!(store.list_trash().unwrap().len(), 1);
+
+        let restored = store.restore_from_trash(record.id).unwrap().unwrap();
+        assert_eq!(restored.id, record.id);
+        assert!(store.list_trash().unwrap().is_empty());
+
+        store.delete_by_id(record.id).unwrap();
+        assert_eq!(store.purge_trash_before(Utc::now() - chrono::Duration::days(1)).unwrap(), 0);
+        assert_eq!(store.empty_trash().unwrap(), 1);
+        assert_eq!(store.load().unwrap().meta.record_count, 0);
+    }
+
+    #[test]
+    fn test_update_records_bounded_revisions() {
+        let store = temp_store("revisions");
+        let mut record = store.insert(sample_record("first", &[])).unwrap();
+
+        // An update that changes nothing is not worth a revision
+        store.update(record.clone()).unwrap();
+        assert!(store.list_revisions(record.id).unwrap().is_empty());
+
+        for i in 0..MAX_REVISIONS_PER_COLLECTION + 5 {
+            record.name = format!("edit {}", i);
+            store.update(record.clone()).unwrap();
+        }
+
+        let revisions = store.list_revisions(record.id).unwrap();
+        assert_eq!(revisions.len(), MAX_REVISIONS_PER_COLLECTION);
+        assert_eq!(revisions[0].revision as usize, MAX_REVISIONS_PER_COLLECTION + 5);
+        assert_eq!(revisions[0].name, format!("edit {}", MAX_REVISIONS_PER_COLLECTION + 3));
+        assert!(store.get_revision(record.id, 1).unwrap().is_none());
+
+        store.delete_by_id(record.id).unwrap();
+        store.empty_trash().unwrap();
+        assert!(store.list_revisions(record.id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_encryption_round_trip() {
+        let store = temp_store("encryption");
+        store.insert(sample_record("secret collection", &[])).unwrap();
+        store.enable_encryption("correct horse").unwrap();
+
+        for path in [store.file_path.clone(), store.backup_path()] {
+            let contents = fs::read_to_string(path).unwrap();
+            assert!(crypto::is_encrypted(&contents));
+            assert!(!contents.contains("secret collection"));
+        }
+
+        // A fresh handle, as on the next launch, must be unlocked first
+        let reopened = JsonStore::with_path(store.file_path.clone()).unwrap();
+        assert!(!reopened.encryption_status().unlocked);
+        assert!(reopened.get_all().unwrap_err().is::<DatabaseLocked>());
+        assert!(reopened.unlock("wrong horse").unwrap_err().is::<WrongPassphrase>());
+        reopened.unlock("correct horse").unwrap();
+        assert_eq!(reopened.get_all().unwrap()[0].name, "secret collection");
+
+        reopened.change_passphrase("correct horse", "battery staple").unwrap();
+        reopened.disable_encryption("battery staple").unwrap();
+        let contents = fs::read_to_string(&store.file_path).unwrap();
+        assert!(contents.contains("secret collection"));
+        assert!(!reopened.encryption_status().encrypted);
+    }
+
+    #[test]
+    fn test_save_keeps_previous_copy_as_backup() {
+        let store = temp_store("backup");
+        store.insert(sample_record("first", &[])).unwrap();
+        store.insert(sample_record("second", &[])).unwrap();
+
+        assert!(!store.temp_path().exists());
+        let backup = store.read_database(&store.backup_path()).unwrap();
+        assert_eq!(backup.data.len(), 1);
+        assert_eq!(store.load().unwrap().data.len(), 2);
+    }
+
+    #[test]
+    fn test_load_falls_back_to_backup_when_main_file_is_corrupt() {
+        let store = temp_store("corrupt");
+        store.insert(sample_record("first", &[])).unwrap();
+        store.insert(sample_record("second", &[])).unwrap();
+
+        // Simulate a truncated write
+        fs::write(&store.file_path, "{\"meta\": {").unwrap();
+
+        let database = store.load().unwrap();
+        assert_eq!(database.data.len(), 1);
+        assert_eq!(database.data[0].name, "first");
+
+        // Saving over the damaged file must not clobber the good backup
+        store.insert(sample_record("third", &[])).unwrap();
+        let backup = store.read_database(&store.backup_path()).unwrap();
+        assert_eq!(backup.data.len(), 1);
+        assert_eq!(store.load().unwrap().data.len(), 2);
+    }
+
+    #[test]
+    fn test_merge_strategies() {
+        let store = temp_store("merge");
+        store.insert(sample_record("Work", &["https://a.com/", "https://b.com"])).unwrap();
+        store.insert(sample_record("Work (2)", &["https://c.com"])).unwrap();
+
+        let incoming = Database {
+            data: vec![
+                sample_record("work", &["https://a.com", "https://d.com#top"]),
+                sample_record("News", &["https://news.com"]),
+            ],
+            ..Database::default()
+        };
+        let merge = |strategy| {
+            let mut database = store.load().unwrap();
+            let report = merge_databases(&mut database, incoming.clone(), strategy);
+            (database, report)
+        };
+
+        let (database, report) = merge(MergeStrategy::Skip);
+        assert_eq!((report.added, report.skipped), (vec!["News".to_string()], vec!["work".to_string()]));
+        assert_eq!(database.data.len(), 3);
+
+        let (database, report) = merge(MergeStrategy::Rename);
+        assert_eq!(report.renamed[0].to, "work (3)");
+        assert_eq!(database.data[2].name, "work (3)");
+        assert_eq!(database.data.len(), 4);
+
+        let (database, report) = merge(MergeStrategy::Overwrite);
+        assert_eq!(report.overwritten, vec!["work".to_string()]);
+        assert_eq!(database.data[0].sites.len(), 2);
+        assert_eq!(database.data[0].sites[1].url, "https://d.com#top");
+        assert_eq!(database.revisions.len(), 1);
+
+        let (database, report) = merge(MergeStrategy::Union);
+        assert_eq!(report.merged[0].added_sites, 1);
+        let urls: Vec<&str> = database.data[0].sites.iter().map(|site| site.url.as_str()).collect();
+        assert_eq!(urls, vec!["https://a.com/", "https://b.com", "https://d.com#top"]);
+        assert_eq!(database.revisions[0].sites.len(), 2);
+
+        // Nothing above was written
+        assert_eq!(store.load().unwrap().data.len(), 2);
+        let json_data = serde_json::to_string(&incoming).unwrap();
+        assert_eq!(store.merge_from_json(json_data, MergeStrategy::Union).unwrap().created_count(), 1);
+        assert_eq!(store.get_by_id(1).unwrap().unwrap().sites.len(), 3);
+        assert_eq!(store.list_revisions(1).unwrap().len(), 1);
+    }
 }
@@ -0,0 +1,151 @@
+use crate::models::{
+    CollectionRecord, DedupeStrategy, DuplicateGroup, DuplicateReport, DuplicateSite, SiteEntry,
+};
+use crate::normalize::canonical_url;
+use std::collections::{BTreeMap, HashMap};
+use tracing::{info, instrument};
+
+/// The key sites are compared by; records saved before canonical URLs existed
+/// are canonicalized on the fly with their collection's settings
+pub fn site_key(site: &SiteEntry, record: &CollectionRecord) -> String {
+    site.canonical_url.clone().unwrap_or_else(|| {
+        canonical_url(
+            &site.url,
+            &record.config.url_normalization.clone().unwrap_or_default(),
+        )
+    })
+}
+
+fn group(canonical_url: String, sites: Vec<DuplicateSite>) -> DuplicateGroup {
+    let mut collection_ids: Vec<u64> = sites.iter().map(|site| site.collection_id).collect();
+    collection_ids.dedup();
+    DuplicateGroup {
+        canonical_url,
+        collection_ids,
+        sites,
+    }
+}
+
+/// Group every site by canonical URL and report those saved more than once,
+/// both inside a single collection and across collections
+#[instrument(skip(records), fields(collection_count = records.len()))]
+pub fn find_duplicates(records: &[CollectionRecord]) -> DuplicateReport {
+    // BTreeMap keeps the report in a stable, URL-sorted order
+    let mut by_url: BTreeMap<String, Vec<DuplicateSite>> = BTreeMap::new();
+    let mut ordered: Vec<&CollectionRecord> = records.iter().collect();
+    ordered.sort_by_key(|record| record.id);
+
+    for record in ordered {
+        for (position, site) in record.sites.iter().enumerate() {
+            by_url.entry(site_key(site, record)).or_default().push(DuplicateSite {
+                collection_id: record.id,
+                position,
+                title: site.title.clone(),
+                url: site.url.clone(),
+            });
+        }
+    }
+
+    let mut report = DuplicateReport::default();
+    for (canonical_url, sites) in by_url {
+        let mut per_collection: BTreeMap<u64, Vec<DuplicateSite>> = BTreeMap::new();
+        for site in &sites {
+            per_collection.entry(site.collection_id).or_default().push(site.clone());
+        }
+
+        for repeated in per_collection.values().filter(|sites| sites.len() > 1) {
+            report
+                .within_collections
+                .push(group(canonical_url.clone(), repeated.clone()));
+        }
+        if per_collection.len() > 1 {
+            report.across_collections.push(group(canonical_url, sites));
+        }
+    }
+
+    info!(
+        "Found {} repeated URLs within collections and {} shared across collections",
+        report.within_collections.len(),
+        report.across_collections.len()
+    );
+    report
+}
+
+/// Keep one site per canonical URL, at the position of its first occurrence.
+///
+/// With `KeepNewestTitle` that site takes the title and URL of the last
+/// occurrence, as sites added later are appended. Returns the removed sites.
+pub fn dedupe_sites(record: &mut CollectionRecord, strategy: DedupeStrategy) -> Vec<SiteEntry> {
+    let mut first_index: HashMap<String, usize> = HashMap::new();
+    let mut kept: Vec<SiteEntry> = Vec::new();
+    let mut removed = Vec::new();
+
+    for site in std::mem::take(&mut record.sites) {
+        let key = site_key(&site, record);
+        match first_index.get(&key) {
+            None => {
+                first_index.insert(key, kept.len());
+                kept.push(site);
+            }
+            Some(&index) => {
+                let replaced = match strategy {
+                    DedupeStrategy::KeepFirst => site,
+                    DedupeStrategy::KeepNewestTitle => std::mem::replace(&mut kept[index], site),
+                };
+                removed.push(replaced);
+            }
+        }
+    }
+
+    record.sites = kept;
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{sample_record, site};
+
+    fn record(id: u64, sites: &[(&str, &str)]) -> CollectionRecord {
+        CollectionRecord {
+            id,
+            sites: sites.iter().map(|(title, url)| site(title, url)).collect(),
+            ..sample_record(&format!("Collection {}", id), &[])
+        }
+    }
+
+    #[test]
+    fn test_find_and_dedupe() {
+        let records = vec![
+            record(2, &[("Docs", "https://docs.rs"), ("Docs again", "https://DOCS.rs/?utm_source=x")]),
+            record(1, &[("Docs", "https://docs.rs/"), ("Crates", "https://crates.io")]),
+            record(3, &[("Crates", "https://crates.io")]),
+        ];
+
+        let report = find_duplicates(&records);
+        assert_eq!(report.within_collections.len(), 1);
+        assert_eq!(report.within_collections[0].collection_ids, vec![2]);
+        assert_eq!(report.within_collections[0].sites.len(), 2);
+
+        let shared: Vec<(&str, &[u64])> = report
+            .across_collections
+            .iter()
+            .map(|g| (g.canonical_url.as_str(), g.collection_ids.as_slice()))
+            .collect();
+        assert_eq!(
+            shared,
+            vec![("https://crates.io/", &[1, 3][..]), ("https://docs.rs/", &[1, 2][..])]
+        );
+
+        let mut first = records[0].clone();
+        let removed = dedupe_sites(&mut first, DedupeStrategy::KeepFirst);
+        assert_eq!(first.sites.len(), 1);
+        assert_eq!(first.sites[0].title, "Docs");
+        assert_eq!(removed[0].title, "Docs again");
+
+        let mut newest = records[0].clone();
+        dedupe_sites(&mut newest, DedupeStrategy::KeepNewestTitle);
+        assert_eq!(newest.sites.len(), 1);
+        assert_eq!(newest.sites[0].title, "Docs again");
+    }
+}
@@ -0,0 +1,167 @@
+use crate::crypto::{DatabaseLocked, WrongPassphrase};
+use crate::migrations::UnsupportedVersion;
+use serde::{Deserialize, Serialize};
+
+/// Stable error categories the frontend can branch on; the wire names are part
+/// of the command API, so only ever add variants
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// A collection, profile, revision, backup or workspace doesn't exist
+    NotFound,
+    /// The input was rejected, e.g. an empty name or a short passphrase
+    Validation,
+    /// The request clashes with the current state, e.g. a duplicate ID
+    Conflict,
+    /// The database file, or a file being imported, can't be parsed
+    CorruptDatabase,
+    /// The database was written by a newer version of the app
+    UnsupportedVersion,
+    /// Another process holds the database lock
+    DatabaseBusy,
+    /// The database is encrypted and hasn't been unlocked yet
+    DatabaseLocked,
+    WrongPassphrase,
+    BrowserLaunch,
+    Io,
+    Cancelled,
+    Internal,
+}
+
+/// Error returned by every command, serialized as `{kind, message, details}`.
+///
+/// Services keep returning `Box<dyn std::error::Error>`; they box an `AppError`
+/// when they know the kind, and `From` classifies everything else.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppError {
+    pub kind: ErrorKind,
+    pub message: String,
+    /// Lower-level cause worth showing in a "details" section, if any
+    pub details: Option<String>,
+}
+
+impl AppError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::NotFound, message)
+    }
+
+    pub fn validation(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Validation, message)
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Conflict, message)
+    }
+
+    pub fn corrupt(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::CorruptDatabase, message)
+    }
+
+    pub fn with_details(mut self, details: impl Into<String>) -> Self {
+        self.details = Some(details.into());
+        self
+    }
+
+    /// Prefix the message with what the command was doing, keeping the kind
+    pub fn context(mut self, action: &str) -> Self {
+        self.message = format!("{}: {}", action, self.message);
+        self
+    }
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        let kind = match e.kind() {
+            std::io::ErrorKind::NotFound => ErrorKind::NotFound,
+            _ => ErrorKind::Io,
+        };
+        Self::new(kind, e.to_string())
+    }
+}
+
+impl From<rusqlite::Error> for AppError {
+    fn from(e: rusqlite::Error) -> Self {
+        use rusqlite::ErrorCode;
+
+        let kind = match e.sqlite_error_code() {
+            Some(ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked) => ErrorKind::DatabaseBusy,
+            Some(ErrorCode::DatabaseCorrupt | ErrorCode::NotADatabase) => ErrorKind::CorruptDatabase,
+            _ if matches!(e, rusqlite::Error::QueryReturnedNoRows) => ErrorKind::NotFound,
+            _ => ErrorKind::Internal,
+        };
+        Self::new(kind, e.to_string())
+    }
+}
+
+impl From<Box<dyn std::error::Error>> for AppError {
+    fn from(e: Box<dyn std::error::Error>) -> Self {
+        let e = match e.downcast::<AppError>() {
+            Ok(app_error) => return *app_error,
+            Err(e) => e,
+        };
+        let e = match e.downcast::<std::io::Error>() {
+            Ok(io_error) => return (*io_error).into(),
+            Err(e) => e,
+        };
+        let e = match e.downcast::<rusqlite::Error>() {
+            Ok(sqlite_error) => return (*sqlite_error).into(),
+            Err(e) => e,
+        };
+
+        let kind = if e.is::<UnsupportedVersion>() {
+            ErrorKind::UnsupportedVersion
+        } else if e.is::<DatabaseLocked>() {
+            ErrorKind::DatabaseLocked
+        } else if e.is::<WrongPassphrase>() {
+            ErrorKind::WrongPassphrase
+        } else if e.is::<serde_json::Error>() {
+            return Self::corrupt("Invalid JSON data").with_details(e.to_string());
+        } else {
+            ErrorKind::Internal
+        };
+        Self::new(kind, e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_boxed_errors_keep_their_kind() {
+        let boxed: Box<dyn std::error::Error> = AppError::not_found("Collection with id 7 not found").into();
+        let error = AppError::from(boxed).context("Failed to get collection");
+        assert_eq!(error.kind, ErrorKind::NotFound);
+        assert_eq!(error.message, "Failed to get collection: Collection with id 7 not found");
+
+        let boxed: Box<dyn std::error::Error> = serde_json::from_str::<u32>("{").unwrap_err().into();
+        let error = AppError::from(boxed);
+        assert_eq!(error.kind, ErrorKind::CorruptDatabase);
+        assert!(error.details.is_some());
+
+        let boxed: Box<dyn std::error::Error> = WrongPassphrase.into();
+        assert_eq!(AppError::from(boxed).kind, ErrorKind::WrongPassphrase);
+
+        let json = serde_json::to_value(AppError::validation("Name cannot be empty")).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"kind": "validation", "message": "Name cannot be empty", "details": null})
+        );
+    }
+}
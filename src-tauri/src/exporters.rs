@@ -0,0 +1,212 @@
+use crate::models::{CollectionRecord, ExportFormat};
+use chrono::Utc;
+use std::fmt::Write;
+use std::path::Path;
+
+/// Writes collections in a format other apps can read.
+///
+/// Only names, titles and URLs are exported; configs and profiles only survive
+/// a JSON backup or `bookmarks.html`.
+pub trait Exporter {
+    /// File extension, also used to pick the format from a save dialog path
+    fn extension(&self) -> &'static str;
+    fn export(&self, records: &[CollectionRecord]) -> String;
+}
+
+/// The exporter for a format picked in the UI
+pub fn exporter(format: ExportFormat) -> Box<dyn Exporter> {
+    match format {
+        ExportFormat::Markdown => Box::new(Markdown),
+        ExportFormat::Csv => Box::new(Csv),
+        ExportFormat::Opml => Box::new(Opml),
+        ExportFormat::UrlList => Box::new(UrlList),
+    }
+}
+
+/// Format for a file chosen in the save dialog; None means a JSON backup
+pub fn format_for_path(path: &Path) -> Option<ExportFormat> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    [ExportFormat::Markdown, ExportFormat::Csv, ExportFormat::Opml, ExportFormat::UrlList]
+        .into_iter()
+        .find(|format| exporter(*format).extension() == extension)
+}
+
+/// `- [title](url)` lists under a `##` heading per collection
+pub struct Markdown;
+
+impl Exporter for Markdown {
+    fn extension(&self) -> &'static str {
+        "md"
+    }
+
+    fn export(&self, records: &[CollectionRecord]) -> String {
+        let mut markdown = String::new();
+        for (i, record) in records.iter().enumerate() {
+            if i > 0 {
+                markdown.push('\n');
+            }
+            let _ = writeln!(markdown, "## {}\n", record.name.trim());
+            for site in &record.sites {
+                let title = site.title.replace('\\', "\\\\").replace('[', "\\[").replace(']', "\\]");
+                // Parentheses and spaces would end the link target early
+                let url = site.url.replace(' ', "%20").replace('(', "%28").replace(')', "%29");
+                let _ = writeln!(markdown, "- [{}]({})", title, url);
+            }
+        }
+        markdown
+    }
+}
+
+/// One row per site: `collection,title,url,created_at`, quoted as in RFC 4180
+pub struct Csv;
+
+impl Csv {
+    fn field(value: &str) -> String {
+        if value.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    }
+}
+
+impl Exporter for Csv {
+    fn extension(&self) -> &'static str {
+        "csv"
+    }
+
+    fn export(&self, records: &[CollectionRecord]) -> String {
+        let mut csv = String::from("collection,title,url,created_at\r\n");
+        for record in records {
+            let created_at = record.created_at.to_rfc3339();
+            for site in &record.sites {
+                let _ = write!(
+                    csv,
+                    "{},{},{},{}\r\n",
+                    Csv::field(&record.name),
+                    Csv::field(&site.title),
+                    Csv::field(&site.url),
+                    created_at
+                );
+            }
+        }
+        csv
+    }
+}
+
+/// OPML 2.0 outline, one `link` outline per site nested in its collection
+pub struct Opml;
+
+impl Exporter for Opml {
+    fn extension(&self) -> &'static str {
+        "opml"
+    }
+
+    fn export(&self, records: &[CollectionRecord]) -> String {
+        let mut opml = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <opml version=\"2.0\">\n  \
+             <head>\n    \
+             <title>Restore Sites</title>\n    \
+             <dateCreated>{}</dateCreated>\n  \
+             </head>\n  \
+             <body>\n",
+            Utc::now().to_rfc2822()
+        );
+        for record in records {
+            let _ = writeln!(
+                opml,
+                "    <outline text=\"{}\" created=\"{}\">",
+                html_escape::encode_double_quoted_attribute(&record.name),
+                record.created_at.to_rfc2822()
+            );
+            for site in &record.sites {
+                let _ = writeln!(
+                    opml,
+                    "      <outline text=\"{}\" type=\"link\" url=\"{}\"/>",
+                    html_escape::encode_double_quoted_attribute(&site.title),
+                    html_escape::encode_double_quoted_attribute(&site.url)
+                );
+            }
+            opml.push_str("    </outline>\n");
+        }
+        opml.push_str("  </body>\n</opml>\n");
+        opml
+    }
+}
+
+/// Bare URLs, one per line, ready to paste back in or into another app
+pub struct UrlList;
+
+impl Exporter for UrlList {
+    fn extension(&self) -> &'static str {
+        "txt"
+    }
+
+    fn export(&self, records: &[CollectionRecord]) -> String {
+        records
+            .iter()
+            .flat_map(|record| &record.sites)
+            .map(|site| format!("{}\n", site.url))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{sample_record, site};
+    use chrono::DateTime;
+
+    fn record(name: &str, sites: &[(&str, &str)]) -> CollectionRecord {
+        let created_at = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        CollectionRecord {
+            id: 1,
+            sites: sites.iter().map(|(title, url)| site(title, url)).collect(),
+            created_at,
+            updated_at: created_at,
+            ..sample_record(name, &[])
+        }
+    }
+
+    #[test]
+    fn test_exporters() {
+        let records = vec![
+            record("Work", &[("Docs [Rust]", "https://docs.rs"), ("Wiki", "https://en.wikipedia.org/wiki/Rust_(language)")]),
+            record("News, daily", &[("Say \"hi\"", "https://a.com/?q=1&b=2")]),
+        ];
+
+        assert_eq!(
+            Markdown.export(&records),
+            "## Work\n\n\
+             - [Docs \\[Rust\\]](https://docs.rs)\n\
+             - [Wiki](https://en.wikipedia.org/wiki/Rust_%28language%29)\n\
+             \n\
+             ## News, daily\n\n\
+             - [Say \"hi\"](https://a.com/?q=1&b=2)\n"
+        );
+
+        assert_eq!(
+            Csv.export(&records),
+            "collection,title,url,created_at\r\n\
+             Work,Docs [Rust],https://docs.rs,2023-11-14T22:13:20+00:00\r\n\
+             Work,Wiki,https://en.wikipedia.org/wiki/Rust_(language),2023-11-14T22:13:20+00:00\r\n\
+             \"News, daily\",\"Say \"\"hi\"\"\",https://a.com/?q=1&b=2,2023-11-14T22:13:20+00:00\r\n"
+        );
+
+        let opml = Opml.export(&records);
+        assert!(opml.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<opml version=\"2.0\">"));
+        assert!(opml.contains("<outline text=\"Work\" created=\"Tue, 14 Nov 2023 22:13:20 +0000\">"));
+        assert!(opml.contains("<outline text=\"Say &quot;hi&quot;\" type=\"link\" url=\"https://a.com/?q=1&amp;b=2\"/>"));
+        assert!(opml.ends_with("  </body>\n</opml>\n"));
+
+        assert_eq!(
+            UrlList.export(&records),
+            "https://docs.rs\nhttps://en.wikipedia.org/wiki/Rust_(language)\nhttps://a.com/?q=1&b=2\n"
+        );
+
+        assert_eq!(format_for_path(Path::new("/tmp/export.MD")), Some(ExportFormat::Markdown));
+        assert_eq!(format_for_path(Path::new("/tmp/export.txt")), Some(ExportFormat::UrlList));
+        assert_eq!(format_for_path(Path::new("/tmp/backup.json")), None);
+    }
+}
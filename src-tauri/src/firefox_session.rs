@@ -0,0 +1,244 @@
+use crate::error::AppError;
+use crate::integrity::is_openable_url;
+use crate::models::{Browser, BrowserSession, SessionFile, SessionWindow, SiteEntry};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, instrument, warn};
+
+/// Header of Firefox's LZ4 files, followed by the decompressed size and one LZ4 block
+const MOZLZ4_MAGIC: &[u8] = b"mozLz40\0";
+
+/// Largest decompressed session accepted; the buffer is allocated up front from
+/// the size in the header, so a damaged header must not decide it alone
+const MAX_SESSION_SIZE: usize = 256 * 1024 * 1024;
+
+/// Session files inside a profile, most current first: written while Firefox
+/// runs, the session before the last restart, and the one saved on a clean exit
+const SESSION_FILES: &[&str] = &[
+    "sessionstore-backups/recovery.jsonlz4",
+    "sessionstore-backups/previous.jsonlz4",
+    "sessionstore.jsonlz4",
+];
+
+// Only the parts of Firefox's session format we read; `_closedTabs` and
+// `_closedWindows` are left out, so closed tabs are never imported
+#[derive(Deserialize)]
+struct SessionState {
+    #[serde(default)]
+    windows: Vec<WindowState>,
+    session: Option<SessionMeta>,
+}
+
+#[derive(Deserialize)]
+struct SessionMeta {
+    #[serde(rename = "lastUpdate")]
+    last_update: Option<i64>,          // Milliseconds since the epoch
+}
+
+#[derive(Deserialize)]
+struct WindowState {
+    #[serde(default)]
+    tabs: Vec<TabState>,
+    selected: Option<usize>,           // 1-based
+}
+
+#[derive(Deserialize)]
+struct TabState {
+    #[serde(default)]
+    entries: Vec<EntryState>,
+    index: Option<usize>,              // 1-based position of the current entry in the tab's history
+    #[serde(default)]
+    pinned: bool,
+}
+
+#[derive(Deserialize)]
+struct EntryState {
+    url: Option<String>,
+    title: Option<String>,
+}
+
+/// Decompress a mozLz4 file such as `recovery.jsonlz4`
+pub fn decode_mozlz4(bytes: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let body = bytes
+        .strip_prefix(MOZLZ4_MAGIC)
+        .ok_or_else(|| AppError::validation("Not a Firefox session file (missing mozLz4 header)"))?;
+    let (size, block) = body
+        .split_first_chunk::<4>()
+        .ok_or_else(|| AppError::corrupt("Firefox session file is truncated"))?;
+
+    let size = u32::from_le_bytes(*size) as usize;
+    if size > MAX_SESSION_SIZE {
+        return Err(AppError::corrupt(format!(
+            "Firefox session file claims to hold {} MiB, more than the {} MiB allowed",
+            size / (1024 * 1024),
+            MAX_SESSION_SIZE / (1024 * 1024)
+        ))
+        .into());
+    }
+
+    lz4_flex::block::decompress(block, size).map_err(|e| {
+        AppError::corrupt("Firefox session file could not be decompressed")
+            .with_details(e.to_string())
+            .into()
+    })
+}
+
+/// Session files in the given Firefox profile directories, newest first
+#[instrument(skip(profile_dirs))]
+pub fn locate_session_files(profile_dirs: &[PathBuf]) -> Vec<SessionFile> {
+    let mut found: Vec<SessionFile> = profile_dirs
+        .iter()
+        .flat_map(|dir| SESSION_FILES.iter().map(move |file| dir.join(file)))
+        .filter_map(|path| {
+            let modified = fs::metadata(&path).and_then(|meta| meta.modified()).ok()?;
+            let profile = path
+                .ancestors()
+                .find(|dir| dir.join("prefs.js").exists())
+                .and_then(Path::file_name)
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            Some(SessionFile {
+                path: path.to_string_lossy().to_string(),
+                browser: Browser::Firefox,
+                profile,
+                modified_at: DateTime::<Utc>::from(modified),
+            })
+        })
+        .collect();
+
+    found.sort_by_key(|file| std::cmp::Reverse(file.modified_at));
+    debug!("Found {} Firefox session files", found.len());
+    found
+}
+
+/// Read the open windows and tabs of a Firefox session file.
+///
+/// Each tab contributes its current history entry. Tabs without an openable
+/// URL (`about:` pages and the like), and pinned tabs with `skip_pinned`, are
+/// counted in `skipped_tabs`.
+#[instrument]
+pub fn read_session(path: &Path, skip_pinned: bool) -> Result<BrowserSession, Box<dyn std::error::Error>> {
+    let json = decode_mozlz4(&fs::read(path)?)?;
+    let state: SessionState = serde_json::from_slice(&json)?;
+
+    let saved_at = state
+        .session
+        .and_then(|meta| meta.last_update)
+        .and_then(DateTime::from_timestamp_millis)
+        .or_else(|| {
+            fs::metadata(path)
+                .and_then(|meta| meta.modified())
+                .ok()
+                .map(DateTime::<Utc>::from)
+        })
+        .unwrap_or_else(Utc::now);
+
+    let mut skipped_tabs = 0;
+    let mut windows = Vec::new();
+    for window in state.windows {
+        let mut tabs = Vec::new();
+        let mut selected_tab = None;
+
+        for (position, tab) in window.tabs.into_iter().enumerate() {
+            if skip_pinned && tab.pinned {
+                skipped_tabs += 1;
+                continue;
+            }
+            let current = tab
+                .index
+                .and_then(|index| index.checked_sub(1))
+                .and_then(|index| tab.entries.get(index))
+                .or(tab.entries.last());
+            let Some(url) = current.and_then(|entry| entry.url.clone()).filter(|url| is_openable_url(url)) else {
+                skipped_tabs += 1;
+                continue;
+            };
+
+            if window.selected == Some(position + 1) {
+                selected_tab = Some(tabs.len());
+            }
+            let title = current
+                .and_then(|entry| entry.title.clone())
+                .filter(|title| !title.trim().is_empty())
+                .unwrap_or_else(|| url.clone());
+            tabs.push(SiteEntry {
+                title,
+                url,
+                canonical_url: None,
+            });
+        }
+
+        if tabs.is_empty() {
+            warn!("Skipping a Firefox window without importable tabs");
+            continue;
+        }
+        windows.push(SessionWindow { tabs, selected_tab });
+    }
+
+    Ok(BrowserSession {
+        browser: Browser::Firefox,
+        saved_at,
+        windows,
+        skipped_tabs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_session() {
+        let state = serde_json::json!({
+            "session": { "lastUpdate": 1_700_000_000_000i64 },
+            "windows": [
+                {
+                    "selected": 3,
+                    "tabs": [
+                        { "pinned": true, "index": 1, "entries": [{ "url": "https://mail.example.com", "title": "Mail" }] },
+                        { "index": 1, "entries": [
+                            { "url": "https://docs.rs", "title": "Docs" },
+                            { "url": "https://docs.rs/serde", "title": "serde" }
+                        ] },
+                        { "index": 1, "entries": [{ "url": "about:newtab" }] },
+                        { "entries": [{ "url": "https://crates.io" }] }
+                    ],
+                    "_closedTabs": [{ "state": { "entries": [{ "url": "https://closed.example.com" }] } }]
+                },
+                { "tabs": [{ "index": 1, "entries": [{ "url": "about:blank" }] }] }
+            ],
+            "_closedWindows": [{ "tabs": [{ "entries": [{ "url": "https://gone.example.com" }] }] }]
+        });
+        let json = serde_json::to_vec(&state).unwrap();
+        let mut bytes = MOZLZ4_MAGIC.to_vec();
+        bytes.extend((json.len() as u32).to_le_bytes());
+        bytes.extend(lz4_flex::block::compress(&json));
+
+        let dir = crate::test_support::temp_dir("firefox-session");
+        let path = dir.join("recovery.jsonlz4");
+        fs::write(&path, &bytes).unwrap();
+
+        let session = read_session(&path, true).unwrap();
+        assert_eq!(session.saved_at.timestamp(), 1_700_000_000);
+        assert_eq!(session.windows.len(), 1);
+        assert_eq!(session.skipped_tabs, 3);
+
+        let window = &session.windows[0];
+        let urls: Vec<&str> = window.tabs.iter().map(|tab| tab.url.as_str()).collect();
+        assert_eq!(urls, vec!["https://docs.rs", "https://crates.io"]);
+        assert_eq!(window.tabs[1].title, "https://crates.io");
+        assert_eq!(window.selected_tab, None);
+
+        let with_pinned = read_session(&path, false).unwrap();
+        assert_eq!(with_pinned.windows[0].tabs.len(), 3);
+        assert_eq!(with_pinned.skipped_tabs, 2);
+
+        assert!(decode_mozlz4(b"not a session").is_err());
+        let oversized = [MOZLZ4_MAGIC, &u32::MAX.to_le_bytes()[..], &[0]].concat();
+        assert!(decode_mozlz4(&oversized).unwrap_err().to_string().contains("MiB"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,432 @@
+use crate::error::AppError;
+use crate::integrity::is_openable_url;
+use crate::models::{CollectionConfig, CollectionRecord, ImportFormat, SiteEntry};
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashSet;
+use tracing::{debug, instrument};
+
+/// Collections read from another app's export, not yet stored
+pub struct ParsedImport {
+    pub collections: Vec<CollectionRecord>,
+    pub skipped_sites: usize,          // Entries without an openable URL
+    pub unnamed: HashSet<String>,      // Names made up because the export had none
+}
+
+/// Converts an export from another app into collections.
+///
+/// Implementations only parse; storing goes through the same merge as
+/// `import_database`, so name clashes are handled in one place.
+pub trait Importer {
+    /// Format name used in errors and logs
+    fn name(&self) -> &'static str;
+    fn parse(&self, contents: &str) -> Result<ParsedImport, Box<dyn std::error::Error>>;
+}
+
+/// The importer for a format picked in the UI
+pub fn importer(format: ImportFormat) -> Box<dyn Importer> {
+    match format {
+        ImportFormat::OneTab => Box::new(OneTab),
+        ImportFormat::SessionBuddy => Box::new(SessionBuddy),
+        ImportFormat::Toby => Box::new(Toby),
+    }
+}
+
+/// Accumulates collections, dropping entries that can't be opened
+#[derive(Default)]
+struct Collector {
+    collections: Vec<CollectionRecord>,
+    skipped_sites: usize,
+    unnamed: HashSet<String>,
+}
+
+impl Collector {
+    fn site(&mut self, url: Option<&str>, title: Option<&str>) -> Option<SiteEntry> {
+        let url = url.map(str::trim).filter(|url| is_openable_url(url));
+        let Some(url) = url else {
+            self.skipped_sites += 1;
+            return None;
+        };
+        let title = title
+            .map(str::trim)
+            .filter(|title| !title.is_empty())
+            .unwrap_or(url);
+        Some(SiteEntry {
+            title: title.to_string(),
+            url: url.to_string(),
+            canonical_url: None,
+        })
+    }
+
+    /// The export's name for a collection, or `fallback` noted as made up
+    fn name_or(&mut self, name: Option<&str>, fallback: impl FnOnce() -> String) -> String {
+        match name.map(str::trim).filter(|name| !name.is_empty()) {
+            Some(name) => name.to_string(),
+            None => {
+                let name = fallback();
+                self.unnamed.insert(name.clone());
+                name
+            }
+        }
+    }
+
+    /// Add a collection unless it ended up empty
+    fn push(
+        &mut self,
+        name: String,
+        sites: Vec<SiteEntry>,
+        created_at: Option<DateTime<Utc>>,
+        updated_at: Option<DateTime<Utc>>,
+    ) {
+        if sites.is_empty() {
+            return;
+        }
+        let created_at = created_at.unwrap_or_else(Utc::now);
+        self.collections.push(CollectionRecord {
+            id: 0, // Assigned on import
+            name,
+            sites,
+            config: CollectionConfig::default(),
+            created_at,
+            updated_at: updated_at.unwrap_or(created_at),
+        });
+    }
+
+    fn finish(self, format: &str) -> ParsedImport {
+        debug!(
+            "Parsed {} collections from {}, skipped {} entries",
+            self.collections.len(),
+            format,
+            self.skipped_sites
+        );
+        ParsedImport {
+            collections: self.collections,
+            skipped_sites: self.skipped_sites,
+            unnamed: self.unnamed,
+        }
+    }
+}
+
+fn parse_json<T: DeserializeOwned>(contents: &str, format: &str) -> Result<T, Box<dyn std::error::Error>> {
+    serde_json::from_str(contents).map_err(|e| {
+        AppError::validation(format!("Not a valid {} export", format))
+            .with_details(e.to_string())
+            .into()
+    })
+}
+
+/// Exports store times as epoch milliseconds or ISO 8601 strings
+fn parse_time(value: Option<&Value>) -> Option<DateTime<Utc>> {
+    match value? {
+        Value::Number(millis) => millis.as_i64().and_then(DateTime::from_timestamp_millis),
+        Value::String(text) => DateTime::parse_from_rfc3339(text)
+            .ok()
+            .map(|time| time.with_timezone(&Utc)),
+        _ => None,
+    }
+}
+
+/// OneTab's "Export URLs" text: `url | title` lines, groups separated by blank lines.
+/// The export has no group names or dates.
+pub struct OneTab;
+
+impl Importer for OneTab {
+    fn name(&self) -> &'static str {
+        "OneTab"
+    }
+
+    #[instrument(skip(self, contents), fields(contents_length = contents.len()))]
+    fn parse(&self, contents: &str) -> Result<ParsedImport, Box<dyn std::error::Error>> {
+        let mut collector = Collector::default();
+        let mut sites = Vec::new();
+
+        // A trailing empty line closes the last group
+        for line in contents.lines().chain(std::iter::once("")) {
+            let line = line.trim();
+            if line.is_empty() {
+                if !sites.is_empty() {
+                    let count = collector.collections.len();
+                    let name = collector.name_or(None, || format!("OneTab group {}", count + 1));
+                    collector.push(name, std::mem::take(&mut sites), None, None);
+                }
+                continue;
+            }
+
+            let (url, title) = match line.split_once(" | ") {
+                Some((url, title)) => (url, Some(title)),
+                None => (line, None),
+            };
+            sites.extend(collector.site(Some(url), title));
+        }
+
+        Ok(collector.finish(self.name()))
+    }
+}
+
+#[derive(Deserialize)]
+struct SessionBuddyExport {
+    // Session Buddy 3: saved and previous sessions with their windows
+    #[serde(default)]
+    sessions: Vec<SessionBuddySession>,
+    // Session Buddy 4: collections of folders
+    #[serde(default)]
+    collections: Vec<SessionBuddyCollection>,
+}
+
+#[derive(Deserialize)]
+struct SessionBuddySession {
+    name: Option<String>,
+    created: Option<Value>,
+    generated: Option<Value>,
+    modified: Option<Value>,
+    #[serde(default)]
+    windows: Vec<SessionBuddyWindow>,
+}
+
+#[derive(Deserialize)]
+struct SessionBuddyWindow {
+    #[serde(default)]
+    tabs: Vec<Link>,
+}
+
+#[derive(Deserialize)]
+struct SessionBuddyCollection {
+    title: Option<String>,
+    created: Option<Value>,
+    updated: Option<Value>,
+    #[serde(default)]
+    folders: Vec<SessionBuddyFolder>,
+}
+
+#[derive(Deserialize)]
+struct SessionBuddyFolder {
+    #[serde(default)]
+    links: Vec<Link>,
+}
+
+#[derive(Deserialize)]
+struct Link {
+    url: Option<String>,
+    title: Option<String>,
+}
+
+/// Session Buddy's JSON backup, one collection per session (all its windows)
+/// or per collection (all its folders)
+pub struct SessionBuddy;
+
+impl Importer for SessionBuddy {
+    fn name(&self) -> &'static str {
+        "Session Buddy"
+    }
+
+    #[instrument(skip(self, contents), fields(contents_length = contents.len()))]
+    fn parse(&self, contents: &str) -> Result<ParsedImport, Box<dyn std::error::Error>> {
+        let export: SessionBuddyExport = parse_json(contents, self.name())?;
+        let mut collector = Collector::default();
+
+        for (index, session) in export.sessions.iter().enumerate() {
+            let sites = session
+                .windows
+                .iter()
+                .flat_map(|window| &window.tabs)
+                .filter_map(|tab| collector.site(tab.url.as_deref(), tab.title.as_deref()))
+                .collect();
+            let name = collector.name_or(session.name.as_deref(), || format!("Session Buddy session {}", index + 1));
+            let created_at = parse_time(session.created.as_ref()).or(parse_time(session.generated.as_ref()));
+            collector.push(name, sites, created_at, parse_time(session.modified.as_ref()));
+        }
+
+        for (index, collection) in export.collections.iter().enumerate() {
+            let sites = collection
+                .folders
+                .iter()
+                .flat_map(|folder| &folder.links)
+                .filter_map(|link| collector.site(link.url.as_deref(), link.title.as_deref()))
+                .collect();
+            let name = collector.name_or(collection.title.as_deref(), || format!("Session Buddy collection {}", index + 1));
+            collector.push(
+                name,
+                sites,
+                parse_time(collection.created.as_ref()),
+                parse_time(collection.updated.as_ref()),
+            );
+        }
+
+        Ok(collector.finish(self.name()))
+    }
+}
+
+#[derive(Deserialize)]
+struct TobyExport {
+    #[serde(default)]
+    lists: Vec<TobyList>,
+    // Exports from Toby for teams nest lists in groups
+    #[serde(default)]
+    groups: Vec<TobyGroup>,
+}
+
+#[derive(Deserialize)]
+struct TobyGroup {
+    name: Option<String>,
+    #[serde(default)]
+    lists: Vec<TobyList>,
+}
+
+#[derive(Deserialize)]
+struct TobyList {
+    title: Option<String>,
+    #[serde(default)]
+    cards: Vec<TobyCard>,
+}
+
+#[derive(Deserialize)]
+struct TobyCard {
+    url: Option<String>,
+    title: Option<String>,
+    #[serde(rename = "customTitle")]
+    custom_title: Option<String>,
+}
+
+/// Toby's JSON export, one collection per list; a renamed card keeps its custom title.
+/// The export has no dates.
+pub struct Toby;
+
+impl Toby {
+    fn push_list(collector: &mut Collector, list: &TobyList, group: Option<&str>) {
+        let sites = list
+            .cards
+            .iter()
+            .filter_map(|card| {
+                let title = card
+                    .custom_title
+                    .as_deref()
+                    .filter(|title| !title.trim().is_empty())
+                    .or(card.title.as_deref());
+                collector.site(card.url.as_deref(), title)
+            })
+            .collect();
+
+        let count = collector.collections.len();
+        let list_name = collector.name_or(list.title.as_deref(), || format!("Toby list {}", count + 1));
+        let name = match group.map(str::trim).filter(|group| !group.is_empty()) {
+            Some(group) => {
+                let name = format!("{} / {}", group, list_name);
+                if collector.unnamed.remove(&list_name) {
+                    collector.unnamed.insert(name.clone());
+                }
+                name
+            }
+            None => list_name,
+        };
+        collector.push(name, sites, None, None);
+    }
+}
+
+impl Importer for Toby {
+    fn name(&self) -> &'static str {
+        "Toby"
+    }
+
+    #[instrument(skip(self, contents), fields(contents_length = contents.len()))]
+    fn parse(&self, contents: &str) -> Result<ParsedImport, Box<dyn std::error::Error>> {
+        let export: TobyExport = parse_json(contents, self.name())?;
+        let mut collector = Collector::default();
+
+        for list in &export.lists {
+            Self::push_list(&mut collector, list, None);
+        }
+        for group in &export.groups {
+            for list in &group.lists {
+                Self::push_list(&mut collector, list, group.name.as_deref());
+            }
+        }
+
+        Ok(collector.finish(self.name()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(parsed: &ParsedImport) -> Vec<(String, Vec<(String, String)>)> {
+        parsed
+            .collections
+            .iter()
+            .map(|c| {
+                let sites = c.sites.iter().map(|s| (s.title.clone(), s.url.clone())).collect();
+                (c.name.clone(), sites)
+            })
+            .collect()
+    }
+
+    fn pair(title: &str, url: &str) -> (String, String) {
+        (title.to_string(), url.to_string())
+    }
+
+    #[test]
+    fn test_importers() {
+        let onetab = OneTab
+            .parse("https://docs.rs | Docs | Rust\nhttps://crates.io\n\n\nchrome://extensions | Extensions\nhttps://a.com | A\n")
+            .unwrap();
+        assert_eq!(onetab.skipped_sites, 1);
+        assert_eq!(
+            summary(&onetab),
+            vec![
+                (
+                    "OneTab group 1".to_string(),
+                    vec![pair("Docs | Rust", "https://docs.rs"), pair("https://crates.io", "https://crates.io")]
+                ),
+                ("OneTab group 2".to_string(), vec![pair("A", "https://a.com")]),
+            ]
+        );
+
+        let session_buddy = SessionBuddy
+            .parse(
+                r#"{
+                    "sessions": [
+                        { "name": "Research", "created": 1700000000000, "windows": [
+                            { "tabs": [{ "url": "https://a.com", "title": "A" }] },
+                            { "tabs": [{ "url": "https://b.com", "title": "B" }, { "url": "about:blank" }] }
+                        ] },
+                        { "type": "previous", "generated": "2024-01-02T03:04:05Z", "windows": [{ "tabs": [{ "url": "https://c.com" }] }] }
+                    ],
+                    "collections": [
+                        { "title": "Reading", "folders": [{ "links": [{ "url": "https://d.com", "title": "D" }] }] }
+                    ]
+                }"#,
+            )
+            .unwrap();
+        assert_eq!(session_buddy.skipped_sites, 1);
+        assert_eq!(
+            summary(&session_buddy),
+            vec![
+                ("Research".to_string(), vec![pair("A", "https://a.com"), pair("B", "https://b.com")]),
+                ("Session Buddy session 2".to_string(), vec![pair("https://c.com", "https://c.com")]),
+                ("Reading".to_string(), vec![pair("D", "https://d.com")]),
+            ]
+        );
+        assert_eq!(session_buddy.collections[0].created_at.timestamp(), 1_700_000_000);
+        assert_eq!(session_buddy.collections[1].created_at.timestamp(), 1_704_164_645);
+
+        let toby = Toby
+            .parse(
+                r#"{
+                    "lists": [{ "title": "Daily", "cards": [{ "url": "https://e.com", "title": "E", "customTitle": "Email" }] }],
+                    "groups": [{ "name": "Team", "lists": [{ "title": "Docs", "cards": [{ "url": "https://f.com", "title": "F" }] }] }]
+                }"#,
+            )
+            .unwrap();
+        assert_eq!(
+            summary(&toby),
+            vec![
+                ("Daily".to_string(), vec![pair("Email", "https://e.com")]),
+                ("Team / Docs".to_string(), vec![pair("F", "https://f.com")]),
+            ]
+        );
+
+        assert!(Toby.parse("not json").is_err());
+    }
+}
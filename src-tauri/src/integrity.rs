@@ -0,0 +1,348 @@
+use crate::models::{Database, IntegrityIssue, IntegrityIssueKind};
+use crate::normalize::canonicalize_sites;
+use crate::utils::clean_url;
+use std::collections::HashSet;
+use tracing::{info, instrument};
+
+/// Sites are opened in a browser, so only absolute http(s) URLs with a host are usable
+pub fn is_openable_url(url: &str) -> bool {
+    url::Url::parse(url)
+        .map(|parsed| matches!(parsed.scheme(), "http" | "https") && parsed.host_str().is_some())
+        .unwrap_or(false)
+}
+
+fn issue(kind: IntegrityIssueKind, collection_id: Option<u64>, message: String) -> IntegrityIssue {
+    IntegrityIssue {
+        kind,
+        message,
+        collection_id,
+    }
+}
+
+/// Highest collection ID in use, counting the trash so restores can't collide with new records
+fn highest_id(database: &Database) -> u64 {
+    database
+        .data
+        .iter()
+        .map(|record| record.id)
+        .chain(database.trash.iter().map(|trashed| trashed.record.id))
+        .max()
+        .unwrap_or(0)
+}
+
+/// Report every inconsistency in `database` without changing it
+#[instrument(skip(database))]
+pub fn check_database(database: &Database) -> Vec<IntegrityIssue> {
+    let mut issues = Vec::new();
+    let meta = &database.meta;
+
+    let mut seen_ids = HashSet::new();
+    for record in &database.data {
+        if !seen_ids.insert(record.id) {
+            issues.push(issue(
+                IntegrityIssueKind::DuplicateId,
+                Some(record.id),
+                format!("Collection '{}' reuses ID {}", record.name, record.id),
+            ));
+        }
+    }
+
+    let highest = highest_id(database);
+    if meta.max_id < highest {
+        issues.push(issue(
+            IntegrityIssueKind::MaxIdTooLow,
+            None,
+            format!("max_id is {} but ID {} is in use", meta.max_id, highest),
+        ));
+    }
+
+    if meta.record_count != database.data.len() {
+        issues.push(issue(
+            IntegrityIssueKind::WrongRecordCount,
+            None,
+            format!(
+                "record_count is {} but there are {} collections",
+                meta.record_count,
+                database.data.len()
+            ),
+        ));
+    }
+
+    if meta.last_updated_id != 0 && !seen_ids.contains(&meta.last_updated_id) {
+        issues.push(issue(
+            IntegrityIssueKind::StaleLastUpdatedId,
+            None,
+            format!("last_updated_id {} does not match any collection", meta.last_updated_id),
+        ));
+    }
+
+    let profile_ids: HashSet<&str> = database.profiles.iter().map(|p| p.id.as_str()).collect();
+    for record in &database.data {
+        if let Some(profile_id) = &record.config.browser_profile_id {
+            if !profile_ids.contains(profile_id.as_str()) {
+                issues.push(issue(
+                    IntegrityIssueKind::DanglingProfileReference,
+                    Some(record.id),
+                    format!(
+                        "Collection '{}' uses missing browser profile '{}'",
+                        record.name, profile_id
+                    ),
+                ));
+            }
+        }
+    }
+
+    let defaults: Vec<&str> = database
+        .profiles
+        .iter()
+        .filter(|profile| profile.is_default)
+        .map(|profile| profile.id.as_str())
+        .collect();
+    if defaults.len() > 1 {
+        issues.push(issue(
+            IntegrityIssueKind::MultipleDefaultProfiles,
+            None,
+            format!("Several profiles are marked default: {}", defaults.join(", ")),
+        ));
+    }
+
+    for record in &database.data {
+        for site in record.sites.iter().filter(|site| !is_openable_url(&site.url)) {
+            issues.push(issue(
+                IntegrityIssueKind::InvalidUrl,
+                Some(record.id),
+                format!("Collection '{}' has invalid URL '{}'", record.name, site.url),
+            ));
+        }
+    }
+
+    issues
+}
+
+/// Fix what can be fixed safely, returning the issues that were fixed.
+///
+/// Invalid URLs are only rewritten when adding a scheme makes them valid;
+/// anything else is left for the user rather than deleting their data.
+#[instrument(skip(database))]
+pub fn repair_database(database: &mut Database) -> Vec<IntegrityIssue> {
+    let mut fixed = Vec::new();
+
+    // Duplicates get fresh IDs above everything in use
+    let mut next_id = database.meta.max_id.max(highest_id(database));
+    let mut seen_ids = HashSet::new();
+    for record in &mut database.data {
+        if !seen_ids.insert(record.id) {
+            // Revisions only carry the ID, so there is no telling which of the
+            // collections sharing it they belong to; keeping them would let one
+            // collection roll back to the other's contents
+            let revisions = database.revisions.len();
+            database.revisions.retain(|revision| revision.collection_id != record.id);
+            let dropped = revisions - database.revisions.len();
+
+            next_id += 1;
+            let mut message = format!(
+                "Collection '{}' moved from duplicate ID {} to {}",
+                record.name, record.id, next_id
+            );
+            if dropped > 0 {
+                message.push_str(&format!(
+                    "; {} revisions of ID {} were dropped as they can't be told apart",
+                    dropped, record.id
+                ));
+            }
+            fixed.push(issue(IntegrityIssueKind::DuplicateId, Some(next_id), message));
+            record.id = next_id;
+            seen_ids.insert(next_id);
+        }
+    }
+
+    let meta = &mut database.meta;
+    if meta.max_id < next_id {
+        fixed.push(issue(
+            IntegrityIssueKind::MaxIdTooLow,
+            None,
+            format!("max_id raised from {} to {}", meta.max_id, next_id),
+        ));
+        meta.max_id = next_id;
+    }
+
+    if meta.record_count != database.data.len() {
+        fixed.push(issue(
+            IntegrityIssueKind::WrongRecordCount,
+            None,
+            format!(
+                "record_count corrected from {} to {}",
+                meta.record_count,
+                database.data.len()
+            ),
+        ));
+        meta.record_count = database.data.len();
+    }
+
+    if meta.last_updated_id != 0 && !seen_ids.contains(&meta.last_updated_id) {
+        let latest = database
+            .data
+            .iter()
+            .max_by_key(|record| record.updated_at)
+            .map_or(0, |record| record.id);
+        fixed.push(issue(
+            IntegrityIssueKind::StaleLastUpdatedId,
+            None,
+            format!("last_updated_id changed from {} to {}", meta.last_updated_id, latest),
+        ));
+        meta.last_updated_id = latest;
+    }
+
+    let profile_ids: HashSet<String> = database.profiles.iter().map(|p| p.id.clone()).collect();
+    for record in &mut database.data {
+        if let Some(profile_id) = &record.config.browser_profile_id {
+            if !profile_ids.contains(profile_id) {
+                fixed.push(issue(
+                    IntegrityIssueKind::DanglingProfileReference,
+                    Some(record.id),
+                    format!(
+                        "Collection '{}' no longer references missing profile '{}'",
+                        record.name, profile_id
+                    ),
+                ));
+                record.config.browser_profile_id = None;
+            }
+        }
+    }
+
+    let mut default_seen = false;
+    for profile in database.profiles.iter_mut().filter(|p| p.is_default) {
+        if default_seen {
+            profile.is_default = false;
+            fixed.push(issue(
+                IntegrityIssueKind::MultipleDefaultProfiles,
+                None,
+                format!("Profile '{}' is no longer marked default", profile.id),
+            ));
+        }
+        default_seen = true;
+    }
+
+    for record in &mut database.data {
+        let mut rewritten = false;
+        for site in &mut record.sites {
+            if is_openable_url(&site.url) {
+                continue;
+            }
+            if let Some(cleaned) = clean_url(&site.url).filter(|url| is_openable_url(url)) {
+                fixed.push(issue(
+                    IntegrityIssueKind::InvalidUrl,
+                    Some(record.id),
+                    format!("URL '{}' rewritten as '{}'", site.url, cleaned),
+                ));
+                site.url = cleaned;
+                rewritten = true;
+            }
+        }
+        // The stored canonical URLs were computed from the old ones
+        if rewritten {
+            canonicalize_sites(&mut record.sites, &record.config);
+        }
+    }
+
+    info!("Repaired {} database issues", fixed.len());
+    fixed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Browser, BrowserMode, BrowserProfile, CollectionRecord, CollectionRevision};
+    use crate::test_support::sample_record;
+    use chrono::Utc;
+
+    fn record(id: u64, urls: &[&str]) -> CollectionRecord {
+        CollectionRecord {
+            id,
+            ..sample_record(&format!("Collection {}", id), urls)
+        }
+    }
+
+    fn profile(id: &str) -> BrowserProfile {
+        let mut profile =
+            BrowserProfile::new(id.to_string(), id.to_string(), Browser::Chrome, BrowserMode::Normal)
+                .unwrap();
+        profile.is_default = true;
+        profile
+    }
+
+    #[test]
+    fn test_check_and_repair() {
+        let mut database = Database {
+            data: vec![
+                record(1, &["https://a.com"]),
+                record(1, &["example.com/page"]),
+                record(2, &["not a url"]),
+            ],
+            profiles: vec![profile("one"), profile("two")],
+            ..Database::default()
+        };
+        database.data[0].config.browser_profile_id = Some("gone".to_string());
+        database.meta.max_id = 1;
+        database.meta.last_updated_id = 7;
+        database.revisions = [1, 2]
+            .into_iter()
+            .map(|id| CollectionRevision {
+                collection_id: id,
+                revision: 1,
+                name: format!("Collection {}", id),
+                sites: Vec::new(),
+                config: Default::default(),
+                saved_at: Utc::now(),
+            })
+            .collect();
+
+        let kinds: Vec<IntegrityIssueKind> = check_database(&database).iter().map(|i| i.kind).collect();
+        for kind in [
+            IntegrityIssueKind::DuplicateId,
+            IntegrityIssueKind::MaxIdTooLow,
+            IntegrityIssueKind::WrongRecordCount,
+            IntegrityIssueKind::StaleLastUpdatedId,
+            IntegrityIssueKind::DanglingProfileReference,
+            IntegrityIssueKind::MultipleDefaultProfiles,
+            IntegrityIssueKind::InvalidUrl,
+        ] {
+            assert!(kinds.contains(&kind), "missing {:?}", kind);
+        }
+
+        let fixed = repair_database(&mut database);
+        assert_eq!(database.data[1].id, 3);
+        assert!(fixed[0].message.contains("1 revisions of ID 1 were dropped"));
+        let revised: Vec<u64> = database.revisions.iter().map(|r| r.collection_id).collect();
+        assert_eq!(revised, vec![2]);
+        assert_eq!(database.meta.max_id, 3);
+        assert_eq!(database.meta.record_count, 3);
+        assert_eq!(database.data[1].sites[0].url, "https://example.com/page");
+        assert!(database.data[0].config.browser_profile_id.is_none());
+        assert!(!database.profiles[1].is_default);
+
+        // Only the unfixable URL is left
+        let remaining = check_database(&database);
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].kind, IntegrityIssueKind::InvalidUrl);
+    }
+
+    #[test]
+    fn test_repaired_urls_are_canonicalized_again() {
+        let mut database = Database {
+            data: vec![record(1, &["Example.com/page", "https://b.com"])],
+            ..Database::default()
+        };
+        database.meta.max_id = 1;
+        database.meta.record_count = 1;
+        for site in &mut database.data[0].sites {
+            site.canonical_url = Some(site.url.clone());
+        }
+
+        repair_database(&mut database);
+        let sites = &database.data[0].sites;
+        assert_eq!(sites[0].url, "https://Example.com/page");
+        assert_eq!(sites[0].canonical_url.as_deref(), Some("https://example.com/page"));
+        assert_eq!(sites[1].canonical_url.as_deref(), Some("https://b.com/"));
+    }
+}
@@ -0,0 +1,334 @@
+use crate::database::{JsonStore, Store};
+use crate::error::AppError;
+use crate::models::{BrowserMode, BrowserProfile, CollectionRecord, JournalEntryInfo, JournalStatus};
+use chrono::{DateTime, Utc};
+use std::sync::{Mutex, MutexGuard};
+use tracing::{info, instrument, warn};
+
+/// Undo history kept per session; the oldest entries are dropped beyond this
+const MAX_JOURNAL_ENTRIES: usize = 50;
+
+/// A mutating command together with the state needed to revert or reapply it
+#[derive(Debug, Clone)]
+pub enum Operation {
+    SaveCollection {
+        record: Box<CollectionRecord>,
+    },
+    UpdateCollection {
+        before: Box<CollectionRecord>,
+        after: Box<CollectionRecord>,
+    },
+    DeleteCollection {
+        id: u64,
+        name: String,
+    },
+    CreateProfile {
+        profile: Box<BrowserProfile>,
+    },
+    UpdateProfile {
+        before: Box<BrowserProfile>,
+        after: Box<BrowserProfile>,
+    },
+    DeleteProfile {
+        profile: Box<BrowserProfile>,
+    },
+    SetDefaultBrowserMode {
+        before: BrowserMode,
+        after: BrowserMode,
+    },
+    /// Whole-database exports taken around the import, so a replace can be
+    /// reverted. Only applied while the database is still as the other side
+    /// left it, see `ensure_unchanged`
+    ImportDatabase {
+        before: String,
+        after: String,
+        replace_existing: bool,
+    },
+    RepairDatabase {
+        before: String,
+        after: String,
+    },
+}
+
+impl Operation {
+    pub fn description(&self) -> String {
+        match self {
+            Operation::SaveCollection { record } => format!("Save collection '{}'", record.name),
+            Operation::UpdateCollection { after, .. } => {
+                format!("Update collection '{}'", after.name)
+            }
+            Operation::DeleteCollection { name, .. } => format!("Delete collection '{}'", name),
+            Operation::CreateProfile { profile } => format!("Create profile '{}'", profile.name),
+            Operation::UpdateProfile { after, .. } => format!("Update profile '{}'", after.name),
+            Operation::DeleteProfile { profile } => format!("Delete profile '{}'", profile.name),
+            Operation::SetDefaultBrowserMode { after, .. } => {
+                format!("Set default browser mode to {:?}", after)
+            }
+            Operation::ImportDatabase {
+                replace_existing: true,
+                ..
+            } => "Import database (replace)".to_string(),
+            Operation::ImportDatabase { .. } => "Import database (merge)".to_string(),
+            Operation::RepairDatabase { .. } => "Repair database".to_string(),
+        }
+    }
+
+    fn undo(&self, store: &dyn Store) -> Result<(), Box<dyn std::error::Error>> {
+        match self {
+            // Undoing a save moves the collection to the trash, where redo finds it again
+            Operation::SaveCollection { record } => {
+                store.delete_by_id(record.id)?;
+            }
+            Operation::UpdateCollection { before, .. } => {
+                store.update(before.as_ref().clone())?;
+            }
+            Operation::DeleteCollection { id, .. } => restore_from_trash(store, *id)?,
+            Operation::CreateProfile { profile } => {
+                store.delete_profile(&profile.id)?;
+            }
+            Operation::UpdateProfile { before, after } => {
+                store.update_profile(&after.id, before.as_ref().clone())?;
+            }
+            Operation::DeleteProfile { profile } => {
+                store.create_profile(profile.as_ref().clone())?;
+            }
+            Operation::SetDefaultBrowserMode { before, .. } => {
+                store.set_default_browser_mode(before.clone())?;
+            }
+            Operation::ImportDatabase { before, after, .. } | Operation::RepairDatabase { before, after } => {
+                ensure_unchanged(store, after, || format!("'{}' can no longer be undone", self.description()))?;
+                store.import_from_json(before.clone(), true)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn redo(&self, store: &dyn Store) -> Result<(), Box<dyn std::error::Error>> {
+        match self {
+            Operation::SaveCollection { record } => restore_from_trash(store, record.id)?,
+            Operation::UpdateCollection { after, .. } => {
+                store.update(after.as_ref().clone())?;
+            }
+            Operation::DeleteCollection { id, .. } => {
+                store.delete_by_id(*id)?;
+            }
+            Operation::CreateProfile { profile } => {
+                store.create_profile(profile.as_ref().clone())?;
+            }
+            Operation::UpdateProfile { before, after } => {
+                store.update_profile(&before.id, after.as_ref().clone())?;
+            }
+            Operation::DeleteProfile { profile } => {
+                store.delete_profile(&profile.id)?;
+            }
+            Operation::SetDefaultBrowserMode { after, .. } => {
+                store.set_default_browser_mode(after.clone())?;
+            }
+            Operation::ImportDatabase { before, after, .. } | Operation::RepairDatabase { before, after } => {
+                ensure_unchanged(store, before, || format!("'{}' can no longer be redone", self.description()))?;
+                store.import_from_json(after.clone(), true)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn restore_from_trash(store: &dyn Store, id: u64) -> Result<(), Box<dyn std::error::Error>> {
+    store
+        .restore_from_trash(id)?
+        .ok_or_else(|| AppError::not_found(format!("Collection with id {} is no longer in the trash", id)))?;
+    Ok(())
+}
+
+/// Replacing the whole database with a copy would throw away anything changed
+/// since the copy was taken, so refuse unless it was last written as in `expected`.
+/// A replace keeps the copy's meta, so this also holds after an undo or redo.
+fn ensure_unchanged(
+    store: &dyn Store,
+    expected: &str,
+    refusal: impl FnOnce() -> String,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (current, _) = JsonStore::parse_database(&store.export_to_json()?)?;
+    let (expected, _) = JsonStore::parse_database(expected)?;
+    if current.meta.last_updated != expected.meta.last_updated {
+        return Err(AppError::conflict(format!("The database has changed since, so {}", refusal())).into());
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+struct JournalEntry {
+    operation: Operation,
+    recorded_at: DateTime<Utc>,
+}
+
+impl JournalEntry {
+    fn info(&self) -> JournalEntryInfo {
+        JournalEntryInfo {
+            description: self.operation.description(),
+            recorded_at: self.recorded_at,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Stacks {
+    undo: Vec<JournalEntry>,
+    redo: Vec<JournalEntry>,
+}
+
+/// Session-wide undo/redo history of mutating commands.
+///
+/// Entries describe what changed rather than the store they changed, so the
+/// journal must be cleared whenever a different store is opened.
+#[derive(Debug, Default)]
+pub struct Journal {
+    stacks: Mutex<Stacks>,
+}
+
+impl Journal {
+    fn stacks(&self) -> MutexGuard<'_, Stacks> {
+        // Stacks are only pushed and popped whole, a panic can't leave them torn
+        self.stacks.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Record a completed operation; this discards anything that could be redone
+    pub fn record(&self, operation: Operation) {
+        let mut stacks = self.stacks();
+        info!("Journaled: {}", operation.description());
+
+        stacks.redo.clear();
+        stacks.undo.push(JournalEntry {
+            operation,
+            recorded_at: Utc::now(),
+        });
+        if stacks.undo.len() > MAX_JOURNAL_ENTRIES {
+            stacks.undo.remove(0);
+        }
+    }
+
+    /// Revert the most recent operation, returning `None` if there is nothing to undo
+    #[instrument(skip(self, store))]
+    pub fn undo(&self, store: &dyn Store) -> Result<Option<JournalEntryInfo>, Box<dyn std::error::Error>> {
+        let mut stacks = self.stacks();
+        let Some(entry) = stacks.undo.pop() else {
+            return Ok(None);
+        };
+
+        if let Err(e) = entry.operation.undo(store) {
+            warn!("Failed to undo '{}': {}", entry.operation.description(), e);
+            stacks.undo.push(entry);
+            return Err(e);
+        }
+
+        info!("Undid: {}", entry.operation.description());
+        let info = entry.info();
+        stacks.redo.push(entry);
+        Ok(Some(info))
+    }
+
+    /// Reapply the most recently undone operation, returning `None` if there is nothing to redo
+    #[instrument(skip(self, store))]
+    pub fn redo(&self, store: &dyn Store) -> Result<Option<JournalEntryInfo>, Box<dyn std::error::Error>> {
+        let mut stacks = self.stacks();
+        let Some(entry) = stacks.redo.pop() else {
+            return Ok(None);
+        };
+
+        if let Err(e) = entry.operation.redo(store) {
+            warn!("Failed to redo '{}': {}", entry.operation.description(), e);
+            stacks.redo.push(entry);
+            return Err(e);
+        }
+
+        info!("Redid: {}", entry.operation.description());
+        let info = entry.info();
+        stacks.undo.push(entry);
+        Ok(Some(info))
+    }
+
+    pub fn status(&self) -> JournalStatus {
+        let stacks = self.stacks();
+        JournalStatus {
+            next_undo: stacks.undo.last().map(JournalEntry::info),
+            next_redo: stacks.redo.last().map(JournalEntry::info),
+        }
+    }
+
+    pub fn clear(&self) {
+        let mut stacks = self.stacks();
+        stacks.undo.clear();
+        stacks.redo.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CollectionData;
+    use crate::services::CollectionService;
+    use crate::test_support::{json_store, site};
+    use std::sync::Arc;
+
+    fn temp_service(name: &str) -> (CollectionService, Arc<Journal>, Arc<dyn Store>) {
+        let store: Arc<dyn Store> = Arc::new(json_store(name));
+        let journal = Arc::new(Journal::default());
+        (CollectionService::new(store.clone(), journal.clone()), journal, store)
+    }
+
+    fn collection(name: &str, url: &str) -> CollectionData {
+        CollectionData {
+            sites: vec![site(url, url)],
+            created_at: Utc::now(),
+            name: Some(name.to_string()),
+            config: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_undo_and_redo_save_and_update() {
+        let (service, journal, store) = temp_service("save");
+        let saved = service.save_collection(collection("Work", "https://a.com")).unwrap();
+        service
+            .update_collection(saved.id, collection("Work", "https://b.com"))
+            .unwrap();
+
+        let undone = journal.undo(store.as_ref()).unwrap().unwrap();
+        assert_eq!(undone.description, "Update collection 'Work'");
+        assert_eq!(service.get_collection(saved.id).unwrap().unwrap().sites[0].url, "https://a.com");
+
+        journal.undo(store.as_ref()).unwrap();
+        assert!(service.get_collection(saved.id).unwrap().is_none());
+        assert!(journal.undo(store.as_ref()).unwrap().is_none());
+
+        journal.redo(store.as_ref()).unwrap();
+        journal.redo(store.as_ref()).unwrap();
+        assert_eq!(service.get_collection(saved.id).unwrap().unwrap().sites[0].url, "https://b.com");
+        assert!(journal.status().next_redo.is_none());
+    }
+
+    #[test]
+    fn test_undo_replace_import_restores_database() {
+        let (service, journal, store) = temp_service("import");
+        service.save_collection(collection("Keep me", "https://a.com")).unwrap();
+
+        let (other, _, _) = temp_service("import-source");
+        other.save_collection(collection("Other", "https://b.com")).unwrap();
+        service
+            .import_database(other.export_database(None, None).unwrap(), true, None)
+            .unwrap();
+        assert_eq!(service.load_all_collections().unwrap()[0].name, "Other");
+
+        journal.undo(store.as_ref()).unwrap();
+        let collections = service.load_all_collections().unwrap();
+        assert_eq!(collections.len(), 1);
+        assert_eq!(collections[0].name, "Keep me");
+
+        // Changes made outside the journal since the redo would be lost by undoing it
+        journal.redo(store.as_ref()).unwrap();
+        let other_id = service.load_all_collections().unwrap()[0].id;
+        store.delete_by_id(other_id).unwrap();
+        assert!(journal.undo(store.as_ref()).is_err());
+        assert!(service.load_all_collections().unwrap().is_empty());
+    }
+}
@@ -1,10 +1,32 @@
 // Module declarations
+mod backups;
+mod bookmarks;
+mod browser_data;
+mod chromium_session;
 mod commands;
+mod crypto;
 mod database;
+mod duplicates;
+mod error;
+mod exporters;
+mod firefox_session;
+mod importers;
+mod integrity;
+mod journal;
+mod migrations;
 mod models;
+mod normalize;
+mod parser;
+mod selection;
 mod services;
+mod sqlite_store;
+mod state;
 mod utils;
+mod workspaces;
+#[cfg(test)]
+mod test_support;
 
+use state::AppState;
 use tracing::info;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -25,17 +47,48 @@ pub fn run() {
 
     info!("Starting Restore Sites application");
 
+    if let Some(data_dir) = utils::data_dir_from_args(std::env::args().skip(1)) {
+        info!("Using data directory from command line: {}", data_dir.display());
+        utils::set_data_dir_override(data_dir);
+    }
+
+    // A database that fails to open is reported through `get_startup_error`
+    let app_state = AppState::new();
+    // An encrypted database is maintained once the user unlocks it
+    if app_state.startup_error().is_none() && app_state.collections().encryption_status().unlocked {
+        app_state.collections().run_startup_maintenance();
+    }
+
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
+        .manage(app_state)
         .invoke_handler(tauri::generate_handler![
             commands::greet,
+            commands::parse_sites_text,
             commands::save_collection,
             commands::load_collections,
             commands::restore_collection,
             commands::get_collection,
             commands::update_collection,
             commands::delete_collection,
+            // Trash
+            commands::list_trash,
+            commands::restore_from_trash,
+            commands::empty_trash,
+            commands::get_trash_retention_days,
+            commands::set_trash_retention_days,
+            // Revision History
+            commands::list_revisions,
+            commands::diff_revisions,
+            commands::rollback_collection,
+            // Duplicates
+            commands::find_duplicates,
+            commands::dedupe_collection,
+            // Undo/Redo
+            commands::undo_last_operation,
+            commands::redo_operation,
+            commands::get_journal_status,
             // Browser Profile Management
             commands::create_browser_profile,
             commands::get_browser_profiles,
@@ -50,7 +103,44 @@ pub fn run() {
             // Backup and Restore
             commands::export_database,
             commands::export_database_to_file,
-            commands::import_database
+            commands::import_database,
+            commands::merge_database,
+            commands::preview_import,
+            commands::import_selected,
+            commands::import_bookmarks_html,
+            commands::import_from_format,
+            commands::export_bookmarks_html,
+            commands::export_collections,
+            // Browser Sessions
+            commands::list_firefox_sessions,
+            commands::read_firefox_session,
+            commands::import_firefox_session,
+            commands::list_chromium_sessions,
+            commands::read_chromium_session,
+            commands::import_chromium_session,
+            // Browser Bookmarks and History
+            commands::list_browser_data_profiles,
+            commands::import_browser_data,
+            commands::list_backups,
+            commands::restore_backup,
+            // Integrity
+            commands::verify_database,
+            commands::repair_database,
+            // Storage Backend
+            commands::get_storage_backend,
+            commands::migrate_to_sqlite,
+            commands::get_startup_error,
+            commands::reopen_database,
+            // Encryption
+            commands::get_encryption_status,
+            commands::unlock_database,
+            commands::enable_encryption,
+            commands::change_passphrase,
+            commands::disable_encryption,
+            // Workspaces
+            commands::get_workspaces,
+            commands::create_workspace,
+            commands::switch_workspace
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
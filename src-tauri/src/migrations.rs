@@ -0,0 +1,285 @@
+use crate::error::AppError;
+use crate::models::{UrlNormalization, DEFAULT_TRASH_RETENTION_DAYS};
+use crate::normalize::canonical_url;
+use chrono::Utc;
+use serde_json::{json, Map, Value};
+use tracing::{info, instrument};
+
+/// Schema version written by this build (v2 added browser profiles, v3 the trash,
+/// v4 revisions, v5 canonical URLs)
+pub const CURRENT_VERSION: u32 = 5;
+
+/// Returned for files written by a newer build; opening them could lose data
+#[derive(Debug)]
+pub struct UnsupportedVersion(pub u32);
+
+impl std::fmt::Display for UnsupportedVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Database schema v{} is newer than this version of Restore Sites supports (v{}), please update the app",
+            self.0, CURRENT_VERSION
+        )
+    }
+}
+
+impl std::error::Error for UnsupportedVersion {}
+
+type MigrationStep = fn(&mut Value) -> Result<(), Box<dyn std::error::Error>>;
+
+/// Ordered upgrade steps, keyed by the version they upgrade from
+const MIGRATIONS: &[(u32, MigrationStep)] = &[
+    (1, migrate_v1_to_v2),
+    (2, migrate_v2_to_v3),
+    (3, migrate_v3_to_v4),
+    (4, migrate_v4_to_v5),
+];
+
+/// Read `meta.version` from a raw database document.
+///
+/// Files from the restore.py era have no `meta` block at all and are treated as v1.
+pub fn schema_version(value: &Value) -> Result<u32, Box<dyn std::error::Error>> {
+    match value.get("meta").and_then(|meta| meta.get("version")) {
+        None => Ok(1),
+        Some(version) => version
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .filter(|v| *v > 0)
+            .ok_or_else(|| AppError::corrupt(format!("Invalid database schema version: {}", version)).into()),
+    }
+}
+
+/// Upgrade a raw database document to `CURRENT_VERSION`.
+///
+/// Returns the migrated document together with the version it was read at, so
+/// callers can decide whether a pre-migration backup is needed.
+#[instrument(skip(value))]
+pub fn migrate(mut value: Value) -> Result<(Value, u32), Box<dyn std::error::Error>> {
+    let original_version = schema_version(&value)?;
+
+    if original_version > CURRENT_VERSION {
+        return Err(UnsupportedVersion(original_version).into());
+    }
+
+    let mut version = original_version;
+    while version < CURRENT_VERSION {
+        let step = MIGRATIONS
+            .iter()
+            .find(|(from, _)| *from == version)
+            .map(|(_, step)| step)
+            .ok_or_else(|| format!("No migration registered from schema v{}", version))?;
+
+        step(&mut value)?;
+        version += 1;
+        value["meta"]["version"] = json!(version);
+        info!("Migrated database schema v{} -> v{}", version - 1, version);
+    }
+
+    Ok((value, original_version))
+}
+
+/// v1 (restore.py) -> v2: add profiles, per-record config and the full meta block
+fn migrate_v1_to_v2(value: &mut Value) -> Result<(), Box<dyn std::error::Error>> {
+    // The oldest files were a bare list of collections
+    if value.is_array() {
+        *value = json!({ "data": value.take() });
+    }
+
+    let root = value
+        .as_object_mut()
+        .ok_or_else(|| AppError::corrupt("Database root must be a JSON object"))?;
+    let now = json!(Utc::now());
+
+    let (max_id, record_count) = {
+        let records = root
+            .entry("data")
+            .or_insert_with(|| json!([]))
+            .as_array_mut()
+            .ok_or_else(|| AppError::corrupt("Database 'data' must be an array"))?;
+
+        let mut max_id = records
+            .iter()
+            .filter_map(|record| record.get("id").and_then(Value::as_u64))
+            .max()
+            .unwrap_or(0);
+
+        for record in records.iter_mut() {
+            let record = record
+                .as_object_mut()
+                .ok_or_else(|| AppError::corrupt("Collection records must be JSON objects"))?;
+
+            let id = match record.get("id").and_then(Value::as_u64) {
+                Some(id) => id,
+                None => {
+                    max_id += 1;
+                    record.insert("id".to_string(), json!(max_id));
+                    max_id
+                }
+            };
+
+            record
+                .entry("name")
+                .or_insert_with(|| json!(format!("Collection {}", id)));
+            let created_at = record.entry("created_at").or_insert(now.clone()).clone();
+            record.entry("updated_at").or_insert(created_at);
+            record
+                .entry("config")
+                .or_insert_with(|| Value::Object(Map::new()));
+
+            // restore.py stored plain URL strings instead of title/url pairs
+            if let Some(sites) = record
+                .entry("sites")
+                .or_insert_with(|| json!([]))
+                .as_array_mut()
+            {
+                for site in sites.iter_mut() {
+                    if let Some(url) = site.as_str() {
+                        *site = json!({ "title": url, "url": url });
+                    }
+                }
+            }
+        }
+
+        (max_id, records.len())
+    };
+
+    let meta = root
+        .entry("meta")
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .ok_or_else(|| AppError::corrupt("Database 'meta' must be an object"))?;
+
+    let stored_max_id = meta.get("max_id").and_then(Value::as_u64).unwrap_or(0);
+    meta.insert("max_id".to_string(), json!(stored_max_id.max(max_id)));
+    meta.insert("record_count".to_string(), json!(record_count));
+    meta.entry("last_updated_id").or_insert(json!(max_id));
+    meta.entry("last_updated").or_insert(now.clone());
+    meta.entry("created_at").or_insert(now);
+    meta.entry("default_browser_mode")
+        .or_insert_with(|| json!("Incognito"));
+
+    root.entry("profiles").or_insert_with(|| json!([]));
+
+    Ok(())
+}
+
+/// v2 -> v3: add the trash for soft-deleted collections and its retention period
+fn migrate_v2_to_v3(value: &mut Value) -> Result<(), Box<dyn std::error::Error>> {
+    let root = value
+        .as_object_mut()
+        .ok_or_else(|| AppError::corrupt("Database root must be a JSON object"))?;
+
+    root.entry("trash").or_insert_with(|| json!([]));
+    root.entry("meta")
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .ok_or_else(|| AppError::corrupt("Database 'meta' must be an object"))?
+        .entry("trash_retention_days")
+        .or_insert(json!(DEFAULT_TRASH_RETENTION_DAYS));
+
+    Ok(())
+}
+
+/// v3 -> v4: add per-collection revision history
+fn migrate_v3_to_v4(value: &mut Value) -> Result<(), Box<dyn std::error::Error>> {
+    value
+        .as_object_mut()
+        .ok_or_else(|| AppError::corrupt("Database root must be a JSON object"))?
+        .entry("revisions")
+        .or_insert_with(|| json!([]));
+
+    Ok(())
+}
+
+/// v4 -> v5: store each site's canonical URL next to the one it was saved with.
+///
+/// No collection has normalization settings yet, so the defaults apply.
+fn migrate_v4_to_v5(value: &mut Value) -> Result<(), Box<dyn std::error::Error>> {
+    let defaults = UrlNormalization::default();
+    let root = value
+        .as_object_mut()
+        .ok_or_else(|| AppError::corrupt("Database root must be a JSON object"))?;
+
+    for (key, pointer) in [("data", "/sites"), ("trash", "/record/sites"), ("revisions", "/sites")] {
+        let Some(Value::Array(items)) = root.get_mut(key) else {
+            continue;
+        };
+        let sites = items
+            .iter_mut()
+            .filter_map(|item| item.pointer_mut(pointer))
+            .filter_map(Value::as_array_mut)
+            .flatten()
+            .filter_map(Value::as_object_mut);
+
+        for site in sites {
+            if let Some(url) = site.get("url").and_then(Value::as_str) {
+                let canonical = canonical_url(url, &defaults);
+                site.insert("canonical_url".to_string(), json!(canonical));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Database;
+
+    #[test]
+    fn test_current_version_is_untouched() {
+        let value = serde_json::to_value(Database::default()).unwrap();
+        let (migrated, version) = migrate(value.clone()).unwrap();
+        assert_eq!(version, CURRENT_VERSION);
+        assert_eq!(migrated, value);
+    }
+
+    #[test]
+    fn test_migrate_v1_to_current() {
+        let v1 = json!({
+            "meta": { "version": 1, "max_id": 2 },
+            "data": [
+                { "id": 2, "name": "Work", "sites": ["https://example.com"], "created_at": "2024-01-01T00:00:00Z" },
+                { "sites": [{ "title": "Docs", "url": "https://docs.rs" }] }
+            ]
+        });
+
+        let (migrated, version) = migrate(v1).unwrap();
+        assert_eq!(version, 1);
+
+        let database: Database = serde_json::from_value(migrated).unwrap();
+        assert_eq!(database.meta.version, CURRENT_VERSION);
+        assert_eq!(database.meta.max_id, 3);
+        assert_eq!(database.meta.record_count, 2);
+        assert!(database.profiles.is_empty());
+        assert!(database.trash.is_empty());
+        assert!(database.revisions.is_empty());
+        assert_eq!(database.meta.trash_retention_days, DEFAULT_TRASH_RETENTION_DAYS);
+        assert_eq!(database.data[0].sites[0].title, "https://example.com");
+        assert_eq!(
+            database.data[0].sites[0].canonical_url.as_deref(),
+            Some("https://example.com/")
+        );
+        assert_eq!(database.data[1].id, 3);
+        assert_eq!(database.data[1].name, "Collection 3");
+    }
+
+    #[test]
+    fn test_bare_list_is_treated_as_v1() {
+        let v1 = json!([{ "name": "Old", "sites": [] }]);
+        let (migrated, version) = migrate(v1).unwrap();
+        assert_eq!(version, 1);
+
+        let database: Database = serde_json::from_value(migrated).unwrap();
+        assert_eq!(database.data.len(), 1);
+        assert_eq!(database.data[0].id, 1);
+    }
+
+    #[test]
+    fn test_newer_version_is_rejected() {
+        let future = json!({ "meta": { "version": CURRENT_VERSION + 1 }, "profiles": [], "data": [] });
+        let err = migrate(future).unwrap_err();
+        assert!(err.is::<UnsupportedVersion>());
+    }
+}
@@ -1,10 +1,18 @@
+use crate::migrations::CURRENT_VERSION;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+pub const DEFAULT_TRASH_RETENTION_DAYS: u32 = 30;
+
+/// Older revisions beyond this are dropped when a collection is updated
+pub const MAX_REVISIONS_PER_COLLECTION: usize = 20;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SiteEntry {
     pub title: String,
-    pub url: String,
+    pub url: String,                         // As entered; this is what gets opened
+    #[serde(default)]
+    pub canonical_url: Option<String>,       // Normalized for comparisons, filled in on save
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -22,16 +30,28 @@ pub struct CollectionData {
     pub config: CollectionConfig,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CollectionConfig {
     pub browser_profile_id: Option<String>,  // PRIMARY: Reference to profile
     // Fallback fields for direct config
     pub browser: Option<Browser>,
     pub mode: Option<BrowserMode>,
     pub custom_path: Option<String>,
+    #[serde(default)]
+    pub url_normalization: Option<UrlNormalization>,  // None uses the defaults
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+// How a collection's URLs are canonicalized; see `normalize::canonical_url`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UrlNormalization {
+    pub drop_fragment: bool,
+    pub sort_query: bool,
+    pub strip_tracking_params: bool,
+    pub tracking_params: Vec<String>,  // Exact names, or prefixes like "utm_*"
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Browser {
     Chrome,
     Firefox,
@@ -40,7 +60,7 @@ pub enum Browser {
     Custom(String),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum BrowserMode {
     Normal,
     Incognito,
@@ -61,23 +81,48 @@ pub struct BrowserProfile {
     pub updated_at: DateTime<Utc>,
 }
 
+// Where the database is persisted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StorageBackend {
+    Json,
+    Sqlite,
+}
+
+// Whether the database file is encrypted at rest, and whether it has been unlocked this session
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct EncryptionStatus {
+    pub encrypted: bool,
+    pub unlocked: bool,
+}
+
+// A named database living in its own directory under the data directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceInfo {
+    pub name: String,
+    pub path: String,
+    pub is_active: bool,
+}
+
 // JSON Database Schema Structures
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DatabaseMeta {
-    pub version: u32,                  // Schema version (v2 for profiles)
+    pub version: u32,                  // Schema version (v2 for profiles, v3 for trash, v4 for revisions, v5 for canonical URLs)
     pub last_updated_id: u64,
     pub last_updated: DateTime<Utc>,
     pub max_id: u64,
     pub record_count: usize,
     pub created_at: DateTime<Utc>,
     pub default_browser_mode: BrowserMode,  // Global default mode
+    pub trash_retention_days: u32,     // Purge trashed collections after this many days, 0 = never
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Database {
     pub meta: DatabaseMeta,
     pub profiles: Vec<BrowserProfile>,  // Browser profiles array
     pub data: Vec<CollectionRecord>,
+    pub trash: Vec<TrashedCollection>,  // Soft-deleted collections
+    pub revisions: Vec<CollectionRevision>,  // Previous versions of updated collections
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -90,17 +135,332 @@ pub struct CollectionRecord {
     pub updated_at: DateTime<Utc>,
 }
 
+// A deleted collection kept until restored or purged
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashedCollection {
+    pub record: CollectionRecord,
+    pub deleted_at: DateTime<Utc>,
+}
+
+// A collection as it was before an update, numbered per collection from 1
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollectionRevision {
+    pub collection_id: u64,
+    pub revision: u32,
+    pub name: String,
+    pub sites: Vec<SiteEntry>,
+    pub config: CollectionConfig,
+    pub saved_at: DateTime<Utc>,       // When this version was last saved
+}
+
+// A site kept between two revisions but under a different title
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetitledSite {
+    pub url: String,
+    pub old_title: String,
+    pub new_title: String,
+}
+
+// Site-level changes between two revisions; `to_revision: None` is the current version
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevisionDiff {
+    pub collection_id: u64,
+    pub from_revision: u32,
+    pub to_revision: Option<u32>,
+    pub added: Vec<SiteEntry>,
+    pub removed: Vec<SiteEntry>,
+    pub retitled: Vec<RetitledSite>,
+}
+
+// Why an automatic backup was taken; part of the backup's file name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BackupReason {
+    Startup,
+    BeforeImport,
+    BeforeRepair,
+}
+
+// A database snapshot in the backups directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupInfo {
+    pub file_name: String,
+    pub path: String,
+    pub reason: BackupReason,
+    pub created_at: DateTime<Utc>,
+    pub size_bytes: u64,
+}
+
+// What the paste parser noticed about a line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ParseDiagnosticKind {
+    SkippedLine,
+    RepairedUrl,
+    DuplicateUrl,
+    MissingTitle,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParseDiagnostic {
+    pub line: usize,                   // 1-based line number in the pasted text
+    pub kind: ParseDiagnosticKind,
+    pub message: String,
+}
+
+// Result of `parse_sites_text`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ParsedSites {
+    pub sites: Vec<SiteEntry>,
+    pub diagnostics: Vec<ParseDiagnostic>,
+}
+
+// A site as listed in a duplicate report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateSite {
+    pub collection_id: u64,
+    pub position: usize,               // 0-based index in the collection's sites
+    pub title: String,
+    pub url: String,
+}
+
+// Sites sharing a canonical URL, with the collections they belong to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub canonical_url: String,
+    pub collection_ids: Vec<u64>,
+    pub sites: Vec<DuplicateSite>,
+}
+
+// Result of `find_duplicates`; a URL repeated inside a collection has one group per collection
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DuplicateReport {
+    pub within_collections: Vec<DuplicateGroup>,
+    pub across_collections: Vec<DuplicateGroup>,
+}
+
+// Which occurrence survives when a collection is deduplicated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DedupeStrategy {
+    KeepFirst,
+    KeepNewestTitle,                   // First position, title of the last-added occurrence
+}
+
+// Result of `dedupe_collection`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DedupeResult {
+    pub collection: CollectionRecord,
+    pub removed: Vec<SiteEntry>,
+}
+
+// What to read from a browser profile
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BrowserDataSource {
+    Bookmarks,
+    History,
+}
+
+// Narrows what is imported from browser bookmarks or history; empty fields match everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BrowserDataFilter {
+    pub folder: Option<String>,            // Folder path such as "Bookmarks bar / Work", subfolders included
+    pub since: Option<DateTime<Utc>>,      // Added (bookmarks) or last visited (history)
+    pub until: Option<DateTime<Utc>>,
+    pub domain: Option<String>,            // Host, subdomains included
+    pub min_visit_count: Option<u32>,
+}
+
+// A detected browser profile and the data that can be imported from it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrowserDataProfile {
+    pub browser: Browser,
+    pub profile: String,                   // Profile directory name
+    pub path: String,
+    pub sources: Vec<BrowserDataSource>,
+}
+
+// Exports of other tab managers that can be imported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImportFormat {
+    OneTab,
+    SessionBuddy,
+    Toby,
+}
+
+// Plain-text formats collections can be exported to, besides a JSON backup
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExportFormat {
+    Markdown,
+    Csv,
+    Opml,
+    UrlList,
+}
+
+// Result of importing collections from another format, merged like `import_database`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub created: Vec<CollectionRecord>,    // Including renamed ones, under their new names
+    pub skipped_collections: Vec<String>,  // Names that were taken, with the Skip strategy
+    pub skipped_sites: usize,              // Entries without an openable URL
+    #[serde(default)]
+    pub profiles: Vec<BrowserProfile>,     // Browser profiles added, under their final IDs
+    #[serde(default)]
+    pub renamed: Vec<RenamedCollection>,
+    #[serde(default)]
+    pub overwritten: Vec<String>,
+    #[serde(default)]
+    pub merged: Vec<MergedCollection>,
+    #[serde(default)]
+    pub dropped_paths: Vec<String>,        // Collections whose imported browser path isn't used here
+}
+
+// How a merging import handles a collection whose name is already taken
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MergeStrategy {
+    Skip,
+    Rename,                                // Import it as "Name (2)", "Name (3)", ...
+    Overwrite,                             // Replace the existing one's sites and config
+    Union,                                 // Add the sites the existing one lacks, by canonical URL
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenamedCollection {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergedCollection {
+    pub name: String,
+    pub added_sites: usize,
+}
+
+// What a merging import did, or would do in a dry run; names are as in the file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MergeReport {
+    pub added: Vec<String>,
+    pub skipped: Vec<String>,
+    pub renamed: Vec<RenamedCollection>,
+    pub overwritten: Vec<String>,
+    pub merged: Vec<MergedCollection>,
+    pub dry_run: bool,
+}
+
+// What happens to an incoming browser profile on import
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProfileImportStatus {
+    New,
+    Existing,                              // An identical profile is already here and is reused
+    IdConflict,                            // The ID is taken by another profile, so it gets a new one
+}
+
+// An incoming collection, listed before choosing what to import
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportPreviewCollection {
+    pub id: u64,                           // ID in the file, used to select it
+    pub name: String,
+    pub site_count: usize,
+    pub browser_profile_id: Option<String>,
+    pub name_exists: bool,                 // Handled by the merge strategy chosen on import
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportPreviewProfile {
+    pub profile: BrowserProfile,
+    pub status: ProfileImportStatus,
+}
+
+// Contents of an export file, checked against the current database
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportPreview {
+    pub collections: Vec<ImportPreviewCollection>,
+    pub profiles: Vec<ImportPreviewProfile>,
+}
+
+// Collections and profiles picked from an import preview, by their IDs in the file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportSelection {
+    pub collection_ids: Vec<u64>,
+    pub profile_ids: Vec<String>,
+}
+
+// A browser's saved session file found on disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionFile {
+    pub path: String,
+    pub browser: Browser,
+    pub profile: String,               // Profile directory name
+    pub modified_at: DateTime<Utc>,
+}
+
+// An open window of a saved browser session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionWindow {
+    pub tabs: Vec<SiteEntry>,
+    pub selected_tab: Option<usize>,   // Index into `tabs` of the active tab, if it was kept
+}
+
+// The windows and tabs read from a session file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrowserSession {
+    pub browser: Browser,
+    pub saved_at: DateTime<Utc>,
+    pub windows: Vec<SessionWindow>,
+    pub skipped_tabs: usize,           // Pinned when asked, or without an openable URL
+}
+
+// Kinds of inconsistency found by the integrity check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IntegrityIssueKind {
+    DuplicateId,
+    MaxIdTooLow,
+    WrongRecordCount,
+    StaleLastUpdatedId,
+    DanglingProfileReference,
+    MultipleDefaultProfiles,
+    InvalidUrl,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrityIssue {
+    pub kind: IntegrityIssueKind,
+    pub message: String,
+    pub collection_id: Option<u64>,    // Affected collection, if any
+}
+
+// Result of `repair_database`: what was fixed, what needs a manual fix, and the backup taken first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepairReport {
+    pub fixed: Vec<IntegrityIssue>,
+    pub remaining: Vec<IntegrityIssue>,
+    pub backup: Option<BackupInfo>,
+}
+
+// An undoable operation as shown to the user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntryInfo {
+    pub description: String,
+    pub recorded_at: DateTime<Utc>,
+}
+
+// What undo and redo would currently act on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalStatus {
+    pub next_undo: Option<JournalEntryInfo>,
+    pub next_redo: Option<JournalEntryInfo>,
+}
+
 impl Default for DatabaseMeta {
     fn default() -> Self {
         let now = Utc::now();
         Self {
-            version: 2,  // Start with schema v2
+            version: CURRENT_VERSION,
             last_updated_id: 0,
             last_updated: now,
             max_id: 0,
             record_count: 0,
             created_at: now,
             default_browser_mode: BrowserMode::Incognito,  // Default to Incognito
+            trash_retention_days: DEFAULT_TRASH_RETENTION_DAYS,
         }
     }
 }
@@ -111,6 +471,8 @@ impl Default for Database {
             meta: DatabaseMeta::default(),
             profiles: Vec::new(),
             data: Vec::new(),
+            trash: Vec::new(),
+            revisions: Vec::new(),
         }
     }
 }
@@ -122,10 +484,18 @@ impl Default for CollectionConfig {
             browser: None,             // No direct config by default
             mode: None,
             custom_path: None,
+            url_normalization: None,
         }
     }
 }
 
+impl MergeReport {
+    /// Collections created by the import
+    pub fn created_count(&self) -> usize {
+        self.added.len() + self.renamed.len()
+    }
+}
+
 // Validation functions for Browser Profiles
 impl BrowserProfile {
     pub fn validate_name(name: &str) -> Result<(), String> {
@@ -0,0 +1,104 @@
+use crate::models::{CollectionConfig, SiteEntry, UrlNormalization};
+use url::Url;
+
+/// Tracking parameters stripped by default; a trailing `*` matches a prefix
+pub const DEFAULT_TRACKING_PARAMS: &[&str] = &[
+    "utm_*", "fbclid", "gclid", "dclid", "gbraid", "wbraid", "msclkid", "mc_cid", "mc_eid",
+    "yclid", "igshid", "_hsenc", "_hsmi",
+];
+
+impl Default for UrlNormalization {
+    fn default() -> Self {
+        Self {
+            drop_fragment: false,
+            sort_query: true,
+            strip_tracking_params: true,
+            tracking_params: DEFAULT_TRACKING_PARAMS.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+}
+
+fn is_tracking_param(name: &str, patterns: &[String]) -> bool {
+    let name = name.to_ascii_lowercase();
+    patterns.iter().any(|pattern| {
+        let pattern = pattern.to_ascii_lowercase();
+        match pattern.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => name == pattern,
+        }
+    })
+}
+
+/// Canonical form of `url`, used to compare sites rather than to open them.
+///
+/// Parsing with the `url` crate already lowercases the host, converts IDN hosts
+/// to punycode, drops the scheme's default port and resolves `.`/`..` segments;
+/// `options` adds the lossy steps. URLs that don't parse are kept as written.
+pub fn canonical_url(url: &str, options: &UrlNormalization) -> String {
+    let trimmed = url.trim();
+    let Ok(mut parsed) = Url::parse(trimmed) else {
+        return trimmed.to_string();
+    };
+
+    if options.drop_fragment {
+        parsed.set_fragment(None);
+    }
+
+    if parsed.query().is_some() {
+        let mut pairs: Vec<(String, String)> = parsed
+            .query_pairs()
+            .into_owned()
+            .filter(|(name, _)| {
+                !(options.strip_tracking_params && is_tracking_param(name, &options.tracking_params))
+            })
+            .collect();
+        if options.sort_query {
+            // Stable, so repeated keys keep their relative order
+            pairs.sort_by(|a, b| a.0.cmp(&b.0));
+        }
+
+        if pairs.is_empty() {
+            parsed.set_query(None);
+        } else {
+            parsed.query_pairs_mut().clear().extend_pairs(pairs);
+        }
+    }
+
+    parsed.to_string()
+}
+
+/// Fill in `canonical_url` for every site using the collection's settings
+pub fn canonicalize_sites(sites: &mut [SiteEntry], config: &CollectionConfig) {
+    let options = config.url_normalization.clone().unwrap_or_default();
+    for site in sites {
+        site.canonical_url = Some(canonical_url(&site.url, &options));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonical_url() {
+        let defaults = UrlNormalization::default();
+        assert_eq!(
+            canonical_url(" HTTPS://Example.COM:443/a/../b?z=1&utm_source=x&a=2&FBCLID=y#top", &defaults),
+            "https://example.com/b?a=2&z=1#top"
+        );
+        assert_eq!(canonical_url("http://bücher.de:80", &defaults), "http://xn--bcher-kva.de/");
+        assert_eq!(canonical_url("https://a.com/?utm_medium=email", &defaults), "https://a.com/");
+        assert_eq!(canonical_url("not a url", &defaults), "not a url");
+
+        let options = UrlNormalization {
+            drop_fragment: true,
+            sort_query: false,
+            strip_tracking_params: true,
+            tracking_params: vec!["ref".to_string()],
+        };
+        assert_eq!(
+            canonical_url("https://a.com/p?z=1&ref=hn&utm_source=x#section", &options),
+            "https://a.com/p?z=1&utm_source=x"
+        );
+    }
+}
@@ -0,0 +1,322 @@
+use crate::integrity::is_openable_url;
+use crate::models::{ParseDiagnostic, ParseDiagnosticKind, ParsedSites, SiteEntry, UrlNormalization};
+use crate::normalize::canonical_url;
+use std::collections::HashSet;
+use tracing::{debug, instrument};
+
+/// Punctuation that ends a sentence or wraps a URL in prose, never part of the URL itself
+const TRAILING_PUNCTUATION: &[char] = &['.', ',', ';', ':', '!', '?', '\'', '"', '*', '>'];
+/// Wrappers that can precede a URL, e.g. `<https://...>` or `(https://...)`
+const LEADING_WRAPPERS: &[char] = &['<', '(', '[', '"', '\'', '*'];
+/// Left between a title and its URL or around list items, e.g. "- Docs: https://..."
+const TITLE_SEPARATORS: &[char] = &['-', '–', '—', ':', '|', '*', '•', '>'];
+
+/// A URL found on a line, before it is repaired and validated
+struct Candidate {
+    title: String,
+    raw_url: String,
+}
+
+enum Token {
+    Word(String),
+    Url { title: Option<String>, raw: String },
+}
+
+fn has_scheme(word: &str) -> bool {
+    let word = word.trim_start_matches(LEADING_WRAPPERS).to_ascii_lowercase();
+    word.starts_with("http://") || word.starts_with("https://")
+}
+
+/// A scheme-less token that is clearly a host, e.g. `example.com/page` or `www.example.com`
+fn looks_like_bare_url(word: &str) -> bool {
+    let word = strip_wrapping(word);
+    if word.contains('@') || word.contains("://") || !word.contains('.') {
+        return false;
+    }
+    url::Url::parse(&format!("https://{}", word))
+        .ok()
+        .and_then(|parsed| parsed.host_str().map(str::to_string))
+        .and_then(|host| host.rsplit('.').next().map(str::to_string))
+        .is_some_and(|tld| tld.len() >= 2 && tld.chars().all(|c| c.is_ascii_alphabetic()))
+}
+
+/// Drop leading wrappers and trailing punctuation; a closing bracket is only
+/// dropped when the URL doesn't open one itself, as in Wikipedia links
+fn strip_wrapping(raw: &str) -> &str {
+    let mut url = raw.trim().trim_start_matches(LEADING_WRAPPERS);
+    loop {
+        let Some(last) = url.chars().last() else {
+            return url;
+        };
+        let unbalanced = |open: char| url.matches(open).count() < url.matches(last).count();
+        let strip = TRAILING_PUNCTUATION.contains(&last)
+            || (last == ')' && unbalanced('('))
+            || (last == ']' && unbalanced('['));
+        if !strip {
+            return url;
+        }
+        url = &url[..url.len() - last.len_utf8()];
+    }
+}
+
+fn clean_title(title: &str) -> String {
+    title
+        .trim_matches(|c: char| c.is_whitespace() || TITLE_SEPARATORS.contains(&c))
+        .to_string()
+}
+
+/// Split out markdown `[title](url)` links, then whitespace-separated words and URLs
+fn tokenize(line: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut rest = line;
+
+    while let Some(open) = rest.find('[') {
+        let link = rest[open + 1..].find("](").and_then(|title_len| {
+            let url_start = open + 1 + title_len + 2;
+            let url_len = rest[url_start..].find(')')?;
+            Some((title_len, url_start, url_len))
+        });
+        let Some((title_len, url_start, url_len)) = link else {
+            break;
+        };
+
+        tokens.extend(rest[..open].split_whitespace().map(word_token));
+        // A link may carry a tooltip: [title](https://... "tooltip")
+        let target = rest[url_start..url_start + url_len]
+            .split_whitespace()
+            .next()
+            .unwrap_or_default();
+        tokens.push(Token::Url {
+            title: Some(rest[open + 1..open + 1 + title_len].to_string()),
+            raw: target.to_string(),
+        });
+        rest = &rest[url_start + url_len + 1..];
+    }
+
+    tokens.extend(rest.split_whitespace().map(word_token));
+    tokens
+}
+
+fn word_token(word: &str) -> Token {
+    if has_scheme(word) {
+        Token::Url {
+            title: None,
+            raw: word.to_string(),
+        }
+    } else {
+        Token::Word(word.to_string())
+    }
+}
+
+/// Pair each URL on a line with its title: the text since the previous URL,
+/// or for a lone URL the text after it, as in "https://... Title"
+fn candidates(line: &str) -> Vec<Candidate> {
+    let mut found = Vec::new();
+    let mut words: Vec<String> = Vec::new();
+
+    for token in tokenize(line) {
+        match token {
+            Token::Word(word) => words.push(word),
+            Token::Url { title, raw } => {
+                let title = title.unwrap_or_else(|| words.join(" "));
+                words.clear();
+                found.push(Candidate {
+                    title,
+                    raw_url: raw,
+                });
+            }
+        }
+    }
+
+    if found.is_empty() {
+        // No scheme anywhere: accept a bare host as the last word ("Docs example.com/docs")
+        if words.last().is_some_and(|word| looks_like_bare_url(word)) {
+            let raw_url = words.pop().unwrap_or_default();
+            found.push(Candidate {
+                title: words.join(" "),
+                raw_url,
+            });
+        }
+    } else if found.len() == 1 && clean_title(&found[0].title).is_empty() {
+        found[0].title = words.join(" ");
+    }
+
+    found
+}
+
+fn diagnostic(line: usize, kind: ParseDiagnosticKind, message: String) -> ParseDiagnostic {
+    ParseDiagnostic {
+        line,
+        kind,
+        message,
+    }
+}
+
+/// Parse pasted text, one or more sites per line, into site entries.
+///
+/// Accepts "Title https://url", tab-separated columns, markdown links, bare
+/// URLs and scheme-less hosts. Anything dropped or changed is reported in the
+/// diagnostics rather than silently fixed; blank lines are ignored.
+#[instrument(skip(text), fields(text_length = text.len()))]
+pub fn parse_sites_text(text: &str) -> ParsedSites {
+    let mut parsed = ParsedSites::default();
+    let mut seen = HashSet::new();
+    let defaults = UrlNormalization::default();
+
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let found = candidates(line);
+        if found.is_empty() {
+            parsed.diagnostics.push(diagnostic(
+                line_number,
+                ParseDiagnosticKind::SkippedLine,
+                format!("No URL found in '{}'", line),
+            ));
+            continue;
+        }
+
+        for candidate in found {
+            let stripped = strip_wrapping(&candidate.raw_url);
+            let url = if has_scheme(stripped) {
+                stripped.to_string()
+            } else {
+                format!("https://{}", stripped)
+            };
+
+            if !is_openable_url(&url) {
+                parsed.diagnostics.push(diagnostic(
+                    line_number,
+                    ParseDiagnosticKind::SkippedLine,
+                    format!("'{}' is not a valid URL", candidate.raw_url),
+                ));
+                continue;
+            }
+            if url != candidate.raw_url {
+                parsed.diagnostics.push(diagnostic(
+                    line_number,
+                    ParseDiagnosticKind::RepairedUrl,
+                    format!("'{}' read as '{}'", candidate.raw_url, url),
+                ));
+            }
+            // Same page, however it was written; the collection's own settings apply on save
+            if !seen.insert(canonical_url(&url, &defaults)) {
+                parsed.diagnostics.push(diagnostic(
+                    line_number,
+                    ParseDiagnosticKind::DuplicateUrl,
+                    format!("'{}' was already listed", url),
+                ));
+                continue;
+            }
+
+            let mut title = clean_title(&candidate.title);
+            if title.is_empty() {
+                parsed.diagnostics.push(diagnostic(
+                    line_number,
+                    ParseDiagnosticKind::MissingTitle,
+                    format!("No title for '{}', using the URL", url),
+                ));
+                title = url.clone();
+            }
+            parsed.sites.push(SiteEntry {
+                title,
+                url,
+                canonical_url: None,
+            });
+        }
+    }
+
+    debug!(
+        "Parsed {} sites with {} diagnostics",
+        parsed.sites.len(),
+        parsed.diagnostics.len()
+    );
+    parsed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sites(text: &str) -> Vec<(String, String)> {
+        parse_sites_text(text)
+            .sites
+            .into_iter()
+            .map(|site| (site.title, site.url))
+            .collect()
+    }
+
+    fn pair(title: &str, url: &str) -> (String, String) {
+        (title.to_string(), url.to_string())
+    }
+
+    #[test]
+    fn test_parse_line_formats() {
+        assert_eq!(
+            sites("Google https://google.com\nRust\thttps://rust-lang.org/learn\n\n"),
+            vec![pair("Google", "https://google.com"), pair("Rust", "https://rust-lang.org/learn")]
+        );
+        assert_eq!(
+            sites("- [The Book](https://doc.rust-lang.org/book/ \"tooltip\"), see also [Std](https://doc.rust-lang.org/std/)"),
+            vec![
+                pair("The Book", "https://doc.rust-lang.org/book/"),
+                pair("Std", "https://doc.rust-lang.org/std/"),
+            ]
+        );
+        assert_eq!(
+            sites("Search https://duckduckgo.com Mail https://mail.example.com/inbox?q=a"),
+            vec![
+                pair("Search", "https://duckduckgo.com"),
+                pair("Mail", "https://mail.example.com/inbox?q=a"),
+            ]
+        );
+        assert_eq!(
+            sites("https://example.com/report\tQuarterly report"),
+            vec![pair("Quarterly report", "https://example.com/report")]
+        );
+        assert_eq!(
+            sites("Node.js docs nodejs.org/en/docs"),
+            vec![pair("Node.js docs", "https://nodejs.org/en/docs")]
+        );
+    }
+
+    #[test]
+    fn test_parse_repairs_and_diagnostics() {
+        let parsed = parse_sites_text(
+            "See https://example.com/a.\n\
+             Wiki: https://en.wikipedia.org/wiki/Rust_(programming_language))\n\
+             just some notes\n\
+             Again https://example.com/a\n\
+             https://example.com/b",
+        );
+
+        let urls: Vec<&str> = parsed.sites.iter().map(|site| site.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://example.com/a",
+                "https://en.wikipedia.org/wiki/Rust_(programming_language)",
+                "https://example.com/b",
+            ]
+        );
+        assert_eq!(parsed.sites[1].title, "Wiki");
+        assert_eq!(parsed.sites[2].title, "https://example.com/b");
+
+        let kinds: Vec<(usize, ParseDiagnosticKind)> =
+            parsed.diagnostics.iter().map(|d| (d.line, d.kind)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (1, ParseDiagnosticKind::RepairedUrl),
+                (2, ParseDiagnosticKind::RepairedUrl),
+                (3, ParseDiagnosticKind::SkippedLine),
+                (4, ParseDiagnosticKind::DuplicateUrl),
+                (5, ParseDiagnosticKind::MissingTitle),
+            ]
+        );
+    }
+}
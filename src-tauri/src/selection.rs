@@ -0,0 +1,245 @@
+use crate::error::AppError;
+use crate::models::{
+    BrowserProfile, Database, ImportPreview, ImportPreviewCollection, ImportPreviewProfile, ImportSelection,
+    ProfileImportStatus,
+};
+use std::collections::{HashMap, HashSet};
+use tracing::debug;
+
+/// Profiles are the same when they would open the same browser the same way
+fn same_profile(a: &BrowserProfile, b: &BrowserProfile) -> bool {
+    a.name == b.name && a.browser == b.browser && a.mode == b.mode && a.custom_path == b.custom_path
+}
+
+fn profile_status(profile: &BrowserProfile, existing: &[BrowserProfile]) -> ProfileImportStatus {
+    if existing.iter().any(|p| same_profile(p, profile)) {
+        ProfileImportStatus::Existing
+    } else if existing.iter().any(|p| p.id == profile.id) {
+        ProfileImportStatus::IdConflict
+    } else {
+        ProfileImportStatus::New
+    }
+}
+
+/// The given collections and the profiles they reference, as an export file.
+/// Trash and revisions stay behind.
+pub fn select_for_export(database: Database, ids: &[u64]) -> Result<Database, Box<dyn std::error::Error>> {
+    let mut records: HashMap<u64, _> = database.data.into_iter().map(|record| (record.id, record)).collect();
+    let data = ids
+        .iter()
+        .map(|id| {
+            records
+                .remove(id)
+                .ok_or_else(|| AppError::not_found(format!("Collection with id {} not found", id)).into())
+        })
+        .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?;
+
+    let referenced: HashSet<&str> = data
+        .iter()
+        .filter_map(|record| record.config.browser_profile_id.as_deref())
+        .collect();
+    let profiles = database
+        .profiles
+        .into_iter()
+        .filter(|profile| referenced.contains(profile.id.as_str()))
+        .collect();
+
+    let mut meta = database.meta;
+    meta.record_count = data.len();
+    Ok(Database {
+        meta,
+        profiles,
+        data,
+        trash: Vec::new(),
+        revisions: Vec::new(),
+    })
+}
+
+/// What importing `incoming` into `existing` would bring in
+pub fn preview_import(incoming: &Database, existing: &Database) -> ImportPreview {
+    let collections = incoming
+        .data
+        .iter()
+        .map(|record| ImportPreviewCollection {
+            id: record.id,
+            name: record.name.clone(),
+            site_count: record.sites.len(),
+            browser_profile_id: record.config.browser_profile_id.clone(),
+            name_exists: existing
+                .data
+                .iter()
+                .any(|r| r.name.to_lowercase() == record.name.to_lowercase()),
+        })
+        .collect();
+    let profiles = incoming
+        .profiles
+        .iter()
+        .map(|profile| ImportPreviewProfile {
+            profile: profile.clone(),
+            status: profile_status(profile, &existing.profiles),
+        })
+        .collect();
+
+    ImportPreview { collections, profiles }
+}
+
+/// Everything in `incoming`, as a plain merging import takes it
+pub fn select_all(incoming: &Database) -> ImportSelection {
+    ImportSelection {
+        collection_ids: incoming.data.iter().map(|record| record.id).collect(),
+        profile_ids: incoming.profiles.iter().map(|profile| profile.id.clone()).collect(),
+    }
+}
+
+/// Narrow `incoming` to the selection, ready for `merge_databases`.
+///
+/// Selected profiles identical to one already here are not imported again;
+/// ones whose ID is taken get a new ID. Collection references follow the
+/// profile they pointed to, and are cleared when it isn't imported and has no
+/// identical profile here.
+pub fn select_for_import(incoming: Database, selection: &ImportSelection, existing: &[BrowserProfile]) -> Database {
+    let mut taken: HashSet<String> = existing.iter().map(|profile| profile.id.clone()).collect();
+    let mut profile_ids: HashMap<String, Option<String>> = HashMap::new();
+    let mut profiles = Vec::new();
+
+    for mut profile in incoming.profiles {
+        if let Some(local) = existing.iter().find(|p| same_profile(p, &profile)) {
+            profile_ids.insert(profile.id, Some(local.id.clone()));
+            continue;
+        }
+        if !selection.profile_ids.contains(&profile.id) {
+            profile_ids.insert(profile.id, None);
+            continue;
+        }
+
+        let mut id = profile.id.clone();
+        let mut suffix = 1;
+        while taken.contains(&id) {
+            id = match suffix {
+                1 => format!("{}-imported", profile.id),
+                n => format!("{}-imported-{}", profile.id, n),
+            };
+            suffix += 1;
+        }
+        if id != profile.id {
+            debug!("Importing profile '{}' as '{}'", profile.id, id);
+        }
+        taken.insert(id.clone());
+        profile_ids.insert(std::mem::replace(&mut profile.id, id.clone()), Some(id));
+        profiles.push(profile);
+    }
+
+    let data = incoming
+        .data
+        .into_iter()
+        .filter(|record| selection.collection_ids.contains(&record.id))
+        .map(|mut record| {
+            if let Some(id) = record.config.browser_profile_id.take() {
+                // References to profiles missing from the file are kept if they exist here
+                record.config.browser_profile_id = match profile_ids.get(&id) {
+                    Some(mapped) => mapped.clone(),
+                    None => existing.iter().any(|p| p.id == id).then_some(id),
+                };
+            }
+            record
+        })
+        .collect();
+
+    Database {
+        profiles,
+        data,
+        ..incoming
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Browser, BrowserMode, CollectionConfig, CollectionRecord};
+    use crate::test_support::sample_record;
+    use chrono::Utc;
+
+    fn profile(id: &str, name: &str, browser: Browser) -> BrowserProfile {
+        BrowserProfile {
+            id: id.to_string(),
+            name: name.to_string(),
+            browser,
+            mode: BrowserMode::Normal,
+            custom_path: None,
+            is_default: false,
+            is_detected: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn record(id: u64, name: &str, profile_id: Option<&str>) -> CollectionRecord {
+        CollectionRecord {
+            id,
+            config: CollectionConfig {
+                browser_profile_id: profile_id.map(str::to_string),
+                ..CollectionConfig::default()
+            },
+            ..sample_record(name, &[])
+        }
+    }
+
+    #[test]
+    fn test_selective_export_and_import() {
+        let source = Database {
+            profiles: vec![
+                profile("work", "Work", Browser::Firefox),
+                profile("home", "Home", Browser::Chrome),
+                profile("spare", "Spare", Browser::Edge),
+            ],
+            data: vec![
+                record(1, "Research", Some("work")),
+                record(2, "Reading", Some("home")),
+                record(3, "Shopping", Some("spare")),
+                record(4, "News", None),
+            ],
+            ..Database::default()
+        };
+
+        let export = select_for_export(source.clone(), &[2, 1]).unwrap();
+        let names: Vec<&str> = export.data.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["Reading", "Research"]);
+        let ids: Vec<&str> = export.profiles.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, vec!["work", "home"]);
+        assert_eq!(export.meta.record_count, 2);
+        assert!(select_for_export(source.clone(), &[9]).is_err());
+
+        // "work" is taken by another profile here; "home" is already here under another ID
+        let local = Database {
+            profiles: vec![
+                profile("work", "Office", Browser::Chrome),
+                profile("work-imported", "Laptop", Browser::Safari),
+                profile("personal", "Home", Browser::Chrome),
+            ],
+            data: vec![record(1, "news", None)],
+            ..Database::default()
+        };
+        let preview = preview_import(&source, &local);
+        let statuses: Vec<ProfileImportStatus> = preview.profiles.iter().map(|p| p.status).collect();
+        assert_eq!(
+            statuses,
+            vec![ProfileImportStatus::IdConflict, ProfileImportStatus::Existing, ProfileImportStatus::New]
+        );
+        let clashes: Vec<bool> = preview.collections.iter().map(|c| c.name_exists).collect();
+        assert_eq!(clashes, vec![false, false, false, true]);
+
+        let selection = ImportSelection {
+            collection_ids: vec![1, 2, 3],
+            profile_ids: vec!["work".to_string(), "home".to_string()],
+        };
+        let selected = select_for_import(source, &selection, &local.profiles);
+        let ids: Vec<&str> = selected.profiles.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, vec!["work-imported-2"]);
+        let references: Vec<Option<&str>> = selected
+            .data
+            .iter()
+            .map(|r| r.config.browser_profile_id.as_deref())
+            .collect();
+        assert_eq!(references, vec![Some("work-imported-2"), Some("personal"), None]);
+    }
+}
@@ -1,20 +1,39 @@
 #![allow(dead_code)]
-use crate::database::JsonStore;
+use crate::backups;
+use crate::bookmarks::{self, BookmarksHtml};
+use crate::browser_data;
+use crate::chromium_session;
+use crate::crypto::{self, EncryptionKey};
+use crate::database::{free_name, merge_databases, JsonStore, Store};
+use crate::duplicates;
+use crate::error::{AppError, ErrorKind};
+use crate::exporters;
+use crate::firefox_session;
+use crate::importers::{self, Importer, ParsedImport};
+use crate::integrity;
+use crate::journal::{Journal, Operation};
+use crate::normalize::canonicalize_sites;
+use crate::selection;
 use crate::models::{
-    Browser, BrowserMode, BrowserProfile, CollectionConfig, CollectionData, CollectionRecord,
-    SiteEntry,
+    BackupInfo, BackupReason, Browser, BrowserDataFilter, BrowserDataProfile, BrowserDataSource, BrowserMode, BrowserProfile, CollectionConfig,
+    CollectionData, CollectionRecord, CollectionRevision, Database, DedupeResult, DedupeStrategy,
+    DuplicateReport, EncryptionStatus, ExportFormat, ImportFormat, ImportPreview, ImportReport, ImportSelection, MergeReport, MergeStrategy, BrowserSession, SessionFile, IntegrityIssue, RepairReport,
+    RetitledSite, RevisionDiff, SiteEntry, StorageBackend, TrashedCollection,
 };
-use chrono::Utc;
+use chrono::{Duration, Local, Utc};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tracing::{info, instrument, warn};
 
 pub struct CollectionService {
-    db: JsonStore,
+    db: Arc<dyn Store>,
+    journal: Arc<Journal>,
 }
 
 impl CollectionService {
-    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        let db = JsonStore::new()?;
-        Ok(Self { db })
+    pub fn new(db: Arc<dyn Store>, journal: Arc<Journal>) -> Self {
+        Self { db, journal }
     }
 
     #[instrument(skip(self, collection_data))]
@@ -22,7 +41,7 @@ impl CollectionService {
         &self,
         collection_data: CollectionData,
     ) -> Result<CollectionRecord, Box<dyn std::error::Error>> {
-        let record = CollectionRecord {
+        let mut record = CollectionRecord {
             id: 0, // Will be auto-assigned by database
             name: collection_data
                 .name
@@ -32,9 +51,13 @@ impl CollectionService {
             created_at: collection_data.created_at,
             updated_at: Utc::now(),
         };
+        canonicalize_sites(&mut record.sites, &record.config);
 
         let saved_record = self.db.insert(record)?;
         info!("Collection saved with ID: {}", saved_record.id);
+        self.journal.record(Operation::SaveCollection {
+            record: Box::new(saved_record.clone()),
+        });
         Ok(saved_record)
     }
 
@@ -70,46 +93,823 @@ impl CollectionService {
         // Get existing record first
         let existing = self
             .get_collection(id)?
-            .ok_or_else(|| format!("Collection with id {} not found", id))?;
+            .ok_or_else(|| AppError::not_found(format!("Collection with id {} not found", id)))?;
 
         let updated_record = CollectionRecord {
             id,
-            name: collection_data.name.unwrap_or(existing.name),
+            name: collection_data.name.unwrap_or(existing.name.clone()),
             sites: collection_data.sites,
             config: collection_data.config,
             created_at: existing.created_at, // Preserve original creation time
             updated_at: Utc::now(),
         };
 
-        self.db.update(updated_record)
+        self.replace_collection(existing, updated_record)
+    }
+
+    /// Store `updated` over `existing` and journal the change.
+    ///
+    /// Canonical URLs are recomputed, the normalization settings may have changed.
+    fn replace_collection(
+        &self,
+        existing: CollectionRecord,
+        mut updated: CollectionRecord,
+    ) -> Result<CollectionRecord, Box<dyn std::error::Error>> {
+        canonicalize_sites(&mut updated.sites, &updated.config);
+        let record = self.db.update(updated)?;
+        self.journal.record(Operation::UpdateCollection {
+            before: Box::new(existing),
+            after: Box::new(record.clone()),
+        });
+        Ok(record)
     }
 
     #[instrument(skip(self))]
     pub fn delete_collection(&self, id: u64) -> Result<bool, Box<dyn std::error::Error>> {
-        self.db.delete_by_id(id)
+        let Some(existing) = self.get_collection(id)? else {
+            return self.db.delete_by_id(id);
+        };
+
+        let deleted = self.db.delete_by_id(id)?;
+        if deleted {
+            self.journal.record(Operation::DeleteCollection {
+                id,
+                name: existing.name,
+            });
+        }
+        Ok(deleted)
+    }
+
+    #[instrument(skip(self))]
+    pub fn list_trash(&self) -> Result<Vec<TrashedCollection>, Box<dyn std::error::Error>> {
+        self.purge_expired_trash()?;
+        let mut trash = self.db.list_trash()?;
+        // Most recently deleted first
+        trash.sort_by_key(|trashed| std::cmp::Reverse(trashed.deleted_at));
+        Ok(trash)
+    }
+
+    #[instrument(skip(self))]
+    pub fn restore_from_trash(
+        &self,
+        id: u64,
+    ) -> Result<CollectionRecord, Box<dyn std::error::Error>> {
+        self.db
+            .restore_from_trash(id)?
+            .ok_or_else(|| AppError::not_found(format!("Collection with id {} not found in trash", id)).into())
+    }
+
+    #[instrument(skip(self))]
+    pub fn empty_trash(&self) -> Result<usize, Box<dyn std::error::Error>> {
+        self.db.empty_trash()
+    }
+
+    /// Permanently delete trashed collections older than the retention period.
+    ///
+    /// A retention of 0 days keeps trashed collections until the trash is emptied.
+    #[instrument(skip(self))]
+    pub fn purge_expired_trash(&self) -> Result<usize, Box<dyn std::error::Error>> {
+        let retention_days = self.db.get_trash_retention_days()?;
+        if retention_days == 0 {
+            return Ok(0);
+        }
+
+        let cutoff = Utc::now() - Duration::days(i64::from(retention_days));
+        self.db.purge_trash_before(cutoff)
+    }
+
+    #[instrument(skip(self))]
+    pub fn get_trash_retention_days(&self) -> Result<u32, Box<dyn std::error::Error>> {
+        self.db.get_trash_retention_days()
+    }
+
+    #[instrument(skip(self))]
+    pub fn set_trash_retention_days(&self, days: u32) -> Result<(), Box<dyn std::error::Error>> {
+        self.db.set_trash_retention_days(days)?;
+        self.purge_expired_trash()?;
+        Ok(())
     }
 
     #[instrument(skip(self))]
-    pub fn export_database(&self) -> Result<String, Box<dyn std::error::Error>> {
-        info!("Exporting database to JSON");
-        self.db.export_to_json()
+    pub fn list_revisions(
+        &self,
+        collection_id: u64,
+    ) -> Result<Vec<CollectionRevision>, Box<dyn std::error::Error>> {
+        self.db.list_revisions(collection_id)
+    }
+
+    fn revision(
+        &self,
+        collection_id: u64,
+        revision: u32,
+    ) -> Result<CollectionRevision, Box<dyn std::error::Error>> {
+        self.db.get_revision(collection_id, revision)?.ok_or_else(|| {
+            AppError::not_found(format!("Revision {} of collection {} not found", revision, collection_id)).into()
+        })
+    }
+
+    /// Compare two revisions, or a revision with the current collection when `to_revision` is `None`
+    #[instrument(skip(self))]
+    pub fn diff_revisions(
+        &self,
+        collection_id: u64,
+        from_revision: u32,
+        to_revision: Option<u32>,
+    ) -> Result<RevisionDiff, Box<dyn std::error::Error>> {
+        let from = self.revision(collection_id, from_revision)?;
+        let to_sites = match to_revision {
+            Some(revision) => self.revision(collection_id, revision)?.sites,
+            None => {
+                self.get_collection(collection_id)?
+                    .ok_or_else(|| AppError::not_found(format!("Collection with id {} not found", collection_id)))?
+                    .sites
+            }
+        };
+
+        let (added, removed, retitled) = diff_sites(&from.sites, &to_sites);
+        Ok(RevisionDiff {
+            collection_id,
+            from_revision,
+            to_revision,
+            added,
+            removed,
+            retitled,
+        })
+    }
+
+    /// Restore a collection's name, sites and config from a revision.
+    ///
+    /// The version being replaced becomes a new revision, so a rollback can itself be undone.
+    #[instrument(skip(self))]
+    pub fn rollback_to_revision(
+        &self,
+        collection_id: u64,
+        revision: u32,
+    ) -> Result<CollectionRecord, Box<dyn std::error::Error>> {
+        let target = self.revision(collection_id, revision)?;
+        let existing = self
+            .get_collection(collection_id)?
+            .ok_or_else(|| AppError::not_found(format!("Collection with id {} not found", collection_id)))?;
+
+        let created_at = existing.created_at;
+        let record = self.replace_collection(
+            existing,
+            CollectionRecord {
+                id: collection_id,
+                name: target.name,
+                sites: target.sites,
+                config: target.config,
+                created_at,
+                updated_at: Utc::now(),
+            },
+        )?;
+        info!("Rolled back collection {} to revision {}", collection_id, revision);
+        Ok(record)
+    }
+
+    /// Sites saved more than once, within a collection or across collections
+    #[instrument(skip(self))]
+    pub fn find_duplicates(&self) -> Result<DuplicateReport, Box<dyn std::error::Error>> {
+        Ok(duplicates::find_duplicates(&self.db.get_all()?))
+    }
+
+    /// Remove repeated canonical URLs from a collection.
+    ///
+    /// Goes through `replace_collection`, so the change is journaled and the
+    /// previous sites are kept as a revision.
+    #[instrument(skip(self))]
+    pub fn dedupe_collection(
+        &self,
+        id: u64,
+        strategy: DedupeStrategy,
+    ) -> Result<DedupeResult, Box<dyn std::error::Error>> {
+        let existing = self
+            .get_collection(id)?
+            .ok_or_else(|| AppError::not_found(format!("Collection with id {} not found", id)))?;
+
+        let mut updated = existing.clone();
+        let removed = duplicates::dedupe_sites(&mut updated, strategy);
+        if removed.is_empty() {
+            return Ok(DedupeResult {
+                collection: existing,
+                removed,
+            });
+        }
+
+        updated.updated_at = Utc::now();
+        let collection = self.replace_collection(existing, updated)?;
+        info!("Removed {} duplicate sites from collection {}", removed.len(), id);
+        Ok(DedupeResult { collection, removed })
     }
 
-    #[instrument(skip(self, json_data), fields(data_length = json_data.len()))]
-    pub fn import_database(&self, json_data: String, replace_existing: bool) -> Result<usize, Box<dyn std::error::Error>> {
+    /// Export as JSON, encrypted with `passphrase` when one is given. With
+    /// `ids`, only those collections and the profiles they use are exported.
+    #[instrument(skip(self, passphrase))]
+    pub fn export_database(
+        &self,
+        passphrase: Option<&str>,
+        ids: Option<&[u64]>,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        info!("Exporting database to JSON, encrypted: {}", passphrase.is_some());
+        let json_data = match ids {
+            Some(ids) => {
+                let (_, database) = self.snapshot()?;
+                serde_json::to_string_pretty(&selection::select_for_export(database, ids)?)?
+            }
+            None => self.db.export_to_json()?,
+        };
+        match passphrase {
+            Some(passphrase) => EncryptionKey::generate(passphrase)?.seal(&json_data),
+            None => Ok(json_data),
+        }
+    }
+
+    /// Import an export, which must come with its `passphrase` if it was encrypted
+    #[instrument(skip(self, json_data, passphrase), fields(data_length = json_data.len()))]
+    pub fn import_database(
+        &self,
+        json_data: String,
+        replace_existing: bool,
+        passphrase: Option<&str>,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
         info!("Importing database from JSON, replace_existing: {}", replace_existing);
-        self.db.import_from_json(json_data, replace_existing)
+        if !replace_existing {
+            let report = self.merge_database(json_data, passphrase, MergeStrategy::Skip, false)?;
+            return Ok(report.created_count());
+        }
+        let json_data = crypto::open_export(json_data, passphrase)?;
+        backups::create_backup(self.db.as_ref(), BackupReason::BeforeImport)?;
+
+        let before = self.db.export_to_json()?;
+        let imported = self.db.import_from_json(json_data, true)?;
+
+        self.journal.record(Operation::ImportDatabase {
+            before,
+            after: self.db.export_to_json()?,
+            replace_existing: true,
+        });
+        Ok(imported)
+    }
+
+    /// Merge an export, handling name clashes as `strategy` says. The file's
+    /// profiles come along, renamed where their IDs clash. A dry run reports
+    /// what would happen without writing anything.
+    #[instrument(skip(self, json_data, passphrase), fields(data_length = json_data.len()))]
+    pub fn merge_database(
+        &self,
+        json_data: String,
+        passphrase: Option<&str>,
+        strategy: MergeStrategy,
+        dry_run: bool,
+    ) -> Result<MergeReport, Box<dyn std::error::Error>> {
+        let (incoming, _) = JsonStore::parse_database(&crypto::open_export(json_data, passphrase)?)?;
+        let everything = selection::select_all(&incoming);
+        let incoming = selection::select_for_import(incoming, &everything, &self.db.get_all_profiles()?);
+
+        if dry_run {
+            let (_, mut existing) = self.snapshot()?;
+            let mut report = merge_databases(&mut existing, incoming, strategy);
+            report.dry_run = true;
+            return Ok(report);
+        }
+
+        let before = self.db.export_to_json()?;
+        let report = self.db.merge_from_json(serde_json::to_string(&incoming)?, strategy)?;
+
+        self.journal.record(Operation::ImportDatabase {
+            before,
+            after: self.db.export_to_json()?,
+            replace_existing: false,
+        });
+        Ok(report)
+    }
+
+    /// List what an export file holds, for picking what to import
+    #[instrument(skip(self, json_data, passphrase), fields(data_length = json_data.len()))]
+    pub fn preview_import(
+        &self,
+        json_data: String,
+        passphrase: Option<&str>,
+    ) -> Result<ImportPreview, Box<dyn std::error::Error>> {
+        let (incoming, _) = JsonStore::parse_database(&crypto::open_export(json_data, passphrase)?)?;
+        let (_, existing) = self.snapshot()?;
+        Ok(selection::preview_import(&incoming, &existing))
+    }
+
+    /// Merge the collections and profiles picked from `preview_import`
+    #[instrument(skip(self, json_data, passphrase), fields(data_length = json_data.len()))]
+    pub fn import_selected(
+        &self,
+        json_data: String,
+        passphrase: Option<&str>,
+        picked: &ImportSelection,
+        strategy: MergeStrategy,
+    ) -> Result<ImportReport, Box<dyn std::error::Error>> {
+        let (incoming, _) = JsonStore::parse_database(&crypto::open_export(json_data, passphrase)?)?;
+        let incoming = selection::select_for_import(incoming, picked, &self.db.get_all_profiles()?);
+        self.merge_import(incoming, 0, strategy)
+    }
+
+    /// Import a Netscape `bookmarks.html`, one collection per folder
+    #[instrument(skip(self, html), fields(html_length = html.len()))]
+    pub fn import_bookmarks_html(
+        &self,
+        html: &str,
+        flatten: bool,
+        strategy: MergeStrategy,
+    ) -> Result<ImportReport, Box<dyn std::error::Error>> {
+        let mut parsed = BookmarksHtml { flatten }.parse(html)?;
+
+        // Exports from another install may name profiles that don't exist here;
+        // their browser and mode were copied into the fallback fields on export
+        let profiles = self.db.get_all_profiles()?;
+        let mut dropped_paths = Vec::new();
+        for record in &mut parsed.collections {
+            let known = record
+                .config
+                .browser_profile_id
+                .as_ref()
+                .is_none_or(|id| profiles.iter().any(|profile| &profile.id == id));
+            if !known {
+                record.config.browser_profile_id = None;
+            }
+
+            // The config comes from a file anyone could have written, and these
+            // paths get launched; only keep ones a profile here already uses
+            let local_path = |path: &str| {
+                profiles.iter().any(|profile| {
+                    profile.custom_path.as_deref() == Some(path)
+                        || profile.browser == Browser::Custom(path.to_string())
+                })
+            };
+            let mut dropped = false;
+            if record.config.custom_path.as_deref().is_some_and(|path| !local_path(path)) {
+                record.config.custom_path = None;
+                dropped = true;
+            }
+            if let Some(Browser::Custom(path)) = &record.config.browser {
+                if !local_path(path) {
+                    record.config.browser = None;
+                    dropped = true;
+                }
+            }
+            if dropped {
+                warn!("Dropped the unknown browser path from the config of '{}'", record.name);
+                dropped_paths.push(record.name.clone());
+            }
+        }
+
+        let mut report = self.import_collections(parsed.collections, parsed.skipped_sites, strategy)?;
+        report.dropped_paths = dropped_paths;
+        Ok(report)
+    }
+
+    /// Export the given collections as a Netscape `bookmarks.html`
+    #[instrument(skip(self))]
+    pub fn export_bookmarks_html(&self, ids: &[u64]) -> Result<String, Box<dyn std::error::Error>> {
+        let records = self.collections_by_id(ids)?;
+        Ok(bookmarks::export_bookmarks_html(&records, &self.db.get_all_profiles()?))
+    }
+
+    /// Export the given collections, or all of them, as Markdown, CSV, OPML or a URL list
+    #[instrument(skip(self))]
+    pub fn export_collections(
+        &self,
+        ids: Option<&[u64]>,
+        format: ExportFormat,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let records = match ids {
+            Some(ids) => self.collections_by_id(ids)?,
+            None => self.db.get_all()?,
+        };
+        Ok(exporters::exporter(format).export(&records))
+    }
+
+    /// Collections in the order given, failing on the first missing ID
+    fn collections_by_id(&self, ids: &[u64]) -> Result<Vec<CollectionRecord>, Box<dyn std::error::Error>> {
+        ids.iter()
+            .map(|id| {
+                self.get_collection(*id)?
+                    .ok_or_else(|| AppError::not_found(format!("Collection with id {} not found", id)).into())
+            })
+            .collect()
+    }
+
+    /// Import an export from another tab manager, see `importers::importer`
+    #[instrument(skip(self, contents), fields(contents_length = contents.len()))]
+    pub fn import_from_format(
+        &self,
+        format: ImportFormat,
+        contents: &str,
+        strategy: MergeStrategy,
+    ) -> Result<ImportReport, Box<dyn std::error::Error>> {
+        let importer = importers::importer(format);
+        info!("Importing {} export", importer.name());
+        let mut parsed = importer.parse(contents)?;
+        self.number_unnamed(&mut parsed)?;
+        self.import_collections(parsed.collections, parsed.skipped_sites, strategy)
+    }
+
+    /// Browser profiles with bookmarks or history to import, from the same
+    /// directories as session import
+    #[instrument(skip(self))]
+    pub fn list_browser_data_profiles(&self) -> Vec<BrowserDataProfile> {
+        let firefox = ProfileService::firefox_profile_dirs()
+            .into_iter()
+            .map(|dir| (Browser::Firefox, dir));
+        let chromium = [Browser::Chrome, Browser::Edge].into_iter().flat_map(|browser| {
+            ProfileService::chromium_profile_dirs(&browser)
+                .into_iter()
+                .map(move |dir| (browser.clone(), dir))
+        });
+
+        firefox
+            .chain(chromium)
+            .filter_map(|(browser, dir)| {
+                let sources = browser_data::available_sources(&browser, &dir);
+                (!sources.is_empty()).then(|| BrowserDataProfile {
+                    profile: dir.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default(),
+                    path: dir.to_string_lossy().to_string(),
+                    browser,
+                    sources,
+                })
+            })
+            .collect()
+    }
+
+    /// Build collections from a profile's bookmarks or history
+    #[instrument(skip(self, filter))]
+    pub fn import_browser_data(
+        &self,
+        browser: Browser,
+        profile_path: &str,
+        source: BrowserDataSource,
+        filter: &BrowserDataFilter,
+        strategy: MergeStrategy,
+    ) -> Result<ImportReport, Box<dyn std::error::Error>> {
+        let mut parsed = browser_data::read_browser_data(&browser, Path::new(profile_path), source, filter)?;
+        self.number_unnamed(&mut parsed)?;
+        self.import_collections(parsed.collections, parsed.skipped_sites, strategy)
+    }
+
+    /// Firefox session files from the detected profiles, newest first
+    #[instrument(skip(self))]
+    pub fn list_firefox_sessions(&self) -> Vec<SessionFile> {
+        firefox_session::locate_session_files(&ProfileService::firefox_profile_dirs())
+    }
+
+    #[instrument(skip(self))]
+    pub fn read_firefox_session(&self, path: &str, skip_pinned: bool) -> Result<BrowserSession, Box<dyn std::error::Error>> {
+        firefox_session::read_session(Path::new(path), skip_pinned)
+    }
+
+    /// Import a Firefox session, one collection per window. `windows` picks
+    /// windows by their index in `read_firefox_session`; `None` imports all.
+    #[instrument(skip(self))]
+    pub fn import_firefox_session(
+        &self,
+        path: &str,
+        skip_pinned: bool,
+        windows: Option<Vec<usize>>,
+        strategy: MergeStrategy,
+    ) -> Result<ImportReport, Box<dyn std::error::Error>> {
+        let session = firefox_session::read_session(Path::new(path), skip_pinned)?;
+        self.import_session(session, windows, strategy)
+    }
+
+    /// Session files of the Chromium-based browsers with detectable profiles, newest first
+    #[instrument(skip(self))]
+    pub fn list_chromium_sessions(&self) -> Vec<SessionFile> {
+        let mut found: Vec<SessionFile> = [Browser::Chrome, Browser::Edge]
+            .iter()
+            .flat_map(|browser| {
+                chromium_session::locate_session_files(browser, &ProfileService::chromium_profile_dirs(browser))
+            })
+            .collect();
+        found.sort_by_key(|file| std::cmp::Reverse(file.modified_at));
+        found
+    }
+
+    #[instrument(skip(self))]
+    pub fn read_chromium_session(
+        &self,
+        path: &str,
+        browser: Browser,
+        skip_pinned: bool,
+    ) -> Result<BrowserSession, Box<dyn std::error::Error>> {
+        chromium_session::read_session(Path::new(path), browser, skip_pinned)
+    }
+
+    /// Import a Chromium session, one collection per window, as with `import_firefox_session`
+    #[instrument(skip(self))]
+    pub fn import_chromium_session(
+        &self,
+        path: &str,
+        browser: Browser,
+        skip_pinned: bool,
+        windows: Option<Vec<usize>>,
+        strategy: MergeStrategy,
+    ) -> Result<ImportReport, Box<dyn std::error::Error>> {
+        let session = chromium_session::read_session(Path::new(path), browser, skip_pinned)?;
+        self.import_session(session, windows, strategy)
+    }
+
+    /// Turn the chosen windows of a session into collections set to open in
+    /// the browser they came from
+    fn import_session(
+        &self,
+        session: BrowserSession,
+        windows: Option<Vec<usize>>,
+        strategy: MergeStrategy,
+    ) -> Result<ImportReport, Box<dyn std::error::Error>> {
+        let saved_at = session.saved_at.with_timezone(&Local).format("%Y-%m-%d %H:%M");
+        let browser_name = match &session.browser {
+            Browser::Custom(name) => name.clone(),
+            browser => format!("{:?}", browser),
+        };
+
+        let records = session
+            .windows
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| windows.as_ref().is_none_or(|chosen| chosen.contains(index)))
+            .map(|(index, window)| CollectionRecord {
+                id: 0, // Assigned on import
+                name: format!("{} window {} ({})", browser_name, index + 1, saved_at),
+                sites: window.tabs,
+                config: CollectionConfig {
+                    browser: Some(session.browser.clone()),
+                    ..CollectionConfig::default()
+                },
+                created_at: session.saved_at,
+                updated_at: session.saved_at,
+            })
+            .collect();
+
+        self.import_collections(records, session.skipped_tabs, strategy)
+    }
+
+    /// Give made-up names that are already taken the next free number. They
+    /// repeat across exports ("OneTab group 1" every time), so left as they
+    /// are a second import would clash with the first instead of adding to it.
+    fn number_unnamed(&self, parsed: &mut ParsedImport) -> Result<(), Box<dyn std::error::Error>> {
+        let mut taken: HashSet<String> = self
+            .db
+            .get_all()?
+            .iter()
+            .map(|record| record.name.to_lowercase())
+            .collect();
+        for record in &mut parsed.collections {
+            if parsed.unnamed.contains(&record.name) {
+                record.name = free_name(&record.name, |name| taken.contains(&name.to_lowercase()));
+            }
+            taken.insert(record.name.to_lowercase());
+        }
+        Ok(())
+    }
+
+    /// Merge collections converted from another format, with the same name
+    /// clash handling and undo entry as `merge_database`
+    fn import_collections(
+        &self,
+        mut records: Vec<CollectionRecord>,
+        skipped_sites: usize,
+        strategy: MergeStrategy,
+    ) -> Result<ImportReport, Box<dyn std::error::Error>> {
+        for record in &mut records {
+            canonicalize_sites(&mut record.sites, &record.config);
+        }
+        let incoming = Database {
+            data: records,
+            ..Database::default()
+        };
+        self.merge_import(incoming, skipped_sites, strategy)
+    }
+
+    /// Merge `incoming` as one undoable import and report what it did
+    fn merge_import(
+        &self,
+        incoming: Database,
+        skipped_sites: usize,
+        strategy: MergeStrategy,
+    ) -> Result<ImportReport, Box<dyn std::error::Error>> {
+        let known_ids: HashSet<u64> = self.db.get_all()?.iter().map(|record| record.id).collect();
+        let known_profiles: HashSet<String> = self.db.get_all_profiles()?.into_iter().map(|profile| profile.id).collect();
+
+        let before = self.db.export_to_json()?;
+        let report = self.db.merge_from_json(serde_json::to_string(&incoming)?, strategy)?;
+
+        let created: Vec<CollectionRecord> = self
+            .db
+            .get_all()?
+            .into_iter()
+            .filter(|record| !known_ids.contains(&record.id))
+            .collect();
+        let profiles: Vec<BrowserProfile> = self
+            .db
+            .get_all_profiles()?
+            .into_iter()
+            .filter(|profile| !known_profiles.contains(&profile.id))
+            .collect();
+
+        let changed = !created.is_empty()
+            || !profiles.is_empty()
+            || !report.overwritten.is_empty()
+            || report.merged.iter().any(|merged| merged.added_sites > 0);
+        if changed {
+            self.journal.record(Operation::ImportDatabase {
+                before,
+                after: self.db.export_to_json()?,
+                replace_existing: false,
+            });
+        }
+        info!(
+            "Imported {} collections, skipped {} existing names and {} sites",
+            created.len(),
+            report.skipped.len(),
+            skipped_sites
+        );
+
+        Ok(ImportReport {
+            created,
+            skipped_collections: report.skipped,
+            skipped_sites,
+            profiles,
+            renamed: report.renamed,
+            overwritten: report.overwritten,
+            merged: report.merged,
+            dropped_paths: Vec::new(),
+        })
+    }
+
+    #[instrument(skip(self))]
+    pub fn create_backup(&self, reason: BackupReason) -> Result<BackupInfo, Box<dyn std::error::Error>> {
+        backups::create_backup(self.db.as_ref(), reason)
+    }
+
+    #[instrument(skip(self))]
+    pub fn list_backups(&self) -> Result<Vec<BackupInfo>, Box<dyn std::error::Error>> {
+        backups::list_backups(self.db.as_ref())
+    }
+
+    /// Replace the database with a snapshot; this is a replace-import, so it
+    /// is backed up first and can be undone
+    #[instrument(skip(self))]
+    pub fn restore_backup(&self, file_name: &str) -> Result<usize, Box<dyn std::error::Error>> {
+        let json_data = backups::read_backup(self.db.as_ref(), file_name)?;
+        let restored = self.import_database(json_data, true, None)?;
+        info!("Restored backup {} with {} collections", file_name, restored);
+        Ok(restored)
+    }
+
+    /// The whole database, read through the same JSON form any backend exports
+    fn snapshot(&self) -> Result<(String, Database), Box<dyn std::error::Error>> {
+        let json_data = self.db.export_to_json()?;
+        let (database, _) = JsonStore::parse_database(&json_data)?;
+        Ok((json_data, database))
+    }
+
+    #[instrument(skip(self))]
+    pub fn verify_database(&self) -> Result<Vec<IntegrityIssue>, Box<dyn std::error::Error>> {
+        let (_, database) = self.snapshot()?;
+        let issues = integrity::check_database(&database);
+        info!("Integrity check found {} issues", issues.len());
+        Ok(issues)
+    }
+
+    /// Fix what `verify_database` reports, after backing up the database
+    #[instrument(skip(self))]
+    pub fn repair_database(&self) -> Result<RepairReport, Box<dyn std::error::Error>> {
+        let (before, mut database) = self.snapshot()?;
+        let fixed = integrity::repair_database(&mut database);
+        let remaining = integrity::check_database(&database);
+
+        if fixed.is_empty() {
+            return Ok(RepairReport {
+                fixed,
+                remaining,
+                backup: None,
+            });
+        }
+
+        let backup = backups::create_backup(self.db.as_ref(), BackupReason::BeforeRepair)?;
+        self.db
+            .import_from_json(serde_json::to_string_pretty(&database)?, true)?;
+        self.journal.record(Operation::RepairDatabase {
+            before,
+            after: self.db.export_to_json()?,
+        });
+
+        Ok(RepairReport {
+            fixed,
+            remaining,
+            backup: Some(backup),
+        })
+    }
+
+    pub fn storage_backend(&self) -> StorageBackend {
+        self.db.backend()
+    }
+
+    /// Housekeeping run once the database can be read: purge expired trash
+    /// and take the startup backup. Failures are only logged.
+    #[instrument(skip(self))]
+    pub fn run_startup_maintenance(&self) {
+        if let Err(e) = self.purge_expired_trash() {
+            warn!("Failed to purge expired trash: {}", e);
+        }
+        if let Err(e) = self.create_backup(BackupReason::Startup) {
+            warn!("Failed to create startup backup: {}", e);
+        }
+    }
+
+    pub fn encryption_status(&self) -> EncryptionStatus {
+        self.db.encryption_status()
+    }
+
+    #[instrument(skip(self, passphrase))]
+    pub fn unlock(&self, passphrase: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.db.unlock(passphrase)
+    }
+
+    /// Encrypt the database and its backups with a new passphrase
+    #[instrument(skip(self, passphrase))]
+    pub fn enable_encryption(&self, passphrase: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let backups = backups::open_all_backups(self.db.as_ref())?;
+        self.db.enable_encryption(passphrase)?;
+        backups::reseal_backups(self.db.as_ref(), backups)
+    }
+
+    #[instrument(skip(self, current, new))]
+    pub fn change_passphrase(&self, current: &str, new: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let backups = backups::open_all_backups(self.db.as_ref())?;
+        self.db.change_passphrase(current, new)?;
+        backups::reseal_backups(self.db.as_ref(), backups)
+    }
+
+    /// Decrypt the database and its backups back to plaintext
+    #[instrument(skip(self, passphrase))]
+    pub fn disable_encryption(&self, passphrase: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let backups = backups::open_all_backups(self.db.as_ref())?;
+        self.db.disable_encryption(passphrase)?;
+        backups::reseal_backups(self.db.as_ref(), backups)
     }
 }
 
+/// Site-level changes from `from` to `to`, matching sites by URL
+pub fn diff_sites(
+    from: &[SiteEntry],
+    to: &[SiteEntry],
+) -> (Vec<SiteEntry>, Vec<SiteEntry>, Vec<RetitledSite>) {
+    // First occurrence wins for duplicated URLs
+    let titles_by_url = |sites: &[SiteEntry]| {
+        let mut titles: HashMap<String, String> = HashMap::new();
+        for site in sites {
+            titles
+                .entry(site.url.clone())
+                .or_insert_with(|| site.title.clone());
+        }
+        titles
+    };
+    let from_titles = titles_by_url(from);
+    let to_titles = titles_by_url(to);
+
+    let added = to
+        .iter()
+        .filter(|site| !from_titles.contains_key(&site.url))
+        .cloned()
+        .collect();
+    let removed = from
+        .iter()
+        .filter(|site| !to_titles.contains_key(&site.url))
+        .cloned()
+        .collect();
+
+    let mut seen = HashSet::new();
+    let retitled = to
+        .iter()
+        .filter(|site| seen.insert(site.url.as_str()))
+        .filter_map(|site| {
+            let old_title = from_titles.get(&site.url)?;
+            (*old_title != site.title).then(|| RetitledSite {
+                url: site.url.clone(),
+                old_title: old_title.clone(),
+                new_title: site.title.clone(),
+            })
+        })
+        .collect();
+
+    (added, removed, retitled)
+}
+
 pub struct ProfileService {
-    db: JsonStore,
+    db: Arc<dyn Store>,
+    journal: Arc<Journal>,
 }
 
 impl ProfileService {
-    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        let db = JsonStore::new()?;
-        Ok(Self { db })
+    pub fn new(db: Arc<dyn Store>, journal: Arc<Journal>) -> Self {
+        Self { db, journal }
     }
 
     // Browser Detection Utilities
@@ -234,6 +1034,71 @@ impl ProfileService {
         }
     }
 
+    /// Firefox profile directories, where the session store and `places.sqlite` live
+    #[instrument]
+    pub fn firefox_profile_dirs() -> Vec<PathBuf> {
+        let mut roots = Vec::new();
+
+        #[cfg(target_os = "macos")]
+        roots.extend(dirs::config_dir().map(|dir| dir.join("Firefox").join("Profiles")));
+
+        #[cfg(target_os = "windows")]
+        roots.extend(dirs::config_dir().map(|dir| dir.join("Mozilla").join("Firefox").join("Profiles")));
+
+        #[cfg(target_os = "linux")]
+        if let Some(home) = dirs::home_dir() {
+            // Distribution packages, then the Snap and Flatpak sandboxes
+            roots.push(home.join(".mozilla/firefox"));
+            roots.push(home.join("snap/firefox/common/.mozilla/firefox"));
+            roots.push(home.join(".var/app/org.mozilla.firefox/.mozilla/firefox"));
+        }
+
+        // Profiles sit next to crash reports and other data; only they have prefs.js
+        let mut profiles: Vec<PathBuf> = roots
+            .iter()
+            .filter_map(|root| std::fs::read_dir(root).ok())
+            .flatten()
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|dir| dir.join("prefs.js").is_file())
+            .collect();
+        profiles.sort();
+        profiles
+    }
+
+    /// Profile directories ("Default", "Profile 1", ...) of a Chromium-based browser
+    #[instrument]
+    pub fn chromium_profile_dirs(browser: &Browser) -> Vec<PathBuf> {
+        let user_data: Option<PathBuf> = match browser {
+            #[cfg(target_os = "macos")]
+            Browser::Chrome => dirs::config_dir().map(|dir| dir.join("Google").join("Chrome")),
+            #[cfg(target_os = "macos")]
+            Browser::Edge => dirs::config_dir().map(|dir| dir.join("Microsoft Edge")),
+
+            #[cfg(target_os = "windows")]
+            Browser::Chrome => dirs::data_local_dir().map(|dir| dir.join("Google").join("Chrome").join("User Data")),
+            #[cfg(target_os = "windows")]
+            Browser::Edge => dirs::data_local_dir().map(|dir| dir.join("Microsoft").join("Edge").join("User Data")),
+
+            #[cfg(target_os = "linux")]
+            Browser::Chrome => dirs::config_dir().map(|dir| dir.join("google-chrome")),
+            #[cfg(target_os = "linux")]
+            Browser::Edge => dirs::config_dir().map(|dir| dir.join("microsoft-edge")),
+
+            _ => None,
+        };
+
+        // Each profile keeps a Preferences file; the user data dir also holds caches
+        let mut profiles: Vec<PathBuf> = user_data
+            .and_then(|dir| std::fs::read_dir(dir).ok())
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|dir| dir.join("Preferences").is_file())
+            .collect();
+        profiles.sort();
+        profiles
+    }
+
     #[instrument]
     pub fn check_custom_path(path: &str) -> bool {
         std::path::Path::new(path).exists()
@@ -325,7 +1190,11 @@ impl ProfileService {
     
     #[instrument(skip(self, profile))]
     pub fn create_profile(&self, profile: BrowserProfile) -> Result<BrowserProfile, Box<dyn std::error::Error>> {
-        self.db.create_profile(profile)
+        let created = self.db.create_profile(profile)?;
+        self.journal.record(Operation::CreateProfile {
+            profile: Box::new(created.clone()),
+        });
+        Ok(created)
     }
     
     #[instrument(skip(self))]
@@ -340,12 +1209,31 @@ impl ProfileService {
     
     #[instrument(skip(self, profile))]
     pub fn update_profile(&self, id: &str, profile: BrowserProfile) -> Result<BrowserProfile, Box<dyn std::error::Error>> {
-        self.db.update_profile(id, profile)
+        let existing = self
+            .get_profile(id)?
+            .ok_or_else(|| AppError::not_found(format!("Profile with ID '{}' not found for update", id)))?;
+
+        let updated = self.db.update_profile(id, profile)?;
+        self.journal.record(Operation::UpdateProfile {
+            before: Box::new(existing),
+            after: Box::new(updated.clone()),
+        });
+        Ok(updated)
     }
     
     #[instrument(skip(self))]
     pub fn delete_profile(&self, id: &str) -> Result<bool, Box<dyn std::error::Error>> {
-        self.db.delete_profile(id)
+        let Some(existing) = self.get_profile(id)? else {
+            return self.db.delete_profile(id);
+        };
+
+        let deleted = self.db.delete_profile(id)?;
+        if deleted {
+            self.journal.record(Operation::DeleteProfile {
+                profile: Box::new(existing),
+            });
+        }
+        Ok(deleted)
     }
     
     #[instrument(skip(self))]
@@ -355,7 +1243,14 @@ impl ProfileService {
     
     #[instrument(skip(self))]
     pub fn set_default_browser_mode(&self, mode: BrowserMode) -> Result<(), Box<dyn std::error::Error>> {
-        self.db.set_default_browser_mode(mode)
+        let before = self.db.get_default_browser_mode()?;
+        self.db.set_default_browser_mode(mode.clone())?;
+
+        self.journal.record(Operation::SetDefaultBrowserMode {
+            before,
+            after: mode,
+        });
+        Ok(())
     }
 }
 
@@ -371,19 +1266,13 @@ pub struct BrowserService;
 
 impl BrowserService {
     // Resolve browser configuration using ProfileService for full profile resolution
-    fn resolve_browser_config(
-        config: &CollectionConfig,
-    ) -> Result<ResolvedBrowserConfig, Box<dyn std::error::Error>> {
-        let profile_service = ProfileService::new()?;
-        profile_service.resolve_browser_config(config)
-    }
-
-    #[instrument(skip(sites))]
+    #[instrument(skip(profiles, sites))]
     pub fn restore_sites_with_config(
+        profiles: &ProfileService,
         sites: Vec<SiteEntry>,
         config: &CollectionConfig,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let resolved_config = Self::resolve_browser_config(config)?;
+        let resolved_config = profiles.resolve_browser_config(config)?;
 
         info!(
             "Starting browser restoration for {} sites with {:?} in {:?} mode",
@@ -392,9 +1281,11 @@ impl BrowserService {
             resolved_config.mode
         );
 
+        let mut failures = Vec::new();
         for (index, site) in sites.iter().enumerate() {
             if let Err(e) = Self::open_url_with_resolved_config(&site.url, &resolved_config) {
                 warn!("Failed to open URL {}: {}", site.url, e);
+                failures.push(format!("{}: {}", site.url, e));
             } else {
                 info!("Opened URL {}: {}", index + 1, site.url);
             }
@@ -403,6 +1294,16 @@ impl BrowserService {
             std::thread::sleep(std::time::Duration::from_millis(500));
         }
 
+        // Some URLs failing is reported in the log; none opening means the browser didn't start
+        if !sites.is_empty() && failures.len() == sites.len() {
+            return Err(AppError::new(
+                ErrorKind::BrowserLaunch,
+                format!("Could not launch {:?}", resolved_config.browser),
+            )
+            .with_details(failures.join("\n"))
+            .into());
+        }
+
         info!("Browser restoration completed");
         Ok(())
     }
@@ -584,9 +1485,114 @@ impl BrowserService {
     }
 
     // Backward compatibility - defaults to Chrome incognito
-    #[instrument(skip(sites))]
-    pub fn restore_sites(sites: Vec<SiteEntry>) -> Result<(), Box<dyn std::error::Error>> {
+    #[instrument(skip(profiles, sites))]
+    pub fn restore_sites(
+        profiles: &ProfileService,
+        sites: Vec<SiteEntry>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
         let default_config = CollectionConfig::default();
-        Self::restore_sites_with_config(sites, &default_config)
+        Self::restore_sites_with_config(profiles, sites, &default_config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{json_store, sample_record, site};
+
+    fn temp_service(name: &str) -> (CollectionService, Arc<Journal>) {
+        let journal = Arc::new(Journal::default());
+        (CollectionService::new(Arc::new(json_store(name)), journal.clone()), journal)
+    }
+
+    #[test]
+    fn test_diff_sites() {
+        let from = vec![
+            site("A", "https://a.com"),
+            site("B", "https://b.com"),
+            site("C", "https://c.com"),
+        ];
+        let to = vec![
+            site("A", "https://a.com"),
+            site("Bee", "https://b.com"),
+            site("D", "https://d.com"),
+        ];
+
+        let (added, removed, retitled) = diff_sites(&from, &to);
+        assert_eq!(added, vec![site("D", "https://d.com")]);
+        assert_eq!(removed, vec![site("C", "https://c.com")]);
+        assert_eq!(retitled.len(), 1);
+        assert_eq!(retitled[0].url, "https://b.com");
+        assert_eq!((retitled[0].old_title.as_str(), retitled[0].new_title.as_str()), ("B", "Bee"));
+    }
+
+    #[test]
+    fn test_import_collections_reports_the_merge() {
+        let (service, journal) = temp_service("import-collections");
+        let lists = || {
+            vec![
+                sample_record("Reading", &["https://a.com"]),
+                sample_record("Reading", &["https://b.com"]),
+                sample_record("News", &["https://c.com"]),
+            ]
+        };
+
+        // The second "Reading" clashes with the first one
+        let report = service.import_collections(lists(), 0, MergeStrategy::Skip).unwrap();
+        assert_eq!(report.created.len(), 2);
+        assert_eq!(report.skipped_collections, vec!["Reading".to_string()]);
+
+        // Nothing new is written, so there is nothing to undo either
+        let report = service.import_collections(lists(), 0, MergeStrategy::Skip).unwrap();
+        assert!(report.created.is_empty());
+        assert_eq!(report.skipped_collections.len(), 3);
+
+        let report = service.import_collections(lists(), 0, MergeStrategy::Rename).unwrap();
+        let renamed: Vec<&str> = report.renamed.iter().map(|r| r.to.as_str()).collect();
+        assert_eq!(renamed, vec!["Reading (2)", "Reading (3)", "News (2)"]);
+
+        journal.undo(service.db.as_ref()).unwrap();
+        journal.undo(service.db.as_ref()).unwrap();
+        assert!(service.load_all_collections().unwrap().is_empty());
+        assert!(journal.status().next_undo.is_none());
+    }
+
+    #[test]
+    fn test_import_two_onetab_exports() {
+        let (service, _) = temp_service("import-onetab");
+        let first = "https://a.com | A\n\nhttps://b.com | B\n";
+        let second = "https://c.com | C\n\nhttps://d.com | D\n";
+
+        service.import_from_format(ImportFormat::OneTab, first, MergeStrategy::Skip).unwrap();
+        let report = service.import_from_format(ImportFormat::OneTab, second, MergeStrategy::Skip).unwrap();
+
+        // The second export's groups get names of their own instead of being skipped
+        assert!(report.skipped_collections.is_empty());
+        let names: Vec<&str> = report.created.iter().map(|record| record.name.as_str()).collect();
+        assert_eq!(names, vec!["OneTab group 1 (2)", "OneTab group 2 (2)"]);
+        assert_eq!(service.load_all_collections().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_import_bookmarks_drops_unknown_browser_paths() {
+        let (service, _) = temp_service("import-bookmarks-paths");
+        let html = r#"<DL><p>
+<DT><H3>Path</H3>
+<!-- restore-sites:config {"browser_profile_id":null,"browser":"Firefox","mode":null,"custom_path":"/tmp/evil"} -->
+<DL><p><DT><A HREF="https://a.com">A</A></DL><p>
+<DT><H3>Custom</H3>
+<!-- restore-sites:config {"browser_profile_id":null,"browser":{"Custom":"/tmp/evil"},"mode":null,"custom_path":null} -->
+<DL><p><DT><A HREF="https://b.com">B</A></DL><p>
+<DT><H3>Plain</H3>
+<!-- restore-sites:config {"browser_profile_id":null,"browser":"Chrome","mode":null,"custom_path":null} -->
+<DL><p><DT><A HREF="https://c.com">C</A></DL><p>
+</DL><p>"#;
+
+        let report = service.import_bookmarks_html(html, false, MergeStrategy::Skip).unwrap();
+        assert_eq!(report.dropped_paths, vec!["Path".to_string(), "Custom".to_string()]);
+        let configs: Vec<_> = report.created.iter().map(|record| &record.config).collect();
+        assert_eq!((configs[0].browser.clone(), configs[0].custom_path.clone()), (Some(Browser::Firefox), None));
+        assert_eq!(configs[1].browser, None);
+        assert_eq!(configs[2].browser, Some(Browser::Chrome));
     }
 }
@@ -0,0 +1,1003 @@
+use crate::database::{
+    is_revision_worthy, merge_databases, record_revision, restore_record_id, JsonStore, Store,
+};
+use crate::error::AppError;
+use crate::normalize::canonicalize_sites;
+use crate::models::{
+    BrowserMode, BrowserProfile, CollectionRecord, CollectionRevision, Database, DatabaseMeta,
+    MergeReport, MergeStrategy, SiteEntry, StorageBackend, TrashedCollection,
+};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Transaction, TransactionBehavior};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+use tracing::{debug, info, instrument, warn};
+
+/// How long a writer waits on another process's transaction before failing.
+///
+/// Writes begin with `TransactionBehavior::Immediate` so the write lock is
+/// taken up front, where this timeout applies; a deferred transaction that
+/// read first would fail at once with SQLITE_BUSY_SNAPSHOT under WAL instead.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS meta (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    data TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS collections (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    config TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_collections_name ON collections (name COLLATE NOCASE);
+
+CREATE TABLE IF NOT EXISTS sites (
+    collection_id INTEGER NOT NULL REFERENCES collections (id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    url TEXT NOT NULL,
+    canonical_url TEXT,
+    PRIMARY KEY (collection_id, position)
+);
+CREATE INDEX IF NOT EXISTS idx_sites_url ON sites (url);
+
+CREATE TABLE IF NOT EXISTS trash (
+    entry INTEGER PRIMARY KEY AUTOINCREMENT,
+    id INTEGER NOT NULL,
+    deleted_at TEXT NOT NULL,
+    data TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS revisions (
+    collection_id INTEGER NOT NULL,
+    revision INTEGER NOT NULL,
+    data TEXT NOT NULL,
+    PRIMARY KEY (collection_id, revision)
+);
+
+CREATE TABLE IF NOT EXISTS profiles (
+    id TEXT PRIMARY KEY,
+    is_default INTEGER NOT NULL DEFAULT 0,
+    data TEXT NOT NULL
+);
+";
+
+/// SQLite-backed store for large databases.
+///
+/// Collections, sites and profiles live in their own indexed tables so lookups
+/// and edits no longer read and rewrite the whole database. `DatabaseMeta` is
+/// kept as a single JSON row to stay in step with the JSON schema.
+#[derive(Debug)]
+pub struct SqliteStore {
+    conn: Mutex<Connection>,
+    path: PathBuf,
+}
+
+impl SqliteStore {
+    pub fn open(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let conn = Connection::open(path)?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(None, "foreign_keys", true)?;
+        conn.execute_batch(SCHEMA)?;
+        Self::upgrade_canonical_urls(&conn)?;
+        Self::upgrade_trash_entries(&conn)?;
+
+        let has_meta: bool = conn.query_row("SELECT EXISTS (SELECT 1 FROM meta)", [], |row| {
+            row.get(0)
+        })?;
+        if !has_meta {
+            Self::write_meta(&conn, &DatabaseMeta::default())?;
+        }
+
+        debug!("Opened SQLite database at {}", path.display());
+        Ok(Self {
+            conn: Mutex::new(conn),
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// The trash used to be keyed by collection ID, so trashing an ID a second
+    /// time (e.g. after a replace-import) overwrote the first; like the JSON
+    /// store it now keeps every entry
+    fn upgrade_trash_entries(conn: &Connection) -> Result<(), Box<dyn std::error::Error>> {
+        let has_column: bool = conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM pragma_table_info('trash') WHERE name = 'entry')",
+            [],
+            |row| row.get(0),
+        )?;
+        if !has_column {
+            conn.execute_batch(
+                "BEGIN IMMEDIATE;
+                 ALTER TABLE trash RENAME TO trash_by_id;
+                 CREATE TABLE trash (
+                     entry INTEGER PRIMARY KEY AUTOINCREMENT,
+                     id INTEGER NOT NULL,
+                     deleted_at TEXT NOT NULL,
+                     data TEXT NOT NULL
+                 );
+                 INSERT INTO trash (id, deleted_at, data)
+                     SELECT id, deleted_at, data FROM trash_by_id ORDER BY deleted_at;
+                 DROP TABLE trash_by_id;
+                 COMMIT;",
+            )?;
+            info!("Upgraded the trash to keep repeated collection IDs");
+        }
+
+        conn.execute_batch(
+            "CREATE INDEX IF NOT EXISTS idx_trash_id ON trash (id);
+             CREATE INDEX IF NOT EXISTS idx_trash_deleted_at ON trash (deleted_at);",
+        )?;
+        Ok(())
+    }
+
+    /// Databases created before canonical URLs were stored get the column, filled in
+    fn upgrade_canonical_urls(conn: &Connection) -> Result<(), Box<dyn std::error::Error>> {
+        let has_column: bool = conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM pragma_table_info('sites') WHERE name = 'canonical_url')",
+            [],
+            |row| row.get(0),
+        )?;
+        if !has_column {
+            // One transaction, so a failed backfill doesn't leave the column
+            // added and half the sites without a canonical URL
+            let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)?;
+            tx.execute_batch("ALTER TABLE sites ADD COLUMN canonical_url TEXT")?;
+            let mut records = Self::query_records(&tx, "", &[])?;
+            for record in &mut records {
+                canonicalize_sites(&mut record.sites, &record.config);
+                Self::write_sites(&tx, record.id, &record.sites)?;
+            }
+            tx.commit()?;
+            info!("Added canonical URLs to {} collections", records.len());
+        }
+
+        conn.execute_batch("CREATE INDEX IF NOT EXISTS idx_sites_canonical_url ON sites (canonical_url)")?;
+        Ok(())
+    }
+
+    fn conn(&self) -> Result<MutexGuard<'_, Connection>, Box<dyn std::error::Error>> {
+        self.conn
+            .lock()
+            .map_err(|_| "SQLite connection lock poisoned".into())
+    }
+
+    fn read_meta(conn: &Connection) -> Result<DatabaseMeta, Box<dyn std::error::Error>> {
+        let data: String = conn.query_row("SELECT data FROM meta WHERE id = 1", [], |row| {
+            row.get(0)
+        })?;
+        Ok(serde_json::from_str(&data)?)
+    }
+
+    fn write_meta(conn: &Connection, meta: &DatabaseMeta) -> Result<(), Box<dyn std::error::Error>> {
+        conn.execute(
+            "INSERT INTO meta (id, data) VALUES (1, ?1)
+             ON CONFLICT (id) DO UPDATE SET data = excluded.data",
+            params![serde_json::to_string(meta)?],
+        )?;
+        Ok(())
+    }
+
+    fn count_collections(conn: &Connection) -> Result<usize, Box<dyn std::error::Error>> {
+        Ok(conn.query_row("SELECT COUNT(*) FROM collections", [], |row| row.get(0))?)
+    }
+
+    fn write_sites(
+        conn: &Connection,
+        collection_id: u64,
+        sites: &[SiteEntry],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        conn.execute("DELETE FROM sites WHERE collection_id = ?1", params![collection_id])?;
+
+        let mut stmt = conn.prepare(
+            "INSERT INTO sites (collection_id, position, title, url, canonical_url)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        for (position, site) in sites.iter().enumerate() {
+            stmt.execute(params![collection_id, position, site.title, site.url, site.canonical_url])?;
+        }
+        Ok(())
+    }
+
+    fn write_record(conn: &Connection, record: &CollectionRecord) -> Result<(), Box<dyn std::error::Error>> {
+        conn.execute(
+            "INSERT INTO collections (id, name, config, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT (id) DO UPDATE SET
+                 name = excluded.name,
+                 config = excluded.config,
+                 created_at = excluded.created_at,
+                 updated_at = excluded.updated_at",
+            params![
+                record.id,
+                record.name,
+                serde_json::to_string(&record.config)?,
+                record.created_at,
+                record.updated_at,
+            ],
+        )?;
+        Self::write_sites(conn, record.id, &record.sites)
+    }
+
+    /// Load collections matching an optional `WHERE` clause, sites included
+    fn query_records(
+        conn: &Connection,
+        filter: &str,
+        params: &[&dyn rusqlite::ToSql],
+    ) -> Result<Vec<CollectionRecord>, Box<dyn std::error::Error>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, name, config, created_at, updated_at FROM collections {} ORDER BY id",
+            filter
+        ))?;
+        let rows = stmt.query_map(params, |row| {
+            Ok((
+                row.get::<_, u64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get(3)?,
+                row.get(4)?,
+            ))
+        })?;
+
+        let mut records = Vec::new();
+        for row in rows {
+            let (id, name, config, created_at, updated_at) = row?;
+            records.push(CollectionRecord {
+                id,
+                name,
+                sites: Vec::new(),
+                config: serde_json::from_str(&config)?,
+                created_at,
+                updated_at,
+            });
+        }
+
+        if records.is_empty() {
+            return Ok(records);
+        }
+
+        // Fetch sites in one pass rather than one query per collection
+        let mut sites_by_collection: HashMap<u64, Vec<SiteEntry>> = HashMap::new();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT collection_id, title, url, canonical_url FROM sites
+             WHERE collection_id IN (SELECT id FROM collections {})
+             ORDER BY collection_id, position",
+            filter
+        ))?;
+        let rows = stmt.query_map(params, |row| {
+            Ok((
+                row.get::<_, u64>(0)?,
+                SiteEntry {
+                    title: row.get(1)?,
+                    url: row.get(2)?,
+                    canonical_url: row.get(3)?,
+                },
+            ))
+        })?;
+        for row in rows {
+            let (collection_id, site) = row?;
+            sites_by_collection.entry(collection_id).or_default().push(site);
+        }
+
+        for record in &mut records {
+            record.sites = sites_by_collection.remove(&record.id).unwrap_or_default();
+        }
+        Ok(records)
+    }
+
+    fn read_profiles(conn: &Connection) -> Result<Vec<BrowserProfile>, Box<dyn std::error::Error>> {
+        let mut stmt = conn.prepare("SELECT data FROM profiles ORDER BY rowid")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+
+        let mut profiles = Vec::new();
+        for data in rows {
+            profiles.push(serde_json::from_str(&data?)?);
+        }
+        Ok(profiles)
+    }
+
+    fn write_profile(conn: &Connection, profile: &BrowserProfile) -> Result<(), Box<dyn std::error::Error>> {
+        conn.execute(
+            "INSERT INTO profiles (id, is_default, data) VALUES (?1, ?2, ?3)
+             ON CONFLICT (id) DO UPDATE SET is_default = excluded.is_default, data = excluded.data",
+            params![profile.id, profile.is_default, serde_json::to_string(profile)?],
+        )?;
+        Ok(())
+    }
+
+    fn read_trash(conn: &Connection) -> Result<Vec<TrashedCollection>, Box<dyn std::error::Error>> {
+        let mut stmt = conn.prepare("SELECT deleted_at, data FROM trash ORDER BY deleted_at, entry")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get::<_, String>(1)?)))?;
+
+        let mut trash = Vec::new();
+        for row in rows {
+            let (deleted_at, data) = row?;
+            trash.push(TrashedCollection {
+                record: serde_json::from_str(&data)?,
+                deleted_at,
+            });
+        }
+        Ok(trash)
+    }
+
+    fn write_trashed(conn: &Connection, trashed: &TrashedCollection) -> Result<(), Box<dyn std::error::Error>> {
+        conn.execute(
+            "INSERT INTO trash (id, deleted_at, data) VALUES (?1, ?2, ?3)",
+            params![
+                trashed.record.id,
+                trashed.deleted_at,
+                serde_json::to_string(&trashed.record)?
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Delete trashed collections older than `cutoff` (all of them for None)
+    /// along with their revisions
+    fn purge_trash(&self, cutoff: Option<DateTime<Utc>>) -> Result<usize, Box<dyn std::error::Error>> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+        let purged = match cutoff {
+            Some(cutoff) => tx.execute("DELETE FROM trash WHERE deleted_at < ?1", params![cutoff])?,
+            None => tx.execute("DELETE FROM trash", [])?,
+        };
+        if purged > 0 {
+            Self::prune_orphan_revisions(&tx)?;
+            let mut meta = Self::read_meta(&tx)?;
+            meta.last_updated = Utc::now();
+            Self::write_meta(&tx, &meta)?;
+        }
+
+        tx.commit()?;
+        Ok(purged)
+    }
+
+    /// Revisions of one collection, or of all of them, newest first
+    fn read_revisions(
+        conn: &Connection,
+        collection_id: Option<u64>,
+    ) -> Result<Vec<CollectionRevision>, Box<dyn std::error::Error>> {
+        let mut stmt = conn.prepare(
+            "SELECT data FROM revisions WHERE ?1 IS NULL OR collection_id = ?1
+             ORDER BY collection_id, revision DESC",
+        )?;
+        let rows = stmt.query_map(params![collection_id], |row| row.get::<_, String>(0))?;
+
+        let mut revisions = Vec::new();
+        for row in rows {
+            revisions.push(serde_json::from_str(&row?)?);
+        }
+        Ok(revisions)
+    }
+
+    /// Replace a collection's stored revisions with `revisions`
+    fn write_revisions(
+        conn: &Connection,
+        collection_id: u64,
+        revisions: &[CollectionRevision],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        conn.execute("DELETE FROM revisions WHERE collection_id = ?1", params![collection_id])?;
+
+        let mut stmt = conn.prepare(
+            "INSERT INTO revisions (collection_id, revision, data) VALUES (?1, ?2, ?3)",
+        )?;
+        for revision in revisions.iter().filter(|r| r.collection_id == collection_id) {
+            stmt.execute(params![
+                revision.collection_id,
+                revision.revision,
+                serde_json::to_string(revision)?
+            ])?;
+        }
+        Ok(())
+    }
+
+    fn prune_orphan_revisions(conn: &Connection) -> Result<(), Box<dyn std::error::Error>> {
+        conn.execute(
+            "DELETE FROM revisions
+             WHERE collection_id NOT IN (SELECT id FROM collections)
+               AND collection_id NOT IN (SELECT id FROM trash)",
+            [],
+        )?;
+        Ok(())
+    }
+
+    fn read_all(conn: &Connection) -> Result<Database, Box<dyn std::error::Error>> {
+        Ok(Database {
+            meta: Self::read_meta(conn)?,
+            profiles: Self::read_profiles(conn)?,
+            data: Self::query_records(conn, "", &[])?,
+            trash: Self::read_trash(conn)?,
+            revisions: Self::read_revisions(conn, None)?,
+        })
+    }
+
+    /// Replace the entire contents of the store in a single transaction
+    #[instrument(skip(self, database))]
+    pub fn replace_all(&self, database: &Database) -> Result<(), Box<dyn std::error::Error>> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+        tx.execute_batch(
+            "DELETE FROM sites; DELETE FROM collections; DELETE FROM profiles; DELETE FROM trash;
+             DELETE FROM revisions;",
+        )?;
+        for profile in &database.profiles {
+            Self::write_profile(&tx, profile)?;
+        }
+        for record in &database.data {
+            Self::write_record(&tx, record)?;
+        }
+        for trashed in &database.trash {
+            Self::write_trashed(&tx, trashed)?;
+        }
+        for revision in &database.revisions {
+            tx.execute(
+                "INSERT OR REPLACE INTO revisions (collection_id, revision, data) VALUES (?1, ?2, ?3)",
+                params![
+                    revision.collection_id,
+                    revision.revision,
+                    serde_json::to_string(revision)?
+                ],
+            )?;
+        }
+        Self::write_meta(&tx, &database.meta)?;
+
+        tx.commit()?;
+        info!("Replaced SQLite database with {} records", database.data.len());
+        Ok(())
+    }
+}
+
+impl Store for SqliteStore {
+    fn backend(&self) -> StorageBackend {
+        StorageBackend::Sqlite
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    #[instrument(skip(self, record))]
+    fn insert(
+        &self,
+        mut record: CollectionRecord,
+    ) -> Result<CollectionRecord, Box<dyn std::error::Error>> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let mut meta = Self::read_meta(&tx)?;
+
+        // Auto-assign ID
+        meta.max_id += 1;
+        record.id = meta.max_id;
+        record.updated_at = Utc::now();
+        Self::write_record(&tx, &record)?;
+
+        meta.record_count = Self::count_collections(&tx)?;
+        meta.last_updated = Utc::now();
+        meta.last_updated_id = record.id;
+        Self::write_meta(&tx, &meta)?;
+
+        tx.commit()?;
+        info!("Inserted new record with ID: {}", record.id);
+        Ok(record)
+    }
+
+    #[instrument(skip(self))]
+    fn get_all(&self) -> Result<Vec<CollectionRecord>, Box<dyn std::error::Error>> {
+        let conn = self.conn()?;
+        Self::query_records(&conn, "", &[])
+    }
+
+    #[instrument(skip(self))]
+    fn get_by_id(
+        &self,
+        id: u64,
+    ) -> Result<Option<CollectionRecord>, Box<dyn std::error::Error>> {
+        let conn = self.conn()?;
+        let records = Self::query_records(&conn, "WHERE id = ?1", &[&id])?;
+        Ok(records.into_iter().next())
+    }
+
+    #[instrument(skip(self))]
+    fn search_by_name(
+        &self,
+        query: &str,
+    ) -> Result<Vec<CollectionRecord>, Box<dyn std::error::Error>> {
+        // Matched in Rust rather than with LIKE, which only folds ASCII case,
+        // so "é" finds "École" as it does in the JSON store
+        let conn = self.conn()?;
+        let query = query.to_lowercase();
+        let results: Vec<CollectionRecord> = Self::query_records(&conn, "", &[])?
+            .into_iter()
+            .filter(|record| record.name.to_lowercase().contains(&query))
+            .collect();
+
+        debug!("Search for '{}' returned {} results", query, results.len());
+        Ok(results)
+    }
+
+    #[instrument(skip(self, updated_record))]
+    fn update(
+        &self,
+        updated_record: CollectionRecord,
+    ) -> Result<CollectionRecord, Box<dyn std::error::Error>> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+        let Some(previous) = Self::query_records(&tx, "WHERE id = ?1", &[&updated_record.id])?.pop()
+        else {
+            return Err(AppError::not_found(format!("Record with ID {} not found for update", updated_record.id)).into());
+        };
+
+        let mut record = updated_record.clone();
+        record.updated_at = Utc::now(); // Ensure updated timestamp
+        Self::write_record(&tx, &record)?;
+
+        if is_revision_worthy(&previous, &record) {
+            let mut revisions = Self::read_revisions(&tx, Some(previous.id))?;
+            record_revision(&mut revisions, &previous);
+            Self::write_revisions(&tx, previous.id, &revisions)?;
+        }
+
+        let mut meta = Self::read_meta(&tx)?;
+        meta.last_updated = Utc::now();
+        meta.last_updated_id = updated_record.id;
+        Self::write_meta(&tx, &meta)?;
+
+        tx.commit()?;
+        info!("Updated record with ID: {}", updated_record.id);
+        Ok(updated_record)
+    }
+
+    #[instrument(skip(self))]
+    fn delete_by_id(&self, id: u64) -> Result<bool, Box<dyn std::error::Error>> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+        let Some(record) = Self::query_records(&tx, "WHERE id = ?1", &[&id])?.pop() else {
+            warn!("Record with ID {} not found for deletion", id);
+            return Ok(false);
+        };
+
+        Self::write_trashed(
+            &tx,
+            &TrashedCollection {
+                record,
+                deleted_at: Utc::now(),
+            },
+        )?;
+        tx.execute("DELETE FROM collections WHERE id = ?1", params![id])?;
+
+        let mut meta = Self::read_meta(&tx)?;
+        meta.record_count = Self::count_collections(&tx)?;
+        meta.last_updated = Utc::now();
+        Self::write_meta(&tx, &meta)?;
+
+        tx.commit()?;
+        info!("Moved record with ID {} to trash", id);
+        Ok(true)
+    }
+
+    #[instrument(skip(self))]
+    fn list_trash(&self) -> Result<Vec<TrashedCollection>, Box<dyn std::error::Error>> {
+        let conn = self.conn()?;
+        Self::read_trash(&conn)
+    }
+
+    #[instrument(skip(self))]
+    fn restore_from_trash(
+        &self,
+        id: u64,
+    ) -> Result<Option<CollectionRecord>, Box<dyn std::error::Error>> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+        // The oldest entry first, as in the JSON store
+        let entry: Option<(i64, String)> = tx
+            .query_row(
+                "SELECT entry, data FROM trash WHERE id = ?1 ORDER BY entry LIMIT 1",
+                params![id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        let Some((entry, data)) = entry else {
+            warn!("Record with ID {} not found in trash", id);
+            return Ok(None);
+        };
+
+        let mut record: CollectionRecord = serde_json::from_str(&data)?;
+        let mut database = Database {
+            meta: Self::read_meta(&tx)?,
+            profiles: Vec::new(),
+            data: Self::query_records(&tx, "WHERE id = ?1", &[&id])?,
+            trash: Vec::new(),
+            revisions: Vec::new(),
+        };
+        restore_record_id(&mut database, &mut record);
+
+        tx.execute("DELETE FROM trash WHERE entry = ?1", params![entry])?;
+        Self::write_record(&tx, &record)?;
+
+        let mut meta = database.meta;
+        meta.record_count = Self::count_collections(&tx)?;
+        meta.last_updated = Utc::now();
+        meta.last_updated_id = record.id;
+        Self::write_meta(&tx, &meta)?;
+
+        tx.commit()?;
+        info!("Restored record with ID {} from trash", record.id);
+        Ok(Some(record))
+    }
+
+    #[instrument(skip(self))]
+    fn empty_trash(&self) -> Result<usize, Box<dyn std::error::Error>> {
+        let purged = self.purge_trash(None)?;
+        info!("Emptied trash, {} collections permanently deleted", purged);
+        Ok(purged)
+    }
+
+    #[instrument(skip(self))]
+    fn purge_trash_before(&self, cutoff: DateTime<Utc>) -> Result<usize, Box<dyn std::error::Error>> {
+        let purged = self.purge_trash(Some(cutoff))?;
+        if purged > 0 {
+            info!("Permanently deleted {} collections from trash", purged);
+        }
+        Ok(purged)
+    }
+
+    #[instrument(skip(self))]
+    fn list_revisions(
+        &self,
+        collection_id: u64,
+    ) -> Result<Vec<CollectionRevision>, Box<dyn std::error::Error>> {
+        let conn = self.conn()?;
+        Self::read_revisions(&conn, Some(collection_id))
+    }
+
+    #[instrument(skip(self))]
+    fn get_revision(
+        &self,
+        collection_id: u64,
+        revision: u32,
+    ) -> Result<Option<CollectionRevision>, Box<dyn std::error::Error>> {
+        let conn = self.conn()?;
+        let data: Option<String> = conn
+            .query_row(
+                "SELECT data FROM revisions WHERE collection_id = ?1 AND revision = ?2",
+                params![collection_id, revision],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        match data {
+            Some(data) => Ok(Some(serde_json::from_str(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    #[instrument(skip(self))]
+    fn get_trash_retention_days(&self) -> Result<u32, Box<dyn std::error::Error>> {
+        let conn = self.conn()?;
+        Ok(Self::read_meta(&conn)?.trash_retention_days)
+    }
+
+    #[instrument(skip(self))]
+    fn set_trash_retention_days(&self, days: u32) -> Result<(), Box<dyn std::error::Error>> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+        let mut meta = Self::read_meta(&tx)?;
+        meta.trash_retention_days = days;
+        meta.last_updated = Utc::now();
+        Self::write_meta(&tx, &meta)?;
+
+        tx.commit()?;
+        info!("Updated trash retention to {} days", days);
+        Ok(())
+    }
+
+    #[instrument(skip(self, profile))]
+    fn create_profile(
+        &self,
+        profile: BrowserProfile,
+    ) -> Result<BrowserProfile, Box<dyn std::error::Error>> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+        let inserted = tx.execute(
+            "INSERT OR IGNORE INTO profiles (id, is_default, data) VALUES (?1, ?2, ?3)",
+            params![profile.id, profile.is_default, serde_json::to_string(&profile)?],
+        )?;
+        if inserted == 0 {
+            return Err(AppError::conflict(format!("Profile with ID '{}' already exists", profile.id)).into());
+        }
+
+        let mut meta = Self::read_meta(&tx)?;
+        meta.last_updated = Utc::now();
+        Self::write_meta(&tx, &meta)?;
+
+        tx.commit()?;
+        info!("Created new browser profile: {}", profile.id);
+        Ok(profile)
+    }
+
+    #[instrument(skip(self))]
+    fn get_profile(
+        &self,
+        id: &str,
+    ) -> Result<Option<BrowserProfile>, Box<dyn std::error::Error>> {
+        let conn = self.conn()?;
+        let data: Option<String> = conn
+            .query_row("SELECT data FROM profiles WHERE id = ?1", params![id], |row| {
+                row.get(0)
+            })
+            .optional()?;
+
+        match data {
+            Some(data) => Ok(Some(serde_json::from_str(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    #[instrument(skip(self))]
+    fn get_all_profiles(&self) -> Result<Vec<BrowserProfile>, Box<dyn std::error::Error>> {
+        let conn = self.conn()?;
+        Self::read_profiles(&conn)
+    }
+
+    #[instrument(skip(self, updated_profile))]
+    fn update_profile(
+        &self,
+        id: &str,
+        updated_profile: BrowserProfile,
+    ) -> Result<BrowserProfile, Box<dyn std::error::Error>> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+        let mut profile = updated_profile.clone();
+        profile.updated_at = Utc::now(); // Ensure updated timestamp
+
+        // The stored row keeps its key even if the payload carries a new ID,
+        // matching the in-place replacement done by JsonStore
+        let updated = tx.execute(
+            "UPDATE profiles SET is_default = ?2, data = ?3 WHERE id = ?1",
+            params![id, profile.is_default, serde_json::to_string(&profile)?],
+        )?;
+        if updated == 0 {
+            return Err(AppError::not_found(format!("Profile with ID '{}' not found for update", id)).into());
+        }
+        if profile.id != id {
+            tx.execute(
+                "UPDATE profiles SET id = ?2 WHERE id = ?1",
+                params![id, profile.id],
+            )?;
+        }
+
+        let mut meta = Self::read_meta(&tx)?;
+        meta.last_updated = Utc::now();
+        Self::write_meta(&tx, &meta)?;
+
+        tx.commit()?;
+        info!("Updated browser profile: {}", id);
+        Ok(updated_profile)
+    }
+
+    #[instrument(skip(self))]
+    fn delete_profile(&self, id: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+        let deleted = tx.execute("DELETE FROM profiles WHERE id = ?1", params![id])? > 0;
+        if !deleted {
+            warn!("Profile with ID '{}' not found for deletion", id);
+            return Ok(false);
+        }
+
+        let mut meta = Self::read_meta(&tx)?;
+        meta.last_updated = Utc::now();
+        Self::write_meta(&tx, &meta)?;
+
+        tx.commit()?;
+        info!("Deleted browser profile: {}", id);
+        Ok(true)
+    }
+
+    #[instrument(skip(self))]
+    fn get_default_browser_mode(&self) -> Result<BrowserMode, Box<dyn std::error::Error>> {
+        let conn = self.conn()?;
+        Ok(Self::read_meta(&conn)?.default_browser_mode)
+    }
+
+    #[instrument(skip(self))]
+    fn set_default_browser_mode(
+        &self,
+        mode: BrowserMode,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+        let mut meta = Self::read_meta(&tx)?;
+        meta.default_browser_mode = mode.clone();
+        meta.last_updated = Utc::now();
+        Self::write_meta(&tx, &meta)?;
+
+        tx.commit()?;
+        info!("Updated default browser mode to: {:?}", mode);
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    fn export_to_json(&self) -> Result<String, Box<dyn std::error::Error>> {
+        let conn = self.conn()?;
+        let database = Self::read_all(&conn)?;
+        let json_data = serde_json::to_string_pretty(&database)?;
+        info!("Exported database to JSON, {} characters", json_data.len());
+        Ok(json_data)
+    }
+
+    #[instrument(skip(self, json_data), fields(data_length = json_data.len()))]
+    fn import_from_json(
+        &self,
+        json_data: String,
+        replace_existing: bool,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        if !replace_existing {
+            return Ok(self.merge_from_json(json_data, MergeStrategy::Skip)?.created_count());
+        }
+        let (import_database, _) = JsonStore::parse_database(&json_data)?;
+
+        let imported_count = import_database.data.len();
+        self.replace_all(&import_database)?;
+        info!("Replaced entire database with {} collections", imported_count);
+        Ok(imported_count)
+    }
+
+    #[instrument(skip(self, json_data), fields(data_length = json_data.len()))]
+    fn merge_from_json(
+        &self,
+        json_data: String,
+        strategy: MergeStrategy,
+    ) -> Result<MergeReport, Box<dyn std::error::Error>> {
+        let (import_database, _) = JsonStore::parse_database(&json_data)?;
+
+        let mut conn = self.conn()?;
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+        let mut existing_database = Self::read_all(&tx)?;
+        let known: HashMap<u64, DateTime<Utc>> = existing_database
+            .data
+            .iter()
+            .map(|r| (r.id, r.updated_at))
+            .collect();
+        let known_profiles = existing_database.profiles.len();
+        let report = merge_databases(&mut existing_database, import_database, strategy);
+
+        // Merging only appends profiles
+        for profile in &existing_database.profiles[known_profiles..] {
+            Self::write_profile(&tx, profile)?;
+        }
+        // New collections, and existing ones that were overwritten or gained sites
+        for record in &existing_database.data {
+            match known.get(&record.id) {
+                None => Self::write_record(&tx, record)?,
+                Some(updated_at) if *updated_at != record.updated_at => {
+                    Self::write_record(&tx, record)?;
+                    Self::write_revisions(&tx, record.id, &existing_database.revisions)?;
+                }
+                Some(_) => {}
+            }
+        }
+        Self::write_meta(&tx, &existing_database.meta)?;
+
+        tx.commit()?;
+        info!("Merged database, imported {} new collections", report.created_count());
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Browser;
+    use crate::test_support::{sample_record, sqlite_store as temp_store};
+
+    #[test]
+    fn test_collection_crud() {
+        let store = temp_store("crud");
+        let first = store.insert(sample_record("Work", &["https://a.com", "https://b.com"])).unwrap();
+        let second = store.insert(sample_record("Personal", &["https://c.com"])).unwrap();
+        assert_eq!((first.id, second.id), (1, 2));
+
+        let loaded = store.get_by_id(1).unwrap().unwrap();
+        assert_eq!(loaded.sites.len(), 2);
+        assert_eq!(loaded.sites[1].url, "https://b.com");
+
+        let mut edited = loaded.clone();
+        edited.sites.truncate(1);
+        store.update(edited).unwrap();
+        assert_eq!(store.get_by_id(1).unwrap().unwrap().sites.len(), 1);
+        assert_eq!(store.list_revisions(1).unwrap()[0].sites.len(), 2);
+        assert_eq!(store.get_revision(1, 1).unwrap().unwrap().revision, 1);
+
+        assert_eq!(store.search_by_name("pers").unwrap().len(), 1);
+        assert!(store.delete_by_id(2).unwrap());
+        assert!(!store.delete_by_id(2).unwrap());
+        assert_eq!(store.list_trash().unwrap()[0].record.sites.len(), 1);
+        assert_eq!(store.restore_from_trash(2).unwrap().unwrap().name, "Personal");
+        assert!(store.delete_by_id(2).unwrap());
+
+        // A second trashed collection with the same ID is kept, as in the JSON store
+        let mut older = store.list_trash().unwrap().remove(0);
+        older.record.name = "Older".to_string();
+        SqliteStore::write_trashed(&store.conn().unwrap(), &older).unwrap();
+        assert_eq!(store.list_trash().unwrap().len(), 2);
+        let before = SqliteStore::read_meta(&store.conn().unwrap()).unwrap().last_updated;
+        assert_eq!(store.empty_trash().unwrap(), 2);
+
+        let meta = SqliteStore::read_meta(&store.conn().unwrap()).unwrap();
+        assert!(meta.last_updated > before);
+        assert_eq!(meta.max_id, 2);
+        assert_eq!(meta.record_count, 1);
+    }
+
+    #[test]
+    fn test_search_folds_non_ascii_case() {
+        let store = temp_store("search");
+        store.insert(sample_record("École", &["https://a.com"])).unwrap();
+        assert_eq!(store.search_by_name("éCOLE").unwrap()[0].name, "École");
+        assert_eq!(store.search_by_name("%").unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_profiles_and_default_mode() {
+        let store = temp_store("profiles");
+        let profile = BrowserProfile::new(
+            "work".to_string(),
+            "Work".to_string(),
+            Browser::Firefox,
+            BrowserMode::Private,
+        )
+        .unwrap();
+
+        store.create_profile(profile.clone()).unwrap();
+        assert!(store.create_profile(profile).is_err());
+        assert_eq!(store.get_all_profiles().unwrap().len(), 1);
+
+        store.set_default_browser_mode(BrowserMode::Normal).unwrap();
+        assert!(matches!(
+            store.get_default_browser_mode().unwrap(),
+            BrowserMode::Normal
+        ));
+        assert!(store.delete_profile("work").unwrap());
+        assert!(store.get_profile("work").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_export_round_trips_through_json_import() {
+        let store = temp_store("roundtrip");
+        store.insert(sample_record("Work", &["https://a.com"])).unwrap();
+        let profile = BrowserProfile::new("work".to_string(), "Work".to_string(), Browser::Firefox, BrowserMode::Normal).unwrap();
+        store.create_profile(profile).unwrap();
+        let exported = store.export_to_json().unwrap();
+
+        let other = temp_store("roundtrip-target");
+        other.insert(sample_record("work", &["https://z.com"])).unwrap();
+        assert_eq!(other.import_from_json(exported.clone(), false).unwrap(), 0);
+        assert!(other.get_profile("work").unwrap().is_some());
+
+        let report = other.merge_from_json(exported.clone(), MergeStrategy::Union).unwrap();
+        assert_eq!(report.merged[0].added_sites, 1);
+        assert_eq!(other.get_all().unwrap()[0].sites.len(), 2);
+        assert_eq!(other.list_revisions(1).unwrap().len(), 1);
+
+        assert_eq!(other.import_from_json(exported, true).unwrap(), 1);
+        assert_eq!(other.get_all().unwrap()[0].sites[0].url, "https://a.com");
+    }
+}
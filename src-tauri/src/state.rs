@@ -0,0 +1,93 @@
+use crate::database::{open_store, Store, UnavailableStore};
+use crate::error::AppError;
+use crate::journal::Journal;
+use crate::services::{CollectionService, ProfileService};
+use std::sync::{Arc, RwLock};
+use tracing::{error, info, instrument};
+
+/// Application-wide state registered with `tauri::Builder::manage`.
+///
+/// Holds the one open store for the lifetime of the app so commands share its
+/// in-memory database instead of re-reading the file on every call.
+#[derive(Debug)]
+pub struct AppState {
+    store: RwLock<Arc<dyn Store>>,
+    startup_error: RwLock<Option<AppError>>,
+    journal: Arc<Journal>,
+}
+
+impl AppState {
+    /// Open the active workspace's store. If that fails the app still starts,
+    /// on a store that reports the error, so the UI can show it and offer
+    /// `reload` or a different workspace instead of crashing.
+    pub fn new() -> Self {
+        let (store, startup_error): (Arc<dyn Store>, _) = match open_store() {
+            Ok(store) => {
+                info!("Opened {:?} store", store.backend());
+                (Arc::from(store), None)
+            }
+            Err(e) => {
+                let error = AppError::from(e).context("Failed to open the database");
+                error!("{}", error);
+                (Arc::new(UnavailableStore::new(error.clone())), Some(error))
+            }
+        };
+        Self {
+            store: RwLock::new(store),
+            startup_error: RwLock::new(startup_error),
+            journal: Arc::new(Journal::default()),
+        }
+    }
+
+    /// Why the store couldn't be opened, until a `reload` succeeds
+    pub fn startup_error(&self) -> Option<AppError> {
+        self.startup_error
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// The active store; cheap to call, the handle is reference counted
+    pub fn store(&self) -> Arc<dyn Store> {
+        // A panic while swapping stores can't leave the Arc half-written
+        self.store
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Re-open the store, e.g. after the data moved to a different backend.
+    ///
+    /// The undo journal is cleared, its entries belong to the previous store.
+    #[instrument(skip(self))]
+    pub fn reload(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.replace_store(Arc::from(open_store()?));
+        Ok(())
+    }
+
+    /// Swap in an already opened store, e.g. one in another workspace
+    pub fn replace_store(&self, store: Arc<dyn Store>) {
+        info!("Opened {:?} store at {}", store.backend(), store.path().display());
+        *self
+            .store
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = store;
+        *self
+            .startup_error
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+        self.journal.clear();
+    }
+
+    pub fn journal(&self) -> &Journal {
+        &self.journal
+    }
+
+    pub fn collections(&self) -> CollectionService {
+        CollectionService::new(self.store(), self.journal.clone())
+    }
+
+    pub fn profiles(&self) -> ProfileService {
+        ProfileService::new(self.store(), self.journal.clone())
+    }
+}
@@ -0,0 +1,54 @@
+//! Fixtures shared by the unit tests
+
+use crate::database::{JsonStore, DB_FILE_NAME, SQLITE_FILE_NAME};
+use crate::models::{CollectionRecord, SiteEntry};
+use crate::sqlite_store::SqliteStore;
+use chrono::Utc;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+
+/// A new, empty directory; the counter keeps tests that share a name, or run
+/// in parallel, from stepping on each other's files
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "restore-sites-test-{}-{}-{}",
+        name,
+        std::process::id(),
+        NEXT_DIR.fetch_add(1, Ordering::Relaxed)
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+pub fn json_store(name: &str) -> JsonStore {
+    JsonStore::with_path(temp_dir(name).join(DB_FILE_NAME)).unwrap()
+}
+
+pub fn sqlite_store(name: &str) -> SqliteStore {
+    SqliteStore::open(&temp_dir(name).join(SQLITE_FILE_NAME)).unwrap()
+}
+
+pub fn site(title: &str, url: &str) -> SiteEntry {
+    SiteEntry {
+        title: title.to_string(),
+        url: url.to_string(),
+        canonical_url: None,
+    }
+}
+
+/// A collection ready to insert, its sites titled with their URLs
+pub fn sample_record(name: &str, urls: &[&str]) -> CollectionRecord {
+    let now = Utc::now();
+    CollectionRecord {
+        id: 0,
+        name: name.to_string(),
+        sites: urls.iter().map(|url| site(url, url)).collect(),
+        config: Default::default(),
+        created_at: now,
+        updated_at: now,
+    }
+}
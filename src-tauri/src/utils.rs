@@ -1,15 +1,50 @@
 #![allow(dead_code)]
 use std::path::PathBuf;
+use std::sync::OnceLock;
 use tracing::{debug, instrument};
 
 const APP_DIR_NAME: &str = ".restore-sites";
+const DATA_DIR_ENV: &str = "RESTORE_SITES_DATA_DIR";
+const DATA_DIR_FLAG: &str = "--data-dir";
 
-/// Get the application data directory ($HOME/.restore-sites)
+/// Data directory passed on the command line, set once at startup
+static DATA_DIR_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Use `path` as the data directory for the rest of the process
+pub fn set_data_dir_override(path: PathBuf) {
+    if DATA_DIR_OVERRIDE.set(path).is_err() {
+        tracing::warn!("Data directory override already set, ignoring");
+    }
+}
+
+/// Find `--data-dir <path>` or `--data-dir=<path>` in the process arguments
+pub fn data_dir_from_args<I: IntoIterator<Item = String>>(args: I) -> Option<PathBuf> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == DATA_DIR_FLAG {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(value) = arg.strip_prefix(DATA_DIR_FLAG).and_then(|rest| rest.strip_prefix('=')) {
+            return Some(PathBuf::from(value));
+        }
+    }
+    None
+}
+
+/// Get the application data directory.
+///
+/// Resolved from the `--data-dir` flag, then `$RESTORE_SITES_DATA_DIR`, then
+/// `$HOME/.restore-sites`. Workspaces live underneath this directory.
 #[instrument]
 pub fn get_data_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
-    let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
-
-    let data_dir = home_dir.join(APP_DIR_NAME);
+    let data_dir = if let Some(dir) = DATA_DIR_OVERRIDE.get() {
+        dir.clone()
+    } else if let Some(dir) = std::env::var_os(DATA_DIR_ENV).filter(|dir| !dir.is_empty()) {
+        PathBuf::from(dir)
+    } else {
+        let home_dir = dirs::home_dir().ok_or("Could not determine home directory")?;
+        home_dir.join(APP_DIR_NAME)
+    };
     debug!("Data directory: {}", data_dir.display());
 
     Ok(data_dir)
@@ -93,6 +128,22 @@ mod tests {
         assert_eq!(clean_url("not-a-url"), None);
     }
 
+    #[test]
+    fn test_data_dir_from_args() {
+        let args = |list: &[&str]| list.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            data_dir_from_args(args(&["app", "--data-dir", "/tmp/sites"])),
+            Some(PathBuf::from("/tmp/sites"))
+        );
+        assert_eq!(
+            data_dir_from_args(args(&["app", "--data-dir=/mnt/sync/sites"])),
+            Some(PathBuf::from("/mnt/sync/sites"))
+        );
+        assert_eq!(data_dir_from_args(args(&["app", "--data-dir"])), None);
+        assert_eq!(data_dir_from_args(args(&["app", "--data-directory=x"])), None);
+    }
+
     #[test]
     fn test_extract_domain() {
         assert_eq!(
@@ -0,0 +1,198 @@
+use crate::error::AppError;
+use crate::models::WorkspaceInfo;
+use crate::utils::get_data_dir;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use tracing::{debug, info, instrument, warn};
+
+/// The workspace stored directly in the data directory (the pre-workspace layout)
+pub const DEFAULT_WORKSPACE: &str = "default";
+
+const WORKSPACES_DIR_NAME: &str = "workspaces";
+const SETTINGS_FILE_NAME: &str = "settings.json";
+
+/// App-level settings kept next to the workspaces, outside any one database
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Settings {
+    active_workspace: Option<String>,
+}
+
+fn settings_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    Ok(get_data_dir()?.join(SETTINGS_FILE_NAME))
+}
+
+fn load_settings() -> Result<Settings, Box<dyn std::error::Error>> {
+    let path = settings_path()?;
+    if !path.exists() {
+        return Ok(Settings::default());
+    }
+
+    match serde_json::from_str(&fs::read_to_string(&path)?) {
+        Ok(settings) => Ok(settings),
+        Err(e) => {
+            warn!("Ignoring unreadable settings file {}: {}", path.display(), e);
+            Ok(Settings::default())
+        }
+    }
+}
+
+fn save_settings(settings: &Settings) -> Result<(), Box<dyn std::error::Error>> {
+    let path = settings_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    // Same write-then-rename as the database so a crash can't truncate it
+    let temp_path = path.with_extension("json.tmp");
+    fs::write(&temp_path, serde_json::to_string_pretty(settings)?)?;
+    fs::rename(&temp_path, &path)?;
+    Ok(())
+}
+
+/// Workspace names double as directory names, so keep them portable
+pub fn validate_workspace_name(name: &str) -> Result<(), String> {
+    let char_count = name.chars().count();
+    if char_count == 0 {
+        return Err("Workspace name cannot be empty".to_string());
+    }
+    if char_count > 64 {
+        return Err("Workspace name cannot exceed 64 characters".to_string());
+    }
+    if name.starts_with('.') || name.starts_with(' ') || name.ends_with(' ') {
+        return Err("Workspace name cannot start with '.' or start or end with a space".to_string());
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_' | '.'))
+    {
+        return Err(
+            "Workspace name can only contain letters, numbers, spaces, '-', '_' and '.'"
+                .to_string(),
+        );
+    }
+    Ok(())
+}
+
+/// Directory holding a workspace's database files
+pub fn workspace_dir(name: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let data_dir = get_data_dir()?;
+    if name == DEFAULT_WORKSPACE {
+        return Ok(data_dir);
+    }
+
+    validate_workspace_name(name).map_err(AppError::validation)?;
+    Ok(data_dir.join(WORKSPACES_DIR_NAME).join(name))
+}
+
+fn workspace_exists(name: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    Ok(name == DEFAULT_WORKSPACE || workspace_dir(name)?.is_dir())
+}
+
+/// Name of the workspace remembered from the last launch
+#[instrument]
+pub fn active_workspace() -> Result<String, Box<dyn std::error::Error>> {
+    let settings = load_settings()?;
+
+    match settings.active_workspace {
+        Some(name) if workspace_exists(&name).unwrap_or(false) => Ok(name),
+        Some(name) => {
+            warn!("Active workspace '{}' no longer exists, using default", name);
+            Ok(DEFAULT_WORKSPACE.to_string())
+        }
+        None => Ok(DEFAULT_WORKSPACE.to_string()),
+    }
+}
+
+/// Directory of the active workspace, where the store is opened
+pub fn active_workspace_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let dir = workspace_dir(&active_workspace()?)?;
+    debug!("Active workspace directory: {}", dir.display());
+    Ok(dir)
+}
+
+fn workspace_info(name: &str, active: &str) -> Result<WorkspaceInfo, Box<dyn std::error::Error>> {
+    Ok(WorkspaceInfo {
+        name: name.to_string(),
+        path: workspace_dir(name)?.to_string_lossy().to_string(),
+        is_active: name == active,
+    })
+}
+
+#[instrument]
+pub fn list_workspaces() -> Result<Vec<WorkspaceInfo>, Box<dyn std::error::Error>> {
+    let active = active_workspace()?;
+    let mut names = Vec::new();
+
+    let workspaces_dir = get_data_dir()?.join(WORKSPACES_DIR_NAME);
+    if workspaces_dir.is_dir() {
+        for entry in fs::read_dir(&workspaces_dir)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name != DEFAULT_WORKSPACE && validate_workspace_name(&name).is_ok() {
+                names.push(name);
+            }
+        }
+    }
+    names.sort_by_key(|name| name.to_lowercase());
+    names.insert(0, DEFAULT_WORKSPACE.to_string());
+
+    names
+        .iter()
+        .map(|name| workspace_info(name, &active))
+        .collect()
+}
+
+#[instrument]
+pub fn create_workspace(name: &str) -> Result<WorkspaceInfo, Box<dyn std::error::Error>> {
+    let name = name.trim();
+    validate_workspace_name(name).map_err(AppError::validation)?;
+    if workspace_exists(name)? {
+        return Err(AppError::conflict(format!("Workspace '{}' already exists", name)).into());
+    }
+
+    fs::create_dir_all(workspace_dir(name)?)?;
+    info!("Created workspace: {}", name);
+    workspace_info(name, &active_workspace()?)
+}
+
+/// Directory of a workspace that must already exist, e.g. one being switched to
+pub fn existing_workspace_dir(name: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    if !workspace_exists(name)? {
+        return Err(AppError::not_found(format!("Workspace '{}' does not exist", name)).into());
+    }
+    workspace_dir(name)
+}
+
+/// Make `name` the active workspace and remember it for the next launch
+#[instrument]
+pub fn set_active_workspace(name: &str) -> Result<WorkspaceInfo, Box<dyn std::error::Error>> {
+    existing_workspace_dir(name)?;
+
+    let mut settings = load_settings()?;
+    settings.active_workspace = Some(name.to_string());
+    save_settings(&settings)?;
+
+    info!("Switched to workspace: {}", name);
+    workspace_info(name, name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_workspace_name() {
+        assert!(validate_workspace_name("Work").is_ok());
+        assert!(validate_workspace_name("client-a_2024").is_ok());
+        assert!(validate_workspace_name("").is_err());
+        assert!(validate_workspace_name("..").is_err());
+        assert!(validate_workspace_name(".hidden").is_err());
+        assert!(validate_workspace_name("a/b").is_err());
+        assert!(validate_workspace_name("a\\b").is_err());
+        assert!(validate_workspace_name(&"x".repeat(65)).is_err());
+    }
+}
@@ -1,12 +1,41 @@
 // Typed Tauri API wrapper for collection operations
 
 import { invoke } from "@tauri-apps/api/core";
+import { toApiError } from "./errors";
 import type {
   CollectionData,
   SaveCollectionRequest,
   SiteEntry,
   CollectionConfig,
   CollectionRecord,
+  StorageBackend,
+  TrashedCollection,
+  CollectionRevision,
+  RevisionDiff,
+  JournalEntryInfo,
+  JournalStatus,
+  BackupInfo,
+  IntegrityIssue,
+  RepairReport,
+  EncryptionStatus,
+  ParsedSites,
+  DuplicateReport,
+  DedupeStrategy,
+  DedupeResult,
+  ImportReport,
+  ImportFormat,
+  ImportPreview,
+  ImportSelection,
+  MergeStrategy,
+  MergeReport,
+  ExportFormat,
+  SessionFile,
+  BrowserSession,
+  Browser,
+  BrowserDataSource,
+  BrowserDataFilter,
+  BrowserDataProfile,
+  AppError,
 } from "$lib/types/models";
 import { DEFAULT_COLLECTION_CONFIG } from "$lib/types/models";
 
@@ -27,7 +56,7 @@ export class CollectionAPI {
       });
       return result;
     } catch (error) {
-      throw new Error(`Failed to save collection: ${error}`);
+      throw toApiError(error);
     }
   }
 
@@ -39,7 +68,7 @@ export class CollectionAPI {
       const result = await invoke<CollectionRecord[]>("load_collections");
       return result;
     } catch (error) {
-      throw new Error(`Failed to load collections: ${error}`);
+      throw toApiError(error);
     }
   }
 
@@ -50,7 +79,7 @@ export class CollectionAPI {
     try {
       await invoke<void>("restore_collection", { sites, config });
     } catch (error) {
-      throw new Error(`Failed to restore collection: ${error}`);
+      throw toApiError(error);
     }
   }
 
@@ -62,7 +91,7 @@ export class CollectionAPI {
       const result = await invoke<CollectionRecord | null>("get_collection", { id });
       return result;
     } catch (error) {
-      throw new Error(`Failed to get collection: ${error}`);
+      throw toApiError(error);
     }
   }
 
@@ -80,7 +109,7 @@ export class CollectionAPI {
       });
       return result;
     } catch (error) {
-      throw new Error(`Failed to update collection: ${error}`);
+      throw toApiError(error);
     }
   }
 
@@ -92,7 +121,7 @@ export class CollectionAPI {
       const result = await invoke<boolean>("delete_collection", { id });
       return result;
     } catch (error) {
-      throw new Error(`Failed to delete collection: ${error}`);
+      throw toApiError(error);
     }
   }
 
@@ -103,7 +132,7 @@ export class CollectionAPI {
     try {
       return await invoke<string>("greet", { name });
     } catch (error) {
-      throw new Error(`Failed to greet: ${error}`);
+      throw toApiError(error);
     }
   }
 }
@@ -167,30 +196,469 @@ export async function deleteCollection(id: number): Promise<boolean> {
   return CollectionAPI.deleteCollection(id);
 }
 
+// Parse pasted text into sites; skipped or repaired lines are reported in `diagnostics`
+export async function parseSitesText(text: string): Promise<ParsedSites> {
+  try {
+    return await invoke<ParsedSites>("parse_sites_text", { text });
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+// Trash
+export async function listTrash(): Promise<TrashedCollection[]> {
+  try {
+    return await invoke<TrashedCollection[]>("list_trash");
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+export async function restoreFromTrash(id: number): Promise<CollectionRecord> {
+  try {
+    return await invoke<CollectionRecord>("restore_from_trash", { id });
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+export async function emptyTrash(): Promise<number> {
+  try {
+    return await invoke<number>("empty_trash");
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+export async function getTrashRetentionDays(): Promise<number> {
+  try {
+    return await invoke<number>("get_trash_retention_days");
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+export async function setTrashRetentionDays(days: number): Promise<void> {
+  try {
+    await invoke<void>("set_trash_retention_days", { days });
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+// Revision History
+export async function listRevisions(collectionId: number): Promise<CollectionRevision[]> {
+  try {
+    return await invoke<CollectionRevision[]>("list_revisions", { collectionId });
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+export async function diffRevisions(
+  collectionId: number,
+  fromRevision: number,
+  toRevision?: number,
+): Promise<RevisionDiff> {
+  try {
+    return await invoke<RevisionDiff>("diff_revisions", {
+      collectionId,
+      fromRevision,
+      toRevision: toRevision ?? null,
+    });
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+export async function rollbackCollection(collectionId: number, revision: number): Promise<CollectionRecord> {
+  try {
+    return await invoke<CollectionRecord>("rollback_collection", { collectionId, revision });
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+// Duplicates
+export async function findDuplicates(): Promise<DuplicateReport> {
+  try {
+    return await invoke<DuplicateReport>("find_duplicates");
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+export async function dedupeCollection(id: number, strategy: DedupeStrategy): Promise<DedupeResult> {
+  try {
+    return await invoke<DedupeResult>("dedupe_collection", { id, strategy });
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+// Undo/Redo
+export async function undoLastOperation(): Promise<JournalEntryInfo | null> {
+  try {
+    return await invoke<JournalEntryInfo | null>("undo_last_operation");
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+export async function redoOperation(): Promise<JournalEntryInfo | null> {
+  try {
+    return await invoke<JournalEntryInfo | null>("redo_operation");
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+export async function getJournalStatus(): Promise<JournalStatus> {
+  try {
+    return await invoke<JournalStatus>("get_journal_status");
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
 // Backup and Restore
-export async function exportDatabase(): Promise<string> {
+// Exports are encrypted when a passphrase is given
+/** Export as JSON; with `ids`, only those collections and the profiles they use */
+export async function exportDatabase(passphrase?: string, ids?: number[]): Promise<string> {
   try {
-    return await invoke<string>("export_database");
+    return await invoke<string>("export_database", { passphrase, ids });
   } catch (error) {
-    throw new Error(`Failed to export database: ${error}`);
+    throw toApiError(error);
   }
 }
 
-export async function exportDatabaseToFile(): Promise<string> {
+/**
+ * Save an export of the collections in `ids` (all when omitted) through a file
+ * dialog. The file type picks the format: `.json` is a backup that can be
+ * imported again, `.md`, `.csv`, `.opml` and `.txt` are text and can't be encrypted
+ */
+export async function exportDatabaseToFile(passphrase?: string, ids?: number[]): Promise<string> {
   try {
-    return await invoke<string>("export_database_to_file");
+    return await invoke<string>("export_database_to_file", { passphrase, ids });
   } catch (error) {
-    throw new Error(`Failed to export database to file: ${error}`);
+    throw toApiError(error);
   }
 }
 
-export async function importDatabase(jsonData: string, replaceExisting: boolean = false): Promise<number> {
+export async function importDatabase(
+  jsonData: string,
+  replaceExisting: boolean = false,
+  passphrase?: string,
+): Promise<number> {
   try {
     return await invoke<number>("import_database", { 
       jsonData, 
-      replaceExisting 
+      replaceExisting,
+      passphrase,
     });
   } catch (error) {
-    throw new Error(`Failed to import database: ${error}`);
+    throw toApiError(error);
+  }
+}
+
+/**
+ * Merge an export, handling name clashes with `strategy`; with `dryRun` the
+ * report describes what would happen and nothing is written
+ */
+export async function mergeDatabase(
+  jsonData: string,
+  strategy: MergeStrategy,
+  dryRun: boolean = false,
+  passphrase?: string,
+): Promise<MergeReport> {
+  try {
+    return await invoke<MergeReport>("merge_database", { jsonData, passphrase, strategy, dryRun });
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+/** List the collections and profiles in an export file, for picking what to import */
+export async function previewImport(jsonData: string, passphrase?: string): Promise<ImportPreview> {
+  try {
+    return await invoke<ImportPreview>("preview_import", { jsonData, passphrase });
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+/** Merge the collections and profiles picked from previewImport, handling name clashes with `strategy` */
+export async function importSelected(
+  jsonData: string,
+  selection: ImportSelection,
+  passphrase?: string,
+  strategy: MergeStrategy = "Skip",
+): Promise<ImportReport> {
+  try {
+    return await invoke<ImportReport>("import_selected", { jsonData, passphrase, selection, strategy });
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+/**
+ * Import a browser's bookmarks.html, one collection per folder
+ * (or per top-level folder with `flatten`)
+ */
+export async function importBookmarksHtml(
+  html: string,
+  flatten: boolean = false,
+  strategy: MergeStrategy = "Skip",
+): Promise<ImportReport> {
+  try {
+    return await invoke<ImportReport>("import_bookmarks_html", { html, flatten, strategy });
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+/**
+ * Export the given collections as a bookmarks.html that browsers can import;
+ * each collection's config is kept for importing back here
+ */
+export async function exportBookmarksHtml(ids: number[]): Promise<string> {
+  try {
+    return await invoke<string>("export_bookmarks_html", { ids });
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+/**
+ * Import a OneTab text export, or a Session Buddy or Toby JSON export
+ */
+/** Export the collections in `ids`, or all of them, as text in the given format */
+export async function exportCollections(format: ExportFormat, ids?: number[]): Promise<string> {
+  try {
+    return await invoke<string>("export_collections", { ids, format });
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+export async function importFromFormat(
+  format: ImportFormat,
+  contents: string,
+  strategy: MergeStrategy = "Skip",
+): Promise<ImportReport> {
+  try {
+    return await invoke<ImportReport>("import_from_format", { format, contents, strategy });
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+// Browser sessions
+export async function listFirefoxSessions(): Promise<SessionFile[]> {
+  try {
+    return await invoke<SessionFile[]>("list_firefox_sessions");
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+export async function readFirefoxSession(path: string, skipPinned: boolean = false): Promise<BrowserSession> {
+  try {
+    return await invoke<BrowserSession>("read_firefox_session", { path, skipPinned });
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+/**
+ * Import a Firefox session, one collection per window; `windows` are indices
+ * into the session returned by readFirefoxSession, all windows when omitted
+ */
+export async function importFirefoxSession(
+  path: string,
+  skipPinned: boolean = false,
+  windows?: number[],
+  strategy: MergeStrategy = "Skip",
+): Promise<ImportReport> {
+  try {
+    return await invoke<ImportReport>("import_firefox_session", { path, skipPinned, windows, strategy });
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+export async function listChromiumSessions(): Promise<SessionFile[]> {
+  try {
+    return await invoke<SessionFile[]>("list_chromium_sessions");
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+export async function readChromiumSession(
+  path: string,
+  browser: Browser,
+  skipPinned: boolean = false,
+): Promise<BrowserSession> {
+  try {
+    return await invoke<BrowserSession>("read_chromium_session", { path, browser, skipPinned });
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+/**
+ * Import a Chrome or Edge session, one collection per window; `windows` are
+ * indices into the session returned by readChromiumSession
+ */
+export async function importChromiumSession(
+  path: string,
+  browser: Browser,
+  skipPinned: boolean = false,
+  windows?: number[],
+  strategy: MergeStrategy = "Skip",
+): Promise<ImportReport> {
+  try {
+    return await invoke<ImportReport>("import_chromium_session", { path, browser, skipPinned, windows, strategy });
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+export async function listBrowserDataProfiles(): Promise<BrowserDataProfile[]> {
+  try {
+    return await invoke<BrowserDataProfile[]>("list_browser_data_profiles");
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+/**
+ * Import a profile's bookmarks (one collection per folder) or history (one
+ * collection of the most recent visits); the database is read from a copy
+ */
+export async function importBrowserData(
+  browser: Browser,
+  profilePath: string,
+  source: BrowserDataSource,
+  filter?: BrowserDataFilter,
+  strategy: MergeStrategy = "Skip",
+): Promise<ImportReport> {
+  try {
+    return await invoke<ImportReport>("import_browser_data", { browser, profilePath, source, filter, strategy });
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+export async function listBackups(): Promise<BackupInfo[]> {
+  try {
+    return await invoke<BackupInfo[]>("list_backups");
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+export async function restoreBackup(fileName: string): Promise<number> {
+  try {
+    return await invoke<number>("restore_backup", { fileName });
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+// Integrity
+export async function verifyDatabase(): Promise<IntegrityIssue[]> {
+  try {
+    return await invoke<IntegrityIssue[]>("verify_database");
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+export async function repairDatabase(): Promise<RepairReport> {
+  try {
+    return await invoke<RepairReport>("repair_database");
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+// Storage Backend
+export async function getStorageBackend(): Promise<StorageBackend> {
+  try {
+    return await invoke<StorageBackend>("get_storage_backend");
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+export async function migrateToSqlite(): Promise<number> {
+  try {
+    return await invoke<number>("migrate_to_sqlite");
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+/** Why the database couldn't be opened at launch, or null when it's open */
+export async function getStartupError(): Promise<AppError | null> {
+  try {
+    return await invoke<AppError | null>("get_startup_error");
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+/** Try opening the database again, e.g. after fixing what getStartupError reported */
+export async function reopenDatabase(): Promise<void> {
+  try {
+    await invoke<void>("reopen_database");
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+export async function getEncryptionStatus(): Promise<EncryptionStatus> {
+  try {
+    return await invoke<EncryptionStatus>("get_encryption_status");
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+// Must be called on launch when the status reports an encrypted, locked database
+export async function unlockDatabase(passphrase: string): Promise<void> {
+  try {
+    await invoke<void>("unlock_database", { passphrase });
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+export async function enableEncryption(passphrase: string): Promise<void> {
+  try {
+    await invoke<void>("enable_encryption", { passphrase });
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+export async function changePassphrase(
+  currentPassphrase: string,
+  newPassphrase: string,
+): Promise<void> {
+  try {
+    await invoke<void>("change_passphrase", { currentPassphrase, newPassphrase });
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+export async function disableEncryption(passphrase: string): Promise<void> {
+  try {
+    await invoke<void>("disable_encryption", { passphrase });
+  } catch (error) {
+    throw toApiError(error);
   }
 }
@@ -0,0 +1,38 @@
+// Errors thrown by the typed API wrappers
+
+import type { AppError, ErrorKind } from "$lib/types/models";
+
+/**
+ * A failed command, keeping the kind the UI can branch on
+ */
+export class ApiError extends Error {
+  kind: ErrorKind;
+  details: string | null;
+
+  constructor(error: AppError) {
+    super(error.message);
+    this.name = "ApiError";
+    this.kind = error.kind;
+    this.details = error.details;
+  }
+}
+
+function isAppError(error: unknown): error is AppError {
+  return (
+    typeof error === "object" &&
+    error !== null &&
+    "kind" in error &&
+    "message" in error
+  );
+}
+
+/**
+ * Wrap whatever `invoke` rejected with; anything that isn't an `AppError`
+ * (e.g. a missing command) is reported as internal
+ */
+export function toApiError(error: unknown): ApiError {
+  if (isAppError(error)) {
+    return new ApiError(error);
+  }
+  return new ApiError({ kind: "internal", message: String(error), details: null });
+}
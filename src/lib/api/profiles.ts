@@ -1,6 +1,7 @@
 // Typed Tauri API wrapper for browser profile operations
 
 import { invoke } from "@tauri-apps/api/core";
+import { toApiError } from "./errors";
 import type { BrowserProfile, BrowserMode } from "$lib/types/models";
 
 export class ProfileAPI {
@@ -12,7 +13,7 @@ export class ProfileAPI {
       const result = await invoke<BrowserProfile>("create_browser_profile", { profile });
       return result;
     } catch (error) {
-      throw new Error(`Failed to create browser profile: ${error}`);
+      throw toApiError(error);
     }
   }
 
@@ -24,7 +25,7 @@ export class ProfileAPI {
       const result = await invoke<BrowserProfile[]>("get_browser_profiles");
       return result;
     } catch (error) {
-      throw new Error(`Failed to get browser profiles: ${error}`);
+      throw toApiError(error);
     }
   }
 
@@ -36,7 +37,7 @@ export class ProfileAPI {
       const result = await invoke<BrowserProfile | null>("get_browser_profile", { id });
       return result;
     } catch (error) {
-      throw new Error(`Failed to get browser profile: ${error}`);
+      throw toApiError(error);
     }
   }
 
@@ -48,7 +49,7 @@ export class ProfileAPI {
       const result = await invoke<BrowserProfile>("update_browser_profile", { id, profile });
       return result;
     } catch (error) {
-      throw new Error(`Failed to update browser profile: ${error}`);
+      throw toApiError(error);
     }
   }
 
@@ -60,7 +61,7 @@ export class ProfileAPI {
       const result = await invoke<boolean>("delete_browser_profile", { id });
       return result;
     } catch (error) {
-      throw new Error(`Failed to delete browser profile: ${error}`);
+      throw toApiError(error);
     }
   }
 
@@ -72,7 +73,7 @@ export class ProfileAPI {
       const result = await invoke<BrowserProfile[]>("check_browser_detection");
       return result;
     } catch (error) {
-      throw new Error(`Failed to check browser detection: ${error}`);
+      throw toApiError(error);
     }
   }
 
@@ -84,7 +85,7 @@ export class ProfileAPI {
       const result = await invoke<BrowserMode>("get_default_browser_mode");
       return result;
     } catch (error) {
-      throw new Error(`Failed to get default browser mode: ${error}`);
+      throw toApiError(error);
     }
   }
 
@@ -95,7 +96,7 @@ export class ProfileAPI {
     try {
       await invoke<void>("set_default_browser_mode", { mode });
     } catch (error) {
-      throw new Error(`Failed to set default browser mode: ${error}`);
+      throw toApiError(error);
     }
   }
 }
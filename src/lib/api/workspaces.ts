@@ -0,0 +1,38 @@
+// Typed Tauri API wrapper for workspace operations
+
+import { invoke } from "@tauri-apps/api/core";
+import { toApiError } from "./errors";
+import type { WorkspaceInfo } from "$lib/types/models";
+
+/**
+ * List all workspaces, starting with the default one
+ */
+export async function getWorkspaces(): Promise<WorkspaceInfo[]> {
+  try {
+    return await invoke<WorkspaceInfo[]>("get_workspaces");
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+/**
+ * Create a new, empty workspace
+ */
+export async function createWorkspace(name: string): Promise<WorkspaceInfo> {
+  try {
+    return await invoke<WorkspaceInfo>("create_workspace", { name });
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
+
+/**
+ * Switch the active workspace; remembered across launches
+ */
+export async function switchWorkspace(name: string): Promise<WorkspaceInfo> {
+  try {
+    return await invoke<WorkspaceInfo>("switch_workspace", { name });
+  } catch (error) {
+    throw toApiError(error);
+  }
+}
@@ -2,7 +2,8 @@
 
 export interface SiteEntry {
   title: string;
-  url: string;
+  url: string;                    // As entered; this is what gets opened
+  canonical_url?: string | null;  // Normalized for comparisons, filled in on save
 }
 
 export interface SaveCollectionRequest {
@@ -24,6 +25,16 @@ export interface CollectionConfig {
   browser?: Browser;
   mode?: BrowserMode;
   custom_path?: string;
+  url_normalization?: UrlNormalization | null;  // Unset uses the defaults
+}
+
+// How a collection's URLs are canonicalized. Hosts are always lowercased,
+// IDN hosts converted to punycode and default ports dropped.
+export interface UrlNormalization {
+  drop_fragment: boolean;        // Default false
+  sort_query: boolean;           // Default true
+  strip_tracking_params: boolean; // Default true
+  tracking_params: string[];     // Exact names, or prefixes like "utm_*"
 }
 
 export type Browser =
@@ -48,21 +59,294 @@ export interface BrowserProfile {
   updated_at: string;            // ISO 8601 DateTime string
 }
 
+// Where the database is persisted
+export type StorageBackend = "Json" | "Sqlite";
+
+// Stable error categories returned by every command
+export type ErrorKind =
+  | "not_found"
+  | "validation"
+  | "conflict"
+  | "corrupt_database"
+  | "unsupported_version"
+  | "database_busy"
+  | "database_locked"
+  | "wrong_passphrase"
+  | "browser_launch"
+  | "io"
+  | "cancelled"
+  | "internal";
+
+// What a failed command rejects with
+export interface AppError {
+  kind: ErrorKind;
+  message: string;         // e.g. "Failed to get collection: Collection with id 7 not found"
+  details: string | null;  // Lower-level cause, e.g. the URLs a browser failed to open
+}
+
+// Whether the database is encrypted at rest, and unlocked for this session
+export interface EncryptionStatus {
+  encrypted: boolean;
+  unlocked: boolean;  // Always true for a plaintext database
+}
+
+// A named database living in its own directory under the data directory
+export interface WorkspaceInfo {
+  name: string;
+  path: string;
+  is_active: boolean;
+}
+
 // JSON Database Schema Types
 export interface DatabaseMeta {
-  version: number;               // Schema version (v2 for profiles)
+  version: number;               // Schema version (v5: profiles, trash, revisions, canonical URLs)
   last_updated_id: number;
   last_updated: string; // ISO 8601 DateTime string
   max_id: number;
   record_count: number;
   created_at: string; // ISO 8601 DateTime string
   default_browser_mode: BrowserMode;  // Global default mode
+  trash_retention_days: number;  // 0 keeps trashed collections until emptied
 }
 
 export interface Database {
   meta: DatabaseMeta;
   profiles: BrowserProfile[];    // Browser profiles array
   data: CollectionRecord[];
+  trash: TrashedCollection[];    // Soft-deleted collections (v3)
+  revisions: CollectionRevision[];  // Previous versions of updated collections (v4)
+}
+
+export interface TrashedCollection {
+  record: CollectionRecord;
+  deleted_at: string; // ISO 8601 DateTime string
+}
+
+export interface CollectionRevision {
+  collection_id: number;
+  revision: number;     // Numbered per collection from 1
+  name: string;
+  sites: SiteEntry[];
+  config: CollectionConfig;
+  saved_at: string; // ISO 8601 DateTime string
+}
+
+export type BackupReason = "Startup" | "BeforeImport" | "BeforeRepair";
+
+export interface BackupInfo {
+  file_name: string;   // db-<timestamp>-<reason>.json
+  path: string;
+  reason: BackupReason;
+  created_at: string; // ISO 8601 DateTime string
+  size_bytes: number;
+}
+
+export type ParseDiagnosticKind =
+  | "SkippedLine"
+  | "RepairedUrl"
+  | "DuplicateUrl"
+  | "MissingTitle";
+
+export interface ParseDiagnostic {
+  line: number;  // 1-based line number in the pasted text
+  kind: ParseDiagnosticKind;
+  message: string;
+}
+
+// Result of parsing pasted "Title https://url" text
+export interface ParsedSites {
+  sites: SiteEntry[];
+  diagnostics: ParseDiagnostic[];
+}
+
+export interface DuplicateSite {
+  collection_id: number;
+  position: number;  // 0-based index in the collection's sites
+  title: string;
+  url: string;
+}
+
+// Sites sharing a canonical URL, with the collections they belong to
+export interface DuplicateGroup {
+  canonical_url: string;
+  collection_ids: number[];
+  sites: DuplicateSite[];
+}
+
+export interface DuplicateReport {
+  within_collections: DuplicateGroup[];  // One group per collection repeating a URL
+  across_collections: DuplicateGroup[];
+}
+
+// KeepNewestTitle keeps the first position with the title of the last-added occurrence
+export type DedupeStrategy = "KeepFirst" | "KeepNewestTitle";
+
+export interface DedupeResult {
+  collection: CollectionRecord;
+  removed: SiteEntry[];
+}
+
+// Exports of other tab managers that can be imported
+export type ImportFormat = "OneTab" | "SessionBuddy" | "Toby";
+
+// Plain-text formats collections can be exported to, besides a JSON backup
+export type ExportFormat = "Markdown" | "Csv" | "Opml" | "UrlList";
+
+// Result of importing collections from another format
+export interface ImportReport {
+  created: CollectionRecord[];    // Including renamed ones, under their new names
+  skipped_collections: string[];  // Names that were taken, with the Skip strategy
+  skipped_sites: number;          // Entries without an openable URL
+  profiles: BrowserProfile[];     // Browser profiles added, under their final IDs
+  renamed: RenamedCollection[];
+  overwritten: string[];
+  merged: MergedCollection[];
+  dropped_paths: string[];        // Collections whose imported browser path isn't used here
+}
+
+// How a merging import handles a collection whose name is already taken
+export type MergeStrategy =
+  | "Skip"
+  | "Rename"      // Import it as "Name (2)", "Name (3)", ...
+  | "Overwrite"   // Replace the existing one's sites and config
+  | "Union";      // Add the sites the existing one lacks, by canonical URL
+
+export interface RenamedCollection {
+  from: string;
+  to: string;
+}
+
+export interface MergedCollection {
+  name: string;
+  added_sites: number;
+}
+
+// What a merging import did, or would do in a dry run; names are as in the file
+export interface MergeReport {
+  added: string[];
+  skipped: string[];
+  renamed: RenamedCollection[];
+  overwritten: string[];
+  merged: MergedCollection[];
+  dry_run: boolean;
+}
+
+// What happens to an incoming browser profile on import
+export type ProfileImportStatus =
+  | "New"
+  | "Existing"     // An identical profile is already here and is reused
+  | "IdConflict";  // The ID is taken by another profile, so it gets a new one
+
+// An incoming collection, listed before choosing what to import
+export interface ImportPreviewCollection {
+  id: number;                         // ID in the file, used to select it
+  name: string;
+  site_count: number;
+  browser_profile_id: string | null;
+  name_exists: boolean;               // Handled by the merge strategy chosen on import
+}
+
+export interface ImportPreviewProfile {
+  profile: BrowserProfile;
+  status: ProfileImportStatus;
+}
+
+// Contents of an export file, checked against the current database
+export interface ImportPreview {
+  collections: ImportPreviewCollection[];
+  profiles: ImportPreviewProfile[];
+}
+
+// Collections and profiles picked from an import preview, by their IDs in the file
+export interface ImportSelection {
+  collection_ids: number[];
+  profile_ids: string[];
+}
+
+// A browser's saved session file found on disk
+export interface SessionFile {
+  path: string;
+  browser: Browser;
+  profile: string;      // Profile directory name
+  modified_at: string;  // ISO 8601 DateTime string
+}
+
+export interface SessionWindow {
+  tabs: SiteEntry[];
+  selected_tab: number | null;  // Index into `tabs` of the active tab
+}
+
+export interface BrowserSession {
+  browser: Browser;
+  saved_at: string;  // ISO 8601 DateTime string
+  windows: SessionWindow[];
+  skipped_tabs: number;
+}
+
+// What to read from a browser profile
+export type BrowserDataSource = "Bookmarks" | "History";
+
+// Narrows a bookmark or history import; omitted fields match everything
+export interface BrowserDataFilter {
+  folder?: string | null;           // Folder path such as "Bookmarks bar / Work", subfolders included
+  since?: string | null;            // ISO 8601; added (bookmarks) or last visited (history)
+  until?: string | null;
+  domain?: string | null;           // Host, subdomains included
+  min_visit_count?: number | null;
+}
+
+// A detected browser profile and the data that can be imported from it
+export interface BrowserDataProfile {
+  browser: Browser;
+  profile: string;  // Profile directory name
+  path: string;
+  sources: BrowserDataSource[];
+}
+
+export type IntegrityIssueKind =
+  | "DuplicateId"
+  | "MaxIdTooLow"
+  | "WrongRecordCount"
+  | "StaleLastUpdatedId"
+  | "DanglingProfileReference"
+  | "MultipleDefaultProfiles"
+  | "InvalidUrl";
+
+export interface IntegrityIssue {
+  kind: IntegrityIssueKind;
+  message: string;
+  collection_id: number | null;  // Affected collection, if any
+}
+
+export interface RepairReport {
+  fixed: IntegrityIssue[];
+  remaining: IntegrityIssue[];   // Need a manual fix, e.g. unrecognisable URLs
+  backup: BackupInfo | null;     // Taken before anything was changed
+}
+
+export interface JournalEntryInfo {
+  description: string;  // e.g. "Delete collection 'Work'"
+  recorded_at: string; // ISO 8601 DateTime string
+}
+
+export interface JournalStatus {
+  next_undo: JournalEntryInfo | null;
+  next_redo: JournalEntryInfo | null;
+}
+
+export interface RetitledSite {
+  url: string;
+  old_title: string;
+  new_title: string;
+}
+
+export interface RevisionDiff {
+  collection_id: number;
+  from_revision: number;
+  to_revision: number | null;  // null compares against the current collection
+  added: SiteEntry[];
+  removed: SiteEntry[];
+  retitled: RetitledSite[];
 }
 
 export interface CollectionRecord {
@@ -131,6 +415,7 @@ export const DEFAULT_COLLECTION_CONFIG: CollectionConfig = {
   browser: undefined,             // No direct config by default
   mode: undefined,
   custom_path: undefined,
+  url_normalization: undefined,
 };
 
 // Utility functions